@@ -0,0 +1,431 @@
+//! showmarket 命令行客户端：直接调用行情源查询报价、K 线与证券，不经过 HTTP 服务。
+//!
+//! 与服务共用配置文件（上游超时、代理、主备报价源、K 线接口等），不需要服务在运行。
+
+mod table;
+
+use anyhow::{Context, bail};
+use chrono::{DateTime, Local, Utc};
+use clap::{Parser, Subcommand, ValueEnum};
+use showmarket::config::{Config, DEFAULT_CONFIG_FILE};
+use showmarket::models::kline::Kline;
+use showmarket::models::market::parse_symbol;
+use showmarket::models::price::PriceUpdate;
+use showmarket::services::ashare::{AshareService, SUPPORTED_INTERVALS};
+use showmarket::services::export::{Encoder, ExportFormat, ExportRequest};
+use showmarket::services::quotes::QuoteFeed;
+use showmarket::services::securities::SecurityMaster;
+use showmarket::services::upstream::{UpstreamClient, UpstreamLimiter};
+use std::io::{IsTerminal, Write};
+use std::path::PathBuf;
+use std::time::Duration;
+use table::{Align, Cell, Table, Tone};
+
+/// showmarket 命令行客户端
+#[derive(Debug, Parser)]
+#[command(name = "showmarket-cli", version, about)]
+struct Args {
+    /// TOML 配置文件路径（默认读取当前目录下的 showmarket.toml，如存在）
+    #[arg(short, long, env = "SHOWMARKET_CONFIG", global = true)]
+    config: Option<PathBuf>,
+    /// 不输出颜色
+    #[arg(long, global = true)]
+    no_color: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// 查询最新报价，如 `quote 600000.SH 000001.SZ`
+    Quote {
+        /// symbol，可用空格或逗号分隔多个
+        #[arg(required = true, value_delimiter = ',')]
+        symbols: Vec<String>,
+    },
+    /// 查询最近的 K 线（前复权）
+    Klines {
+        symbol: String,
+        /// K 线周期：1m、5m、15m、30m、1h、1d、1w、1M
+        #[arg(long, default_value = "1d")]
+        interval: String,
+        /// 最多多少根，上限 500
+        #[arg(long, default_value_t = 200)]
+        limit: u16,
+        #[arg(long, value_enum, default_value_t = Format::Table)]
+        format: Format,
+    },
+    /// 按代码、拼音首字母或中文名搜索证券
+    Search {
+        query: String,
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+    },
+    /// 持续刷新报价，Ctrl-C 退出，如 `watch 000001.SH,399001.SZ`
+    Watch {
+        #[arg(required = true, value_delimiter = ',')]
+        symbols: Vec<String>,
+        /// 刷新间隔（毫秒）
+        #[arg(long, default_value_t = 2000)]
+        interval_ms: u64,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    Table,
+    Csv,
+    Jsonl,
+}
+
+/// 命令用到的服务，构造方式与 `AppState` 相同：共用一个上游连接池与限流。
+struct Services {
+    ashare: AshareService,
+    quotes: QuoteFeed,
+    securities: SecurityMaster,
+}
+
+impl Services {
+    fn new(config: &Config) -> anyhow::Result<Self> {
+        let mut http = UpstreamClient::new(&config.upstream)?;
+        http.set_limiter(UpstreamLimiter::new(&config.upstream));
+        let ashare = AshareService::with_client(http.clone())
+            .with_kline_url(&config.klines.history_url)
+            .with_list_url(&config.securities.list_url);
+        let quotes = QuoteFeed::new(&config.providers, http);
+        let securities = SecurityMaster::load(config.server.data_dir.join("securities.json"))?;
+        Ok(Self {
+            ashare,
+            quotes,
+            securities,
+        })
+    }
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+    // 日志只写 stderr，默认只显示警告（如主备切换），不干扰表格输出
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| "warn".into()),
+        )
+        .with_writer(std::io::stderr)
+        .init();
+
+    let config = load_config(&args)?;
+    let services = Services::new(&config)?;
+    let color = !args.no_color && std::io::stdout().is_terminal();
+    match args.command {
+        Command::Quote { symbols } => {
+            let symbols = check_symbols(symbols)?;
+            let quotes = fetch_quotes(&services, &symbols).await;
+            let failed = quotes.iter().filter(|(_, q)| q.is_err()).count();
+            print!("{}", render_quotes(&services, &quotes, color).await);
+            if failed == quotes.len() {
+                bail!("no quotes fetched");
+            }
+        }
+        Command::Klines {
+            symbol,
+            interval,
+            limit,
+            format,
+        } => {
+            let symbol = check_symbols(vec![symbol])?.remove(0);
+            if !SUPPORTED_INTERVALS.contains(&interval.as_str()) {
+                bail!(
+                    "unsupported interval {interval}, expected one of {}",
+                    SUPPORTED_INTERVALS.join(", ")
+                );
+            }
+            let bars = services
+                .ashare
+                .fetch_klines(&symbol, &interval, limit)
+                .await?;
+            print_klines(&symbol, &interval, &bars, format, color)?;
+        }
+        Command::Search { query, limit } => {
+            let found = services.securities.search(&query, limit).await;
+            if found.is_empty() {
+                bail!("no securities match {query}");
+            }
+            let mut t = Table::new(&[
+                ("symbol", Align::Left),
+                ("name", Align::Left),
+                ("pinyin", Align::Left),
+                ("kind", Align::Left),
+                ("listed", Align::Left),
+            ]);
+            for s in found {
+                t.push(vec![
+                    s.symbol.into(),
+                    s.name.into(),
+                    s.pinyin.into(),
+                    format!("{:?}", s.kind).to_lowercase().into(),
+                    s.listed.map(|d| d.to_string()).unwrap_or_default().into(),
+                ]);
+            }
+            print!("{}", t.render(color));
+        }
+        Command::Watch {
+            symbols,
+            interval_ms,
+        } => {
+            let symbols = check_symbols(symbols)?;
+            watch(
+                &services,
+                &symbols,
+                Duration::from_millis(interval_ms.max(500)),
+                color,
+            )
+            .await?;
+        }
+    }
+    Ok(())
+}
+
+/// 与服务相同的配置来源，但不校验只与 HTTP 服务有关的项（如 static_dir）。
+fn load_config(args: &Args) -> anyhow::Result<Config> {
+    let mut config = match &args.config {
+        Some(path) => Config::from_file(path)?,
+        None if std::path::Path::new(DEFAULT_CONFIG_FILE).exists() => {
+            Config::from_file(std::path::Path::new(DEFAULT_CONFIG_FILE))?
+        }
+        None => Config::default(),
+    };
+    config.apply_env(std::env::vars())?;
+    Ok(config)
+}
+
+fn check_symbols(symbols: Vec<String>) -> anyhow::Result<Vec<String>> {
+    let symbols: Vec<String> = symbols
+        .into_iter()
+        .map(|s| s.trim().to_uppercase())
+        .filter(|s| !s.is_empty())
+        .collect();
+    if let Some(bad) = symbols.iter().find(|s| parse_symbol(s).is_none()) {
+        bail!("unsupported symbol {bad}, expected e.g. 600000.SH");
+    }
+    Ok(symbols)
+}
+
+async fn fetch_quotes(
+    services: &Services,
+    symbols: &[String],
+) -> Vec<(String, anyhow::Result<PriceUpdate>)> {
+    let fetches = symbols
+        .iter()
+        .map(|s| async move { (s.clone(), services.quotes.fetch_quote(s).await) });
+    futures_util::future::join_all(fetches).await
+}
+
+/// 报价表，取价失败的 symbol 在表格下方列出原因。
+async fn render_quotes(
+    services: &Services,
+    quotes: &[(String, anyhow::Result<PriceUpdate>)],
+    color: bool,
+) -> String {
+    let mut errors = Vec::new();
+    let mut t = Table::new(&[
+        ("symbol", Align::Left),
+        ("name", Align::Left),
+        ("price", Align::Right),
+        ("change", Align::Right),
+        ("change%", Align::Right),
+        ("prev close", Align::Right),
+        ("time", Align::Left),
+    ]);
+    for (symbol, quote) in quotes {
+        let name = services
+            .securities
+            .get(symbol)
+            .await
+            .map(|s| s.name)
+            .unwrap_or_default();
+        let quote = match quote {
+            Ok(q) => q,
+            Err(err) => {
+                t.push(vec![symbol.as_str().into(), name.into(), "-".into()]);
+                errors.push(format!("{symbol}: {err:#}"));
+                continue;
+            }
+        };
+        let (_, market) = parse_symbol(symbol).expect("symbols are checked");
+        let decimals = market.price_decimals() as usize;
+        let change = quote.prev_close.map(|p| quote.price - p);
+        let tone = match change {
+            Some(c) if c > 0.0 => Tone::Up,
+            Some(c) if c < 0.0 => Tone::Down,
+            _ => Tone::Plain,
+        };
+        let ts = quote.source_ts_ms.unwrap_or(quote.ts_ms);
+        let time = DateTime::<Utc>::from_timestamp_millis(ts)
+            .map(|t| {
+                t.with_timezone(&market.timezone())
+                    .format("%m-%d %H:%M:%S")
+                    .to_string()
+            })
+            .unwrap_or_default();
+        t.push(vec![
+            symbol.as_str().into(),
+            name.into(),
+            Cell::new(format!("{:.decimals$}", quote.price)).tone(tone),
+            Cell::new(change.map_or("-".into(), |c| format!("{c:+.decimals$}"))).tone(tone),
+            Cell::new(
+                quote
+                    .change_pct()
+                    .map_or("-".into(), |p| format!("{p:+.2}%")),
+            )
+            .tone(tone),
+            quote
+                .prev_close
+                .map_or("-".into(), |p| format!("{p:.decimals$}"))
+                .into(),
+            Cell::new(if quote.stale {
+                format!("{time} (stale)")
+            } else {
+                time
+            }),
+        ]);
+    }
+    let mut out = t.render(color);
+    for err in errors {
+        out.push_str(&err);
+        out.push('\n');
+    }
+    out
+}
+
+fn print_klines(
+    symbol: &str,
+    interval: &str,
+    bars: &[Kline],
+    format: Format,
+    color: bool,
+) -> anyhow::Result<()> {
+    let (_, market) = parse_symbol(symbol).context("unsupported symbol")?;
+    let tz = market.timezone();
+    let date = |ms: i64| {
+        DateTime::<Utc>::from_timestamp_millis(ms).map(|t| t.with_timezone(&tz).date_naive())
+    };
+    let export = match format {
+        Format::Table => None,
+        Format::Csv => Some(ExportFormat::Csv),
+        Format::Jsonl => Some(ExportFormat::Jsonl),
+    };
+    if let Some(export) = export {
+        // 与 `/api/klines/{symbol}/export` 的列一致
+        let today = Utc::now().with_timezone(&tz).date_naive();
+        let req = ExportRequest {
+            symbol: symbol.to_string(),
+            interval: interval.to_string(),
+            adjust: Default::default(),
+            start: bars
+                .first()
+                .and_then(|b| date(b.open_time))
+                .unwrap_or(today),
+            end: bars.last().and_then(|b| date(b.open_time)).unwrap_or(today),
+        };
+        let mut encoder = Encoder::new(export, &req)?;
+        let mut out = std::io::stdout().lock();
+        out.write_all(&encoder.header()?)?;
+        out.write_all(&encoder.encode(bars)?)?;
+        out.write_all(&encoder.finish()?)?;
+        return Ok(());
+    }
+
+    let intraday = matches!(interval, "1m" | "5m" | "15m" | "30m" | "1h");
+    let decimals = market.price_decimals() as usize;
+    let mut t = Table::new(&[
+        ("time", Align::Left),
+        ("open", Align::Right),
+        ("high", Align::Right),
+        ("low", Align::Right),
+        ("close", Align::Right),
+        ("change%", Align::Right),
+        ("volume", Align::Right),
+        ("amount", Align::Right),
+    ]);
+    for bar in bars {
+        let time = DateTime::<Utc>::from_timestamp_millis(bar.open_time)
+            .map(|t| {
+                let t = t.with_timezone(&tz);
+                if intraday {
+                    t.format("%Y-%m-%d %H:%M").to_string()
+                } else {
+                    t.format("%Y-%m-%d").to_string()
+                }
+            })
+            .unwrap_or_default();
+        let tone = match bar.change_pct {
+            Some(p) if p > 0.0 => Tone::Up,
+            Some(p) if p < 0.0 => Tone::Down,
+            _ => Tone::Plain,
+        };
+        let price = |p: f64| format!("{p:.decimals$}");
+        t.push(vec![
+            time.into(),
+            price(bar.open).into(),
+            price(bar.high).into(),
+            price(bar.low).into(),
+            Cell::new(price(bar.close)).tone(tone),
+            Cell::new(bar.change_pct.map_or("-".into(), |p| format!("{p:+.2}%"))).tone(tone),
+            human(bar.volume).into(),
+            bar.amount.map_or("-".into(), human).into(),
+        ]);
+    }
+    print!("{}", t.render(color));
+    Ok(())
+}
+
+/// 成交量、成交额按万、亿缩写。
+fn human(v: f64) -> String {
+    if v.abs() >= 1e8 {
+        format!("{:.2}亿", v / 1e8)
+    } else if v.abs() >= 1e4 {
+        format!("{:.2}万", v / 1e4)
+    } else {
+        format!("{v:.0}")
+    }
+}
+
+/// 定时刷新报价表，直到 Ctrl-C。
+async fn watch(
+    services: &Services,
+    symbols: &[String],
+    every: Duration,
+    color: bool,
+) -> anyhow::Result<()> {
+    let mut tick = tokio::time::interval(every);
+    tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    // 信号只注册一次，等待间隔和取数时都能响应 Ctrl-C
+    let ctrl_c = tokio::signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+    loop {
+        let table = tokio::select! {
+            table = async {
+                tick.tick().await;
+                let quotes = fetch_quotes(services, symbols).await;
+                render_quotes(services, &quotes, color).await
+            } => table,
+            _ = &mut ctrl_c => return Ok(()),
+        };
+        let mut out = std::io::stdout().lock();
+        if color {
+            // 清屏并回到左上角
+            write!(out, "\x1b[2J\x1b[H")?;
+        }
+        writeln!(
+            out,
+            "{}  every {}ms, Ctrl-C to quit",
+            Local::now().format("%H:%M:%S"),
+            every.as_millis()
+        )?;
+        write!(out, "{table}")?;
+        if !color {
+            writeln!(out)?;
+        }
+        out.flush()?;
+    }
+}
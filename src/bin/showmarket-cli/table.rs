@@ -0,0 +1,136 @@
+//! 终端表格：按显示宽度对齐，中文等全角字符占两列。
+
+use std::fmt::Write as _;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    Right,
+}
+
+/// 单元格文字颜色，只在输出到终端时生效。
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Tone {
+    Plain,
+    /// 上涨（A 股习惯为红色）
+    Up,
+    /// 下跌（绿色）
+    Down,
+    Dim,
+}
+
+pub struct Cell {
+    text: String,
+    tone: Tone,
+}
+
+impl Cell {
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            tone: Tone::Plain,
+        }
+    }
+
+    pub fn tone(mut self, tone: Tone) -> Self {
+        self.tone = tone;
+        self
+    }
+}
+
+impl From<String> for Cell {
+    fn from(text: String) -> Self {
+        Cell::new(text)
+    }
+}
+
+impl From<&str> for Cell {
+    fn from(text: &str) -> Self {
+        Cell::new(text)
+    }
+}
+
+pub struct Table {
+    headers: Vec<&'static str>,
+    aligns: Vec<Align>,
+    rows: Vec<Vec<Cell>>,
+}
+
+impl Table {
+    /// `columns` 为 (标题, 对齐方式)。
+    pub fn new(columns: &[(&'static str, Align)]) -> Self {
+        Self {
+            headers: columns.iter().map(|(h, _)| *h).collect(),
+            aligns: columns.iter().map(|(_, a)| *a).collect(),
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, row: Vec<Cell>) {
+        self.rows.push(row);
+    }
+
+    pub fn render(&self, color: bool) -> String {
+        let mut widths: Vec<usize> = self.headers.iter().map(|h| width(h)).collect();
+        for row in &self.rows {
+            for (w, cell) in widths.iter_mut().zip(row) {
+                *w = (*w).max(width(&cell.text));
+            }
+        }
+
+        let mut out = String::new();
+        let line = |out: &mut String, cells: &mut dyn Iterator<Item = (&str, Tone)>| {
+            let mut parts = Vec::new();
+            for (i, (text, tone)) in cells.enumerate() {
+                let pad = " ".repeat(widths[i] - width(text));
+                let text = paint(text, tone, color);
+                parts.push(match self.aligns[i] {
+                    Align::Left => format!("{text}{pad}"),
+                    Align::Right => format!("{pad}{text}"),
+                });
+            }
+            let _ = writeln!(out, "{}", parts.join("  ").trim_end());
+        };
+        line(&mut out, &mut self.headers.iter().map(|h| (*h, Tone::Dim)));
+        let rule: Vec<String> = widths.iter().map(|w| "─".repeat(*w)).collect();
+        let _ = writeln!(out, "{}", paint(&rule.join("  "), Tone::Dim, color));
+        for row in &self.rows {
+            line(&mut out, &mut row.iter().map(|c| (c.text.as_str(), c.tone)));
+        }
+        out
+    }
+}
+
+fn paint(text: &str, tone: Tone, color: bool) -> String {
+    let code = match tone {
+        _ if !color => return text.to_string(),
+        Tone::Plain => return text.to_string(),
+        Tone::Up => "31",
+        Tone::Down => "32",
+        Tone::Dim => "2",
+    };
+    format!("\x1b[{code}m{text}\x1b[0m")
+}
+
+/// 终端显示宽度，东亚宽字符与全角符号按两列计。
+pub fn width(s: &str) -> usize {
+    s.chars().map(|c| if is_wide(c) { 2 } else { 1 }).sum()
+}
+
+fn is_wide(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x1100..=0x115F
+            | 0x2E80..=0x303E
+            | 0x3041..=0x33FF
+            | 0x3400..=0x4DBF
+            | 0x4E00..=0x9FFF
+            | 0xA000..=0xA4CF
+            | 0xAC00..=0xD7A3
+            | 0xF900..=0xFAFF
+            | 0xFE30..=0xFE4F
+            | 0xFF00..=0xFF60
+            | 0xFFE0..=0xFFE6
+            | 0x20000..=0x3FFFD
+    )
+}
@@ -0,0 +1,810 @@
+//! 服务配置。
+//!
+//! 按以下顺序逐层覆盖：内置默认值 → TOML 配置文件 → `SHOWMARKET_*` 环境变量 → 命令行参数。
+
+use crate::models::kline::Adjust;
+use crate::models::market::{Market, parse_symbol};
+use anyhow::{Context, anyhow, bail};
+use chrono::NaiveDate;
+use clap::{Parser, Subcommand};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use utoipa::ToSchema;
+
+/// 未指定 `--config` 时，若当前目录存在该文件则自动加载。
+pub const DEFAULT_CONFIG_FILE: &str = "showmarket.toml";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub log: LogConfig,
+    pub poller: PollerConfig,
+    pub upstream: UpstreamConfig,
+    pub providers: ProvidersConfig,
+    pub klines: KlinesConfig,
+    pub backfill: BackfillConfig,
+    pub stream: StreamConfig,
+    pub securities: SecuritiesConfig,
+    pub calendar: CalendarConfig,
+    pub webhook: WebhookSettings,
+    pub auth: AuthConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub bind: SocketAddr,
+    /// `/static` 对应的目录
+    pub static_dir: PathBuf,
+    /// 告警、自选列表等运行时数据目录
+    pub data_dir: PathBuf,
+    /// 收到 SIGINT/SIGTERM 后，等待连接与后台任务收尾的最长时间
+    pub shutdown_timeout_ms: u64,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            bind: SocketAddr::from(([127, 0, 0, 1], 3000)),
+            static_dir: PathBuf::from("static"),
+            data_dir: PathBuf::from("data"),
+            shutdown_timeout_ms: 10_000,
+        }
+    }
+}
+
+impl ServerConfig {
+    pub fn shutdown_timeout(&self) -> Duration {
+        Duration::from_millis(self.shutdown_timeout_ms)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    /// tracing `EnvFilter` 语法
+    pub filter: String,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            filter: "showmarket=info,tower_http=info,axum=info".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PollerConfig {
+    pub interval_ms: u64,
+    /// 最近一次报价在此时间内才算新鲜，`/ready` 据此判断
+    pub fresh_within_ms: u64,
+    /// 交易时段内报价超过该时间没有前进即标记为 stale
+    pub stale_after_ms: u64,
+    /// 除自选列表外始终轮询的 symbol
+    pub symbols: Vec<String>,
+}
+
+impl Default for PollerConfig {
+    fn default() -> Self {
+        Self {
+            interval_ms: 800,
+            fresh_within_ms: 30_000,
+            stale_after_ms: 60_000,
+            symbols: Vec::new(),
+        }
+    }
+}
+
+impl PollerConfig {
+    pub fn interval(&self) -> Duration {
+        Duration::from_millis(self.interval_ms)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UpstreamConfig {
+    pub timeout_ms: u64,
+    pub user_agent: String,
+    /// 每个上游 host 每秒最多请求数，0 表示不限流
+    pub rate_limit_per_sec: f64,
+    /// 令牌桶容量，允许的瞬时突发
+    pub rate_limit_burst: u32,
+    /// 令牌不足时最多排队等待多久，超过直接返回 upstream unavailable
+    pub rate_limit_max_wait_ms: u64,
+    /// 同一 host 连续失败多少次后熔断
+    pub breaker_failure_threshold: u32,
+    /// 熔断持续时间，之后放一个请求试探
+    pub breaker_open_ms: u64,
+    /// 每个 host 保留的空闲连接数上限
+    pub pool_max_idle_per_host: usize,
+    /// 空闲连接保留多久，0 表示不回收
+    pub pool_idle_timeout_ms: u64,
+    /// TCP keep-alive 间隔，0 表示关闭
+    pub tcp_keepalive_ms: u64,
+    /// 允许通过 ALPN 协商 HTTP/2；关闭则只用 HTTP/1.1
+    pub http2: bool,
+    /// 所有上游请求经过的 HTTP(S) 代理，如 `http://10.0.0.1:3128`
+    pub proxy: Option<String>,
+}
+
+impl Default for UpstreamConfig {
+    fn default() -> Self {
+        Self {
+            timeout_ms: 5000,
+            user_agent: "showmarket-ashare/0.1".to_string(),
+            rate_limit_per_sec: 20.0,
+            rate_limit_burst: 40,
+            rate_limit_max_wait_ms: 2000,
+            breaker_failure_threshold: 5,
+            breaker_open_ms: 30_000,
+            pool_max_idle_per_host: 8,
+            pool_idle_timeout_ms: 90_000,
+            tcp_keepalive_ms: 60_000,
+            http2: true,
+            proxy: None,
+        }
+    }
+}
+
+impl UpstreamConfig {
+    pub fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms)
+    }
+}
+
+/// 实时报价来源。
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ProviderKind {
+    /// 东方财富 push2
+    Eastmoney,
+    /// 腾讯 qt.gtimg.cn
+    Tencent,
+}
+
+impl ProviderKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            ProviderKind::Eastmoney => "eastmoney",
+            ProviderKind::Tencent => "tencent",
+        }
+    }
+}
+
+impl std::str::FromStr for ProviderKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "eastmoney" => Ok(ProviderKind::Eastmoney),
+            "tencent" => Ok(ProviderKind::Tencent),
+            other => Err(format!("unknown provider {other}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProvidersConfig {
+    pub primary: ProviderKind,
+    /// 主源超时或出错时切换到的备用源，设为 `"none"` 则不做故障切换
+    #[serde(
+        serialize_with = "serialize_backup",
+        deserialize_with = "deserialize_backup"
+    )]
+    pub backup: Option<ProviderKind>,
+    /// 使用备用源期间，每隔多久试探一次主源，成功即切回
+    pub failback_interval_ms: u64,
+    /// 主备报价对账间隔，0 表示不对账
+    pub reconcile_interval_secs: u64,
+    /// 主备价格相差超过该百分比即告警
+    pub reconcile_tolerance_pct: f64,
+    /// 东方财富实时行情接口，可替换为自建镜像
+    pub eastmoney_url: String,
+    /// 腾讯行情接口，代码直接拼在后面
+    pub tencent_url: String,
+}
+
+impl Default for ProvidersConfig {
+    fn default() -> Self {
+        Self {
+            primary: ProviderKind::Eastmoney,
+            backup: Some(ProviderKind::Tencent),
+            failback_interval_ms: 30_000,
+            reconcile_interval_secs: 300,
+            reconcile_tolerance_pct: 0.5,
+            eastmoney_url: "https://push2.eastmoney.com/api/qt/stock/get".to_string(),
+            tencent_url: "https://qt.gtimg.cn/q=".to_string(),
+        }
+    }
+}
+
+fn serialize_backup<S>(backup: &Option<ProviderKind>, ser: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    ser.serialize_str(backup.map_or("none", ProviderKind::name))
+}
+
+fn deserialize_backup<'de, D>(de: D) -> Result<Option<ProviderKind>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    match String::deserialize(de)?.as_str() {
+        "none" => Ok(None),
+        other => other.parse().map(Some).map_err(serde::de::Error::custom),
+    }
+}
+
+impl ProvidersConfig {
+    pub fn failback_interval(&self) -> Duration {
+        Duration::from_millis(self.failback_interval_ms)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KlinesConfig {
+    /// K 线缓存有效期，0 表示不缓存
+    pub cache_ttl_ms: u64,
+    /// 最多缓存多少个 (symbol, interval)
+    pub cache_capacity: usize,
+    /// 东方财富历史 K 线接口，可替换为自建镜像
+    pub history_url: String,
+}
+
+impl Default for KlinesConfig {
+    fn default() -> Self {
+        Self {
+            cache_ttl_ms: 30_000,
+            cache_capacity: 256,
+            history_url: "https://push2his.eastmoney.com/api/qt/stock/kline/get".to_string(),
+        }
+    }
+}
+
+impl KlinesConfig {
+    pub fn cache_ttl(&self) -> Duration {
+        Duration::from_millis(self.cache_ttl_ms)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BackfillConfig {
+    /// 同一窗口连续失败多少次后放弃该 (symbol, interval)，本地限流与熔断不计入
+    pub max_retries: u32,
+    /// 失败后等待多久重试
+    pub retry_delay_ms: u64,
+}
+
+impl Default for BackfillConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: 5,
+            retry_delay_ms: 5_000,
+        }
+    }
+}
+
+impl BackfillConfig {
+    pub fn retry_delay(&self) -> Duration {
+        Duration::from_millis(self.retry_delay_ms)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StreamConfig {
+    /// 最近推送的事件保留多少条，供 SSE 断线续传
+    pub replay_capacity: usize,
+    /// SSE 无事件时发送注释行保活的间隔
+    pub sse_keepalive_ms: u64,
+    /// 服务端向 WebSocket 客户端发送 ping 的间隔
+    pub ws_ping_interval_ms: u64,
+    /// 这么久没有收到客户端任何消息（含 pong）即断开
+    pub ws_idle_timeout_ms: u64,
+    /// 每个连接待发送消息的上限，堆满后按 symbol 合并，只保留最新值
+    pub ws_send_queue: usize,
+    /// 客户端持续落后（消息在合并）超过这么久即断开，0 表示只合并不断开
+    pub ws_max_behind_ms: u64,
+}
+
+impl Default for StreamConfig {
+    fn default() -> Self {
+        Self {
+            replay_capacity: 1024,
+            sse_keepalive_ms: 15_000,
+            ws_ping_interval_ms: 20_000,
+            ws_idle_timeout_ms: 60_000,
+            ws_send_queue: 256,
+            ws_max_behind_ms: 30_000,
+        }
+    }
+}
+
+impl StreamConfig {
+    pub fn sse_keepalive(&self) -> Duration {
+        Duration::from_millis(self.sse_keepalive_ms)
+    }
+
+    pub fn ws_ping_interval(&self) -> Duration {
+        Duration::from_millis(self.ws_ping_interval_ms)
+    }
+
+    pub fn ws_idle_timeout(&self) -> Duration {
+        Duration::from_millis(self.ws_idle_timeout_ms)
+    }
+
+    pub fn ws_max_behind(&self) -> Option<Duration> {
+        (self.ws_max_behind_ms > 0).then(|| Duration::from_millis(self.ws_max_behind_ms))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SecuritiesConfig {
+    /// 从行情源刷新证券列表的间隔
+    pub refresh_interval_secs: u64,
+    /// 东方财富证券列表接口，可替换为自建镜像
+    pub list_url: String,
+    /// 刷新得到的证券数少于当前数量的这个比例时不替换（视为上游数据不全）
+    pub min_refresh_ratio: f64,
+}
+
+impl Default for SecuritiesConfig {
+    fn default() -> Self {
+        Self {
+            refresh_interval_secs: 24 * 60 * 60,
+            list_url: "https://push2.eastmoney.com/api/qt/clist/get".to_string(),
+            min_refresh_ratio: 0.9,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CalendarConfig {
+    /// 各市场的休市日（交易所当地日期），如 `SH = ["2025-10-01"]`；休市日不判定报价变旧
+    pub holidays: BTreeMap<Market, Vec<NaiveDate>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WebhookSettings {
+    /// 为空时不启用 webhook
+    pub urls: Vec<String>,
+    pub secret: Option<String>,
+    pub max_retries: u32,
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+    pub timeout_ms: u64,
+}
+
+impl Default for WebhookSettings {
+    fn default() -> Self {
+        Self {
+            urls: Vec::new(),
+            secret: None,
+            max_retries: 5,
+            initial_backoff_ms: 500,
+            max_backoff_ms: 30_000,
+            timeout_ms: 5000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthConfig {
+    /// 为空时不鉴权，所有接口开放（只适合本机使用）
+    pub keys: Vec<ApiKeyConfig>,
+}
+
+/// 一个 API key，写在 `[[auth.keys]]` 中。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ApiKeyConfig {
+    /// 出现在审计日志里，不参与鉴权
+    pub name: String,
+    pub key: String,
+    pub scopes: Vec<Scope>,
+    /// 每秒请求数，缺省不限
+    #[serde(default)]
+    pub rate_limit_per_sec: Option<f64>,
+    /// 令牌桶容量，缺省为每秒请求数（至少 1）
+    #[serde(default)]
+    pub rate_limit_burst: Option<u32>,
+    /// 每天（UTC）最多请求数，缺省不限
+    #[serde(default)]
+    pub daily_quota: Option<u64>,
+}
+
+/// API key 的权限范围；`admin` 包含其余所有权限。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    /// 实时报价推送、证券搜索，以及读取告警与自选列表
+    Quotes,
+    /// K 线
+    Klines,
+    /// 创建/删除告警、修改自选列表
+    Write,
+    /// 运行状态、指标与管理接口
+    Admin,
+}
+
+impl Scope {
+    pub fn name(self) -> &'static str {
+        match self {
+            Scope::Quotes => "quotes",
+            Scope::Klines => "klines",
+            Scope::Write => "write",
+            Scope::Admin => "admin",
+        }
+    }
+}
+
+/// showmarket 行情服务
+#[derive(Debug, Default, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// TOML 配置文件路径（默认读取当前目录下的 showmarket.toml，如存在）
+    #[arg(short, long, env = "SHOWMARKET_CONFIG")]
+    pub config: Option<PathBuf>,
+    /// 监听地址，如 0.0.0.0:3000
+    #[arg(long)]
+    pub bind: Option<SocketAddr>,
+    /// 静态资源目录
+    #[arg(long)]
+    pub static_dir: Option<PathBuf>,
+    /// 运行时数据目录
+    #[arg(long)]
+    pub data_dir: Option<PathBuf>,
+    /// 日志过滤规则（tracing EnvFilter 语法）
+    #[arg(long)]
+    pub log_filter: Option<String>,
+    /// 实时价格轮询间隔（毫秒）
+    #[arg(long)]
+    pub poll_interval_ms: Option<u64>,
+    /// 额外轮询的 symbol，逗号分隔
+    #[arg(long, value_delimiter = ',')]
+    pub symbols: Option<Vec<String>>,
+    /// 打印合并后的配置并退出
+    #[arg(long)]
+    pub print_config: bool,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// 下载历史 K 线到 data_dir/history，前台执行到完成；不带 --symbols 时续传未完成的任务
+    Backfill(BackfillArgs),
+}
+
+#[derive(Debug, clap::Args)]
+pub struct BackfillArgs {
+    /// 要下载的 symbol，逗号分隔
+    #[arg(long, value_delimiter = ',')]
+    pub symbols: Vec<String>,
+    /// K 线周期，逗号分隔
+    #[arg(long, value_delimiter = ',', default_value = "1d")]
+    pub intervals: Vec<String>,
+    /// 复权方式：none、forward、backward
+    #[arg(long, default_value = "forward")]
+    pub adjust: Adjust,
+    /// 起始日期 YYYY-MM-DD，缺省为上市日期
+    #[arg(long)]
+    pub start: Option<NaiveDate>,
+    /// 结束日期 YYYY-MM-DD，缺省为今天
+    #[arg(long)]
+    pub end: Option<NaiveDate>,
+}
+
+impl Config {
+    /// 按 默认值 → 文件 → 环境变量 → 命令行 的顺序合并并校验。
+    pub fn load(cli: &Cli) -> anyhow::Result<Self> {
+        Self::load_with_env(cli, std::env::vars())
+    }
+
+    /// 同 [`Config::load`]，环境变量由调用方给出。
+    pub fn load_with_env(
+        cli: &Cli,
+        vars: impl IntoIterator<Item = (String, String)>,
+    ) -> anyhow::Result<Self> {
+        let mut cfg = match &cli.config {
+            Some(path) => Self::from_file(path)?,
+            None if Path::new(DEFAULT_CONFIG_FILE).exists() => {
+                Self::from_file(Path::new(DEFAULT_CONFIG_FILE))?
+            }
+            None => Self::default(),
+        };
+        cfg.apply_env(vars)?;
+        cfg.apply_cli(cli);
+        cfg.validate()?;
+        Ok(cfg)
+    }
+
+    pub fn from_file(path: &Path) -> anyhow::Result<Self> {
+        let body = std::fs::read_to_string(path)
+            .with_context(|| format!("read config file {} failed", path.display()))?;
+        toml::from_str(&body)
+            .with_context(|| format!("parse config file {} failed", path.display()))
+    }
+
+    /// 应用 `SHOWMARKET_<SECTION>_<KEY>` 形式的环境变量，对应配置文件 `[section]` 下的 `key`，
+    /// 如 `SHOWMARKET_UPSTREAM_TIMEOUT_MS` 对应 `upstream.timeout_ms`。
+    ///
+    /// 值按该项的类型解析，列表用逗号分隔；空字符串表示恢复默认值（可选项即不配置）。
+    /// 不对应任何配置项的变量报错，`SHOWMARKET_CONFIG` 除外；`auth.keys` 只能写在配置文件里。
+    pub fn apply_env(
+        &mut self,
+        vars: impl IntoIterator<Item = (String, String)>,
+    ) -> anyhow::Result<()> {
+        let mut vars: Vec<(String, String)> = vars
+            .into_iter()
+            .filter(|(name, _)| name.starts_with(ENV_PREFIX) && name != "SHOWMARKET_CONFIG")
+            .collect();
+        vars.sort();
+
+        for (name, value) in vars {
+            let path = name[ENV_PREFIX.len()..].to_ascii_lowercase();
+            let (section, key) = path
+                .split_once('_')
+                .ok_or_else(|| anyhow!("{name}: expected SHOWMARKET_<SECTION>_<KEY>"))?;
+            if section == "auth" {
+                bail!("{name}: auth settings can only be set in the config file");
+            }
+            let mut root = toml::Value::try_from(&*self)?;
+            let table = root
+                .get_mut(section)
+                .and_then(toml::Value::as_table_mut)
+                .ok_or_else(|| anyhow!("{name}: unknown config section {section}"))?;
+            match env_value(table.get(key), &value)
+                .map_err(|e| anyhow!("invalid value for {name}: {e}"))?
+            {
+                Some(v) => table.insert(key.to_string(), v),
+                None => table.remove(key),
+            };
+            *self = root
+                .try_into()
+                .map_err(|e| anyhow!("invalid value for {name}: {e}"))?;
+        }
+        Ok(())
+    }
+
+    pub fn apply_cli(&mut self, cli: &Cli) {
+        if let Some(v) = cli.bind {
+            self.server.bind = v;
+        }
+        if let Some(v) = &cli.static_dir {
+            self.server.static_dir = v.clone();
+        }
+        if let Some(v) = &cli.data_dir {
+            self.server.data_dir = v.clone();
+        }
+        if let Some(v) = &cli.log_filter {
+            self.log.filter = v.clone();
+        }
+        if let Some(v) = cli.poll_interval_ms {
+            self.poller.interval_ms = v;
+        }
+        if let Some(v) = &cli.symbols {
+            self.poller.symbols = v.clone();
+        }
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if !self.server.static_dir.is_dir() {
+            bail!(
+                "server.static_dir {} is not a directory",
+                self.server.static_dir.display()
+            );
+        }
+        if self.server.shutdown_timeout_ms == 0 {
+            bail!("server.shutdown_timeout_ms must be positive");
+        }
+        if self.poller.interval_ms < 100 {
+            bail!("poller.interval_ms must be at least 100");
+        }
+        if self.poller.fresh_within_ms < self.poller.interval_ms {
+            bail!("poller.fresh_within_ms must not be shorter than poller.interval_ms");
+        }
+        if self.poller.stale_after_ms < self.poller.interval_ms {
+            bail!("poller.stale_after_ms must not be shorter than poller.interval_ms");
+        }
+        if let Some(bad) = self
+            .poller
+            .symbols
+            .iter()
+            .find(|s| parse_symbol(s).is_none())
+        {
+            bail!("poller.symbols: unsupported symbol {bad}");
+        }
+        if self.upstream.timeout_ms == 0 {
+            bail!("upstream.timeout_ms must be positive");
+        }
+        if self.upstream.user_agent.trim().is_empty() {
+            bail!("upstream.user_agent must not be empty");
+        }
+        if !self.upstream.rate_limit_per_sec.is_finite() || self.upstream.rate_limit_per_sec < 0.0 {
+            bail!("upstream.rate_limit_per_sec must be zero or positive");
+        }
+        if self.upstream.rate_limit_burst == 0 {
+            bail!("upstream.rate_limit_burst must be positive");
+        }
+        if self.upstream.breaker_failure_threshold == 0 {
+            bail!("upstream.breaker_failure_threshold must be positive");
+        }
+        if let Some(proxy) = &self.upstream.proxy {
+            reqwest::Proxy::all(proxy)
+                .with_context(|| format!("upstream.proxy: invalid proxy URL {proxy}"))?;
+        }
+        if self.providers.backup == Some(self.providers.primary) {
+            bail!("providers.backup must differ from providers.primary");
+        }
+        if self.providers.failback_interval_ms == 0 {
+            bail!("providers.failback_interval_ms must be positive");
+        }
+        if self.providers.reconcile_tolerance_pct <= 0.0
+            || self.providers.reconcile_tolerance_pct.is_nan()
+        {
+            bail!("providers.reconcile_tolerance_pct must be positive");
+        }
+        for (name, url) in [
+            ("providers.eastmoney_url", &self.providers.eastmoney_url),
+            ("providers.tencent_url", &self.providers.tencent_url),
+        ] {
+            if !(url.starts_with("http://") || url.starts_with("https://")) {
+                bail!("{name}: {url} is not an http(s) URL");
+            }
+        }
+        if self.stream.replay_capacity == 0 {
+            bail!("stream.replay_capacity must be positive");
+        }
+        if self.stream.sse_keepalive_ms < 1000 {
+            bail!("stream.sse_keepalive_ms must be at least 1000");
+        }
+        if self.stream.ws_ping_interval_ms < 100 {
+            bail!("stream.ws_ping_interval_ms must be at least 100");
+        }
+        if self.stream.ws_idle_timeout_ms <= self.stream.ws_ping_interval_ms {
+            bail!("stream.ws_idle_timeout_ms must be longer than stream.ws_ping_interval_ms");
+        }
+        if self.stream.ws_send_queue == 0 {
+            bail!("stream.ws_send_queue must be positive");
+        }
+        let history_url = &self.klines.history_url;
+        if !(history_url.starts_with("http://") || history_url.starts_with("https://")) {
+            bail!("klines.history_url: {history_url} is not an http(s) URL");
+        }
+        if self.backfill.retry_delay_ms == 0 {
+            bail!("backfill.retry_delay_ms must be positive");
+        }
+        if self.securities.refresh_interval_secs < 60 {
+            bail!("securities.refresh_interval_secs must be at least 60");
+        }
+        let list_url = &self.securities.list_url;
+        if !(list_url.starts_with("http://") || list_url.starts_with("https://")) {
+            bail!("securities.list_url: {list_url} is not an http(s) URL");
+        }
+        if !(0.0..=1.0).contains(&self.securities.min_refresh_ratio) {
+            bail!("securities.min_refresh_ratio must be between 0 and 1");
+        }
+        if let Some(bad) = self
+            .webhook
+            .urls
+            .iter()
+            .find(|u| !(u.starts_with("http://") || u.starts_with("https://")))
+        {
+            bail!("webhook.urls: {bad} is not an http(s) URL");
+        }
+        if self.webhook.timeout_ms == 0 {
+            bail!("webhook.timeout_ms must be positive");
+        }
+        let mut names = std::collections::HashSet::new();
+        let mut secrets = std::collections::HashSet::new();
+        for key in &self.auth.keys {
+            if key.name.trim().is_empty() {
+                bail!("auth.keys: name must not be empty");
+            }
+            if !names.insert(key.name.as_str()) {
+                bail!("auth.keys: duplicate name {}", key.name);
+            }
+            if key.key.len() < 16 {
+                bail!("auth.keys.{}: key must be at least 16 characters", key.name);
+            }
+            if !secrets.insert(key.key.as_str()) {
+                bail!(
+                    "auth.keys.{}: key is already used by another entry",
+                    key.name
+                );
+            }
+            if key.scopes.is_empty() {
+                bail!("auth.keys.{}: scopes must not be empty", key.name);
+            }
+            if key
+                .rate_limit_per_sec
+                .is_some_and(|r| r <= 0.0 || !r.is_finite())
+            {
+                bail!(
+                    "auth.keys.{}: rate_limit_per_sec must be positive",
+                    key.name
+                );
+            }
+            if key.rate_limit_burst == Some(0) {
+                bail!("auth.keys.{}: rate_limit_burst must be positive", key.name);
+            }
+            if key.daily_quota == Some(0) {
+                bail!("auth.keys.{}: daily_quota must be positive", key.name);
+            }
+        }
+        Ok(())
+    }
+
+    /// 序列化为 TOML，密钥类字段与代理 URL 中的用户名密码打码。
+    pub fn to_toml_redacted(&self) -> anyhow::Result<String> {
+        let mut cfg = self.clone();
+        if let Some(proxy) = &mut cfg.upstream.proxy {
+            *proxy = redact_url(proxy);
+        }
+        if cfg.webhook.secret.is_some() {
+            cfg.webhook.secret = Some("***".to_string());
+        }
+        for key in &mut cfg.auth.keys {
+            key.key = "***".to_string();
+        }
+        Ok(toml::to_string_pretty(&cfg)?)
+    }
+}
+
+const ENV_PREFIX: &str = "SHOWMARKET_";
+
+/// 按配置项当前值的类型解析环境变量；返回 `None` 表示删除该项、回到默认值。
+fn env_value(current: Option<&toml::Value>, raw: &str) -> Result<Option<toml::Value>, String> {
+    use toml::Value;
+
+    let raw = raw.trim();
+    if raw.is_empty() {
+        return Ok(None);
+    }
+    let value = match current {
+        Some(Value::Integer(_)) => Value::Integer(raw.parse().map_err(|e| format!("{e}"))?),
+        Some(Value::Float(_)) => Value::Float(raw.parse().map_err(|e| format!("{e}"))?),
+        Some(Value::Boolean(_)) => Value::Boolean(raw.parse().map_err(|e| format!("{e}"))?),
+        Some(Value::Array(items)) if items.iter().all(Value::is_str) => Value::Array(
+            raw.split(',')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(|s| Value::String(s.to_string()))
+                .collect(),
+        ),
+        Some(Value::Array(_) | Value::Table(_)) => {
+            return Err("can only be set in the config file".to_string());
+        }
+        // 字符串以及当前未配置的可选项
+        _ => Value::String(raw.to_string()),
+    };
+    Ok(Some(value))
+}
+
+/// URL 中的用户名与密码替换为 `***`，无法解析时整体打码。
+fn redact_url(raw: &str) -> String {
+    let Ok(mut url) = reqwest::Url::parse(raw) else {
+        return "***".to_string();
+    };
+    if url.username().is_empty() && url.password().is_none() {
+        return raw.to_string();
+    }
+    let _ = url.set_username("***");
+    let _ = url.set_password(Some("***"));
+    url.to_string()
+}
@@ -0,0 +1,242 @@
+//! API 错误类型与请求 ID。
+//!
+//! 所有 `/api/*` 错误都以 `{"code","message","request_id"}` 的 JSON 返回，
+//! `request_id` 同时出现在响应头 `x-request-id` 中，便于和日志对照。
+
+use axum::{
+    Json,
+    extract::Request,
+    http::{HeaderValue, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use std::fmt;
+use utoipa::ToSchema;
+
+use crate::services::auth::AuthError;
+use crate::services::backfill::PlanError;
+use crate::services::upstream::UpstreamUnavailable;
+use crate::services::watchlists::WatchlistError;
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+#[derive(Debug)]
+pub enum ApiError {
+    /// 请求参数不合法
+    BadRequest(String),
+    /// symbol 格式不对或市场不支持
+    InvalidSymbol(String),
+    /// 不支持的 K 线周期
+    InvalidInterval(String),
+    /// symbol 格式正确但证券列表中没有
+    UnknownSecurity(String),
+    NotFound(String),
+    Conflict(String),
+    /// 缺少或无效的 API key
+    Unauthorized(AuthError),
+    /// API key 没有所需权限
+    Forbidden(AuthError),
+    /// API key 超过限流或当天配额
+    TooManyRequests(AuthError),
+    /// 行情源返回错误或数据无法解析
+    Upstream(anyhow::Error),
+    /// 行情源超时
+    UpstreamTimeout(anyhow::Error),
+    /// 本地限流或熔断打开，没有发出请求
+    UpstreamUnavailable(UpstreamUnavailable),
+    Internal(anyhow::Error),
+}
+
+/// 当前请求的 ID，在 `request_id` 中间件之外调用时为 `None`。
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(Clone::clone).ok()
+}
+
+/// 错误响应体。
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ErrorBody {
+    /// 机器可读的错误码，如 `invalid_symbol`、`upstream_timeout`，见 README
+    pub code: &'static str,
+    /// 给人看的说明，内容可能变化，不要据此判断
+    pub message: String,
+    /// 与响应头 `x-request-id` 相同
+    pub request_id: Option<String>,
+}
+
+impl ApiError {
+    /// 按错误链区分本地限流/熔断、超时和其它上游错误。
+    pub fn upstream(err: anyhow::Error) -> Self {
+        if let Some(unavailable) = err.downcast_ref::<UpstreamUnavailable>() {
+            return ApiError::UpstreamUnavailable(unavailable.clone());
+        }
+        let timed_out = err
+            .chain()
+            .filter_map(|e| e.downcast_ref::<reqwest::Error>())
+            .any(reqwest::Error::is_timeout);
+        if timed_out {
+            ApiError::UpstreamTimeout(err)
+        } else {
+            ApiError::Upstream(err)
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ApiError::BadRequest(_) | ApiError::InvalidSymbol(_) | ApiError::InvalidInterval(_) => {
+                StatusCode::BAD_REQUEST
+            }
+            ApiError::UnknownSecurity(_) | ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::Conflict(_) => StatusCode::CONFLICT,
+            ApiError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            ApiError::Forbidden(_) => StatusCode::FORBIDDEN,
+            ApiError::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
+            ApiError::Upstream(_) => StatusCode::BAD_GATEWAY,
+            ApiError::UpstreamTimeout(_) => StatusCode::GATEWAY_TIMEOUT,
+            ApiError::UpstreamUnavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            ApiError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::BadRequest(_) => "bad_request",
+            ApiError::InvalidSymbol(_) => "invalid_symbol",
+            ApiError::InvalidInterval(_) => "invalid_interval",
+            ApiError::UnknownSecurity(_) => "unknown_security",
+            ApiError::NotFound(_) => "not_found",
+            ApiError::Conflict(_) => "conflict",
+            ApiError::Unauthorized(_) => "unauthorized",
+            ApiError::Forbidden(_) => "forbidden",
+            ApiError::TooManyRequests(AuthError::QuotaExceeded { .. }) => "quota_exceeded",
+            ApiError::TooManyRequests(_) => "rate_limited",
+            ApiError::Upstream(_) => "upstream_error",
+            ApiError::UpstreamTimeout(_) => "upstream_timeout",
+            ApiError::UpstreamUnavailable(_) => "upstream_unavailable",
+            ApiError::Internal(_) => "internal_error",
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::BadRequest(msg) | ApiError::NotFound(msg) | ApiError::Conflict(msg) => {
+                f.write_str(msg)
+            }
+            ApiError::InvalidSymbol(s) => write!(f, "invalid symbol {s}"),
+            ApiError::InvalidInterval(i) => write!(f, "unsupported interval {i}"),
+            ApiError::UnknownSecurity(s) => write!(f, "unknown symbol {s}"),
+            ApiError::Unauthorized(err)
+            | ApiError::Forbidden(err)
+            | ApiError::TooManyRequests(err) => {
+                write!(f, "{err}")
+            }
+            // 上游和内部错误的细节只进日志
+            ApiError::Upstream(_) => f.write_str("market data provider error"),
+            ApiError::UpstreamTimeout(_) => f.write_str("market data provider timed out"),
+            ApiError::UpstreamUnavailable(err) => {
+                write!(f, "market data provider unavailable: {err}")
+            }
+            ApiError::Internal(_) => f.write_str("internal error"),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let request_id = current_request_id();
+        match &self {
+            ApiError::Upstream(err) | ApiError::UpstreamTimeout(err) => {
+                tracing::warn!(error = %format!("{err:#}"), request_id, "upstream request failed");
+            }
+            ApiError::Internal(err) => {
+                tracing::error!(error = %format!("{err:#}"), request_id, "internal error");
+            }
+            _ => {}
+        }
+        let retry_after = match &self {
+            ApiError::UpstreamUnavailable(err) => err.retry_after(),
+            ApiError::TooManyRequests(
+                AuthError::RateLimited { retry_after, .. }
+                | AuthError::QuotaExceeded { retry_after, .. },
+            ) => Some(*retry_after),
+            _ => None,
+        };
+        let unauthorized = matches!(self, ApiError::Unauthorized(_));
+        let body = ErrorBody {
+            code: self.code(),
+            message: self.to_string(),
+            request_id,
+        };
+        let mut resp = (self.status(), Json(body)).into_response();
+        if let Some(after) = retry_after {
+            resp.headers_mut().insert(
+                header::RETRY_AFTER,
+                HeaderValue::from(after.as_secs().max(1)),
+            );
+        }
+        if unauthorized {
+            resp.headers_mut()
+                .insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+        }
+        resp
+    }
+}
+
+impl From<AuthError> for ApiError {
+    fn from(err: AuthError) -> Self {
+        match err {
+            AuthError::Missing | AuthError::Invalid => ApiError::Unauthorized(err),
+            AuthError::Forbidden { .. } => ApiError::Forbidden(err),
+            AuthError::RateLimited { .. } | AuthError::QuotaExceeded { .. } => {
+                ApiError::TooManyRequests(err)
+            }
+        }
+    }
+}
+
+impl From<WatchlistError> for ApiError {
+    fn from(err: WatchlistError) -> Self {
+        match err {
+            WatchlistError::NotFound => ApiError::NotFound(err.to_string()),
+            WatchlistError::AlreadyExists | WatchlistError::SymbolExists(_) => {
+                ApiError::Conflict(err.to_string())
+            }
+            WatchlistError::Invalid(msg) => ApiError::BadRequest(msg),
+            WatchlistError::Storage(e) => ApiError::Internal(e),
+        }
+    }
+}
+
+impl From<PlanError> for ApiError {
+    fn from(err: PlanError) -> Self {
+        match err {
+            PlanError::Invalid(msg) => ApiError::BadRequest(msg),
+            PlanError::InvalidSymbol(symbol) => ApiError::InvalidSymbol(symbol),
+            PlanError::InvalidInterval(interval) => ApiError::InvalidInterval(interval),
+            PlanError::UnknownSecurity(symbol) => ApiError::UnknownSecurity(symbol),
+        }
+    }
+}
+
+/// 为每个请求分配 ID：沿用客户端传入的 `x-request-id`，否则生成 UUID。
+pub async fn request_id(req: Request, next: Next) -> Response {
+    let id = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .filter(|v| !v.is_empty() && v.len() <= 128)
+        .map(str::to_string)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+    let mut resp = REQUEST_ID.scope(id.clone(), next.run(req)).await;
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        resp.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    resp
+}
@@ -1,3 +1,13 @@
+pub mod admin;
+pub mod alerts;
+pub mod auth;
+pub mod health;
+pub mod jobs;
 pub mod klines;
+pub mod metrics;
 pub mod page;
+pub mod stream;
+pub mod symbols;
+pub mod watchlists;
 pub mod ws;
+pub mod ws_upgrade;
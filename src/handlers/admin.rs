@@ -0,0 +1,183 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use std::time::Duration;
+
+use crate::config::ProviderKind;
+use crate::error::{ApiError, ErrorBody};
+use crate::handlers::symbols::check_symbol;
+use crate::models::admin::{
+    CacheFlushed, PollSymbol, PollerSettings, SetInterval, SetProvider, WsClientInfo,
+};
+use crate::models::status::FeedStatus;
+use crate::state::AppState;
+
+// 以下修改只在内存中生效，重启后恢复为配置文件中的值。
+
+async fn poller_settings(state: &AppState) -> PollerSettings {
+    let symbols = state.poller().symbols();
+    let mut effective = symbols.clone();
+    for sym in state.watchlists().all_symbols().await {
+        if !effective.contains(&sym) {
+            effective.push(sym);
+        }
+    }
+    PollerSettings {
+        interval_ms: state.poller().interval().as_millis() as u64,
+        symbols,
+        effective_symbols: effective,
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/admin/poller",
+    tag = "admin",
+    responses((status = 200, body = PollerSettings))
+)]
+pub async fn get_poller(State(state): State<AppState>) -> Json<PollerSettings> {
+    Json(poller_settings(&state).await)
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/admin/poller/interval",
+    tag = "admin",
+    request_body = SetInterval,
+    responses(
+        (status = 200, body = PollerSettings),
+        (status = 400, description = "`bad_request`：小于 100ms", body = ErrorBody),
+    )
+)]
+pub async fn set_poll_interval(
+    State(state): State<AppState>,
+    Json(body): Json<SetInterval>,
+) -> Result<Json<PollerSettings>, ApiError> {
+    state
+        .poller()
+        .set_interval(Duration::from_millis(body.interval_ms))
+        .map_err(|e| ApiError::BadRequest(e.to_string()))?;
+    tracing::info!(interval_ms = body.interval_ms, "poll interval changed");
+    Ok(Json(poller_settings(&state).await))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/admin/poller/symbols",
+    tag = "admin",
+    request_body = PollSymbol,
+    responses(
+        (status = 201, body = PollerSettings),
+        (status = 400, description = "`invalid_symbol`", body = ErrorBody),
+        (status = 404, description = "`unknown_security`", body = ErrorBody),
+        (status = 409, description = "`conflict`：已在轮询", body = ErrorBody),
+    )
+)]
+pub async fn add_poll_symbol(
+    State(state): State<AppState>,
+    Json(body): Json<PollSymbol>,
+) -> Result<impl IntoResponse, ApiError> {
+    check_symbol(&state, &body.symbol).await?;
+    if !state.poller().add_symbol(&body.symbol) {
+        return Err(ApiError::Conflict(format!(
+            "{} is already polled",
+            body.symbol
+        )));
+    }
+    tracing::info!(symbol = %body.symbol, "symbol added to poller");
+    Ok((StatusCode::CREATED, Json(poller_settings(&state).await)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/admin/poller/symbols/{symbol}",
+    tag = "admin",
+    params(("symbol" = String, Path)),
+    responses(
+        (status = 204, description = "已移除"),
+        (status = 404, description = "`not_found`：不在常驻轮询列表中", body = ErrorBody),
+    )
+)]
+pub async fn remove_poll_symbol(
+    State(state): State<AppState>,
+    Path(symbol): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    if !state.poller().remove_symbol(&symbol) {
+        return Err(ApiError::NotFound(format!("{symbol} is not polled")));
+    }
+    tracing::info!(%symbol, "symbol removed from poller");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/admin/klines/cache",
+    tag = "admin",
+    responses((status = 200, body = CacheFlushed))
+)]
+pub async fn flush_kline_cache(State(state): State<AppState>) -> Json<CacheFlushed> {
+    let flushed = state.kline_cache().clear().await;
+    tracing::info!(flushed, "kline cache flushed");
+    Json(CacheFlushed { flushed })
+}
+
+/// 手动指定报价源，`auto` 恢复自动主备切换。
+#[utoipa::path(
+    put,
+    path = "/api/admin/provider",
+    tag = "admin",
+    request_body = SetProvider,
+    responses(
+        (status = 200, body = FeedStatus),
+        (status = 400, description = "`bad_request`：未知的报价源", body = ErrorBody),
+    )
+)]
+pub async fn set_provider(
+    State(state): State<AppState>,
+    Json(body): Json<SetProvider>,
+) -> Result<Json<FeedStatus>, ApiError> {
+    let kind = match body.provider.trim() {
+        "auto" => None,
+        name => Some(name.parse::<ProviderKind>().map_err(ApiError::BadRequest)?),
+    };
+    state.quotes().pin(kind);
+    tracing::info!(
+        provider = kind.map_or("auto", ProviderKind::name),
+        "quote provider switched"
+    );
+    Ok(Json(state.quotes().status()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/admin/clients",
+    tag = "admin",
+    responses((status = 200, body = [WsClientInfo]))
+)]
+pub async fn list_clients(State(state): State<AppState>) -> impl IntoResponse {
+    Json(state.ws_clients().list())
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/admin/clients/{id}",
+    tag = "admin",
+    params(("id" = u64, Path)),
+    responses(
+        (status = 204, description = "已通知断开"),
+        (status = 404, description = "`not_found`", body = ErrorBody),
+    )
+)]
+pub async fn kick_client(
+    State(state): State<AppState>,
+    Path(id): Path<u64>,
+) -> Result<StatusCode, ApiError> {
+    if !state.ws_clients().kick(id) {
+        return Err(ApiError::NotFound("client not found".to_string()));
+    }
+    tracing::info!(client = id, "websocket client kicked");
+    Ok(StatusCode::NO_CONTENT)
+}
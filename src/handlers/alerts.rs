@@ -0,0 +1,73 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+
+use crate::error::{ApiError, ErrorBody};
+use crate::handlers::symbols::check_symbol;
+use crate::models::alert::{Alert, NewAlert};
+use crate::state::AppState;
+
+#[utoipa::path(
+    get,
+    path = "/api/alerts",
+    tag = "alerts",
+    responses((status = 200, body = [Alert]))
+)]
+pub async fn list_alerts(State(state): State<AppState>) -> impl IntoResponse {
+    Json(state.alerts().list().await)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/alerts",
+    tag = "alerts",
+    request_body = NewAlert,
+    responses(
+        (status = 201, body = Alert),
+        (status = 400, description = "`invalid_symbol` 或 `bad_request`", body = ErrorBody),
+        (status = 404, description = "`unknown_security`", body = ErrorBody),
+    )
+)]
+pub async fn create_alert(
+    State(state): State<AppState>,
+    Json(new): Json<NewAlert>,
+) -> Result<impl IntoResponse, ApiError> {
+    check_symbol(&state, &new.symbol).await?;
+    new.rule.validate().map_err(ApiError::BadRequest)?;
+
+    let now = chrono::Utc::now().timestamp_millis();
+    let alert = state
+        .alerts()
+        .create(new, now)
+        .await
+        .map_err(ApiError::Internal)?;
+    Ok((StatusCode::CREATED, Json(alert)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/alerts/{id}",
+    tag = "alerts",
+    params(("id" = u64, Path)),
+    responses(
+        (status = 204, description = "已删除"),
+        (status = 404, description = "`not_found`", body = ErrorBody),
+    )
+)]
+pub async fn delete_alert(
+    State(state): State<AppState>,
+    Path(id): Path<u64>,
+) -> Result<StatusCode, ApiError> {
+    match state
+        .alerts()
+        .delete(id)
+        .await
+        .map_err(ApiError::Internal)?
+    {
+        true => Ok(StatusCode::NO_CONTENT),
+        false => Err(ApiError::NotFound("alert not found".to_string())),
+    }
+}
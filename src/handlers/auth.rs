@@ -0,0 +1,104 @@
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::{Method, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use crate::config::Scope;
+use crate::error::{ApiError, current_request_id};
+use crate::openapi::{DOCS_PATH, SPEC_PATH};
+use crate::state::AppState;
+
+/// 通过鉴权的 API key 名称，放在请求扩展里供后续 handler 使用。
+#[derive(Debug, Clone)]
+pub struct AuthenticatedKey(pub String);
+
+/// 按请求方法和路由模板确定所需权限，`None` 表示公开（页面、探针与接口文档）。
+///
+/// 告警与自选列表的读取只需 `quotes`，其余方法（创建、删除、修改）需要 `write`。
+pub fn required_scope(method: &Method, route: &str) -> Option<Scope> {
+    match route {
+        "/" | "/health" | "/ready" | SPEC_PATH => None,
+        r if r.starts_with(DOCS_PATH) => None,
+        "/api/status" | "/metrics" => Some(Scope::Admin),
+        r if r.starts_with("/api/admin") || r.starts_with("/api/jobs") => Some(Scope::Admin),
+        r if r.starts_with("/api/klines") => Some(Scope::Klines),
+        _ if !matches!(*method, Method::GET | Method::HEAD) => Some(Scope::Write),
+        _ => Some(Scope::Quotes),
+    }
+}
+
+/// 鉴权中间件：配置了 API key 时，非公开接口必须带 key，并记录审计日志。
+///
+/// key 可放在 `Authorization: Bearer <key>`、`X-API-Key` 或查询参数 `token` 中；
+/// 浏览器里的 WebSocket/EventSource 不能加请求头，只能用查询参数。
+pub async fn authenticate(State(state): State<AppState>, mut req: Request, next: Next) -> Response {
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| p.as_str().to_string())
+        .unwrap_or_default();
+    if !state.api_keys().enabled() {
+        return next.run(req).await;
+    }
+    let Some(scope) = required_scope(req.method(), &route) else {
+        // 公开接口不要求 key，但带了有效 key 时照样标明身份
+        if let Some(key) = state.api_keys().identify(token(&req).as_deref()) {
+            req.extensions_mut().insert(AuthenticatedKey(key));
+        }
+        return next.run(req).await;
+    };
+
+    let method = req.method().clone();
+    // 只记路径，查询参数里可能有 token
+    let path = req.uri().path().to_string();
+    let request_id = current_request_id();
+
+    let key = match state.api_keys().authorize(token(&req).as_deref(), scope) {
+        Ok(key) => key,
+        Err(err) => {
+            tracing::warn!(
+                target: "audit",
+                %method,
+                %path,
+                scope = scope.name(),
+                request_id,
+                error = %err,
+                "api request denied"
+            );
+            return ApiError::from(err).into_response();
+        }
+    };
+    req.extensions_mut().insert(AuthenticatedKey(key.clone()));
+    let resp = next.run(req).await;
+    tracing::info!(
+        target: "audit",
+        key,
+        %method,
+        %path,
+        status = resp.status().as_u16(),
+        request_id,
+        "api request"
+    );
+    resp
+}
+
+fn token(req: &Request) -> Option<String> {
+    let headers = req.headers();
+    if let Some(bearer) = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+    {
+        return Some(bearer.trim().to_string());
+    }
+    if let Some(key) = headers.get("x-api-key").and_then(|v| v.to_str().ok()) {
+        return Some(key.trim().to_string());
+    }
+    serde_urlencoded::from_str::<Vec<(String, String)>>(req.uri().query()?)
+        .ok()?
+        .into_iter()
+        .find(|(k, _)| k == "token")
+        .map(|(_, v)| v)
+}
@@ -0,0 +1,72 @@
+use axum::{Json, extract::State, http::StatusCode, response::IntoResponse};
+use serde_json::json;
+
+use crate::models::status::{Readiness, StatusReport};
+use crate::state::AppState;
+
+/// 存活探针：进程在跑就返回 200。
+#[utoipa::path(
+    get,
+    path = "/health",
+    tag = "health",
+    security(()),
+    responses((status = 200, description = "进程存活", body = serde_json::Value, example = json!({"status": "ok"})))
+)]
+pub async fn health() -> impl IntoResponse {
+    Json(json!({ "status": "ok" }))
+}
+
+/// 就绪探针：有新鲜报价且行情源可达才返回 200，否则 503。
+#[utoipa::path(
+    get,
+    path = "/ready",
+    tag = "health",
+    security(()),
+    responses(
+        (status = 200, description = "已就绪", body = Readiness),
+        (status = 503, description = "没有新鲜报价或行情源不可达", body = Readiness),
+    )
+)]
+pub async fn ready(State(state): State<AppState>) -> impl IntoResponse {
+    let now = chrono::Utc::now().timestamp_millis();
+    let fresh_within = state.config().poller.fresh_within_ms as i64;
+    let is_fresh = |ts: i64| now - ts <= fresh_within;
+
+    let fresh_quote = state
+        .latest_all()
+        .await
+        .iter()
+        .any(|update| is_fresh(update.ts_ms));
+    let provider_reachable = state.poll_status().last_ok_ms().is_some_and(is_fresh);
+    let body = Readiness {
+        ready: fresh_quote && provider_reachable,
+        fresh_quote,
+        provider_reachable,
+    };
+    let code = if body.ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (code, Json(body))
+}
+
+/// 运行状态：各 symbol 最近更新距今多久、轮询失败次数、WebSocket 连接数、版本。
+#[utoipa::path(
+    get,
+    path = "/api/status",
+    tag = "health",
+    responses((status = 200, body = StatusReport))
+)]
+pub async fn status(State(state): State<AppState>) -> impl IntoResponse {
+    let now = chrono::Utc::now().timestamp_millis();
+    Json(StatusReport {
+        version: env!("CARGO_PKG_VERSION"),
+        uptime_secs: state.started_at().elapsed().as_secs(),
+        ws_clients: state.ws_clients().len(),
+        poll_interval_ms: state.poller().interval().as_millis() as u64,
+        provider: state.quotes().status(),
+        open_circuits: state.limiter().open_circuits(),
+        symbols: state.poll_status().snapshot(now),
+    })
+}
@@ -0,0 +1,99 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+
+use crate::error::{ApiError, ErrorBody};
+use crate::models::job::{BackfillSpec, Job};
+use crate::services::backfill;
+use crate::state::AppState;
+
+#[utoipa::path(
+    get,
+    path = "/api/jobs",
+    tag = "jobs",
+    responses((status = 200, description = "全部下载任务，按 id 升序", body = [Job]))
+)]
+pub async fn list_jobs(State(state): State<AppState>) -> impl IntoResponse {
+    Json(state.jobs().list().await)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/jobs/{id}",
+    tag = "jobs",
+    params(("id" = u64, Path)),
+    responses(
+        (status = 200, description = "任务状态与按窗口计的进度", body = Job),
+        (status = 404, description = "`not_found`", body = ErrorBody),
+    )
+)]
+pub async fn get_job(
+    State(state): State<AppState>,
+    Path(id): Path<u64>,
+) -> Result<Json<Job>, ApiError> {
+    state
+        .jobs()
+        .get(id)
+        .await
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound("job not found".to_string()))
+}
+
+/// 创建历史 K 线下载任务并在后台执行。
+#[utoipa::path(
+    post,
+    path = "/api/admin/jobs/backfill",
+    tag = "jobs",
+    request_body = BackfillSpec,
+    responses(
+        (status = 202, description = "已创建，进度见 `/api/jobs/{id}`", body = Job),
+        (status = 400, description = "`bad_request`、`invalid_symbol` 或 `invalid_interval`", body = ErrorBody),
+        (status = 404, description = "`unknown_security`", body = ErrorBody),
+    )
+)]
+pub async fn create_backfill(
+    State(state): State<AppState>,
+    Json(spec): Json<BackfillSpec>,
+) -> Result<impl IntoResponse, ApiError> {
+    let tasks = backfill::plan(&spec, state.securities()).await?;
+    let job = state
+        .jobs()
+        .submit(spec, tasks)
+        .await
+        .map_err(ApiError::Internal)?;
+    tracing::info!(
+        job = job.id,
+        tasks = job.tasks.len(),
+        "backfill job created"
+    );
+    backfill::spawn_job(state.clone(), job.id);
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+/// 取消任务，已下载的部分保留在本地。
+#[utoipa::path(
+    delete,
+    path = "/api/admin/jobs/{id}",
+    tag = "jobs",
+    params(("id" = u64, Path)),
+    responses(
+        (status = 200, description = "取消后的任务；已结束的任务原样返回", body = Job),
+        (status = 404, description = "`not_found`", body = ErrorBody),
+    )
+)]
+pub async fn cancel_job(
+    State(state): State<AppState>,
+    Path(id): Path<u64>,
+) -> Result<Json<Job>, ApiError> {
+    let job = state
+        .jobs()
+        .cancel(id)
+        .await
+        .map_err(ApiError::Internal)?
+        .ok_or_else(|| ApiError::NotFound("job not found".to_string()))?;
+    tracing::info!(job = id, state = ?job.state, "backfill job cancel requested");
+    Ok(Json(job))
+}
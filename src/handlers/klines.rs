@@ -1,35 +1,173 @@
-use axum::{Json, extract::Path, http::StatusCode, response::IntoResponse};
+use axum::{
+    Json,
+    body::Body,
+    extract::{Path, Query, State},
+    http::header,
+    response::{IntoResponse, Response},
+};
+use chrono::NaiveDate;
 
-use crate::services::ashare::AshareService;
+use crate::error::{ApiError, ErrorBody};
+use crate::handlers::symbols::check_symbol;
+use crate::models::kline::{Adjust, Kline};
+use crate::services::ashare::SUPPORTED_INTERVALS;
+use crate::services::export::{
+    ExportFormat, ExportRequest, KlineExport, default_start, market_today,
+};
+use crate::state::AppState;
+use utoipa::IntoParams;
 
-#[derive(serde::Deserialize)]
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct KlineQuery {
+    /// K 线周期，如 `1m`、`5m`、`1d`，缺省 `1m`
     pub interval: Option<String>,
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/klines/{symbol}",
+    tag = "klines",
+    params(("symbol" = String, Path, description = "如 600000.SH"), KlineQuery),
+    responses(
+        (status = 200, description = "最近 200 根 K 线，按时间升序", body = [Kline]),
+        (status = 400, description = "`invalid_symbol` 或 `invalid_interval`", body = ErrorBody),
+        (status = 404, description = "`unknown_security`", body = ErrorBody),
+        (status = 502, description = "`upstream_error`", body = ErrorBody),
+        (status = 503, description = "`upstream_unavailable`", body = ErrorBody),
+        (status = 504, description = "`upstream_timeout`", body = ErrorBody),
+    )
+)]
 pub async fn get_klines(
+    State(state): State<AppState>,
     Path(symbol): Path<String>,
-    axum::extract::Query(KlineQuery { interval }): axum::extract::Query<KlineQuery>,
-) -> impl IntoResponse {
-    // 简单起见，每次请求都创建一个 service。后面可放到 AppState 里复用或共享连接。
-    let svc = AshareService::new();
-
+    Query(KlineQuery { interval }): Query<KlineQuery>,
+) -> Result<Response, ApiError> {
+    check_symbol(&state, &symbol).await?;
     let interval = interval.unwrap_or_else(|| "1m".to_string());
+    if !SUPPORTED_INTERVALS.contains(&interval.as_str()) {
+        return Err(ApiError::InvalidInterval(interval));
+    }
 
-    match svc.fetch_klines(&symbol, &interval, 200).await {
-        Ok(klines) => (StatusCode::OK, Json(klines)).into_response(),
-        Err(err) => {
-            tracing::warn!(
-                error = %err,
-                %symbol,
-                %interval,
-                "failed to fetch klines from Ashare service"
-            );
-            (
-                StatusCode::BAD_GATEWAY,
-                "failed to fetch klines from Ashare service",
-            )
-                .into_response()
-        }
+    let cached = state.kline_cache().get(&symbol, &interval).await;
+    state.metrics().kline_cache(cached.is_some());
+    if let Some(klines) = cached {
+        return Ok(Json(klines.as_slice()).into_response());
+    }
+
+    let klines = state
+        .ashare()
+        .fetch_klines(&symbol, &interval, 200)
+        .await
+        .map_err(ApiError::upstream)?;
+    let klines = state.kline_cache().insert(&symbol, &interval, klines).await;
+    Ok(Json(klines.as_slice()).into_response())
+}
+
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ExportQuery {
+    /// `csv`、`jsonl` 或 `parquet`，缺省 `csv`
+    pub format: Option<String>,
+    /// K 线周期，缺省 `1d`
+    pub interval: Option<String>,
+    /// 复权方式 `none`、`forward`、`backward`，缺省 `forward`
+    pub adjust: Option<String>,
+    /// 起始日期（含），`YYYY-MM-DD` 或 `YYYYMMDD`，缺省为上市日期
+    pub start: Option<String>,
+    /// 结束日期（含），缺省为交易所当地的今天
+    pub end: Option<String>,
+}
+
+/// 流式导出一段时间的 K 线，按窗口分批向上游拉取，不在内存中攒整段历史。
+#[utoipa::path(
+    get,
+    path = "/api/klines/{symbol}/export",
+    tag = "klines",
+    params(("symbol" = String, Path, description = "如 600000.SH"), ExportQuery),
+    responses(
+        (status = 200, description = "带标题行的 CSV、每行一个对象的 JSONL 或 Parquet 文件",
+            content(
+                (String = "text/csv"),
+                (String = "application/x-ndjson"),
+                (Vec<u8> = "application/vnd.apache.parquet"),
+            )),
+        (status = 400, description = "`bad_request`、`invalid_symbol` 或 `invalid_interval`", body = ErrorBody),
+        (status = 404, description = "`unknown_security`", body = ErrorBody),
+        (status = 502, description = "`upstream_error`", body = ErrorBody),
+        (status = 503, description = "`upstream_unavailable`", body = ErrorBody),
+        (status = 504, description = "`upstream_timeout`", body = ErrorBody),
+    )
+)]
+pub async fn export_klines(
+    State(state): State<AppState>,
+    Path(symbol): Path<String>,
+    Query(query): Query<ExportQuery>,
+) -> Result<Response, ApiError> {
+    check_symbol(&state, &symbol).await?;
+    let format: ExportFormat = query
+        .format
+        .as_deref()
+        .map(str::parse)
+        .transpose()
+        .map_err(ApiError::BadRequest)?
+        .unwrap_or_default();
+    let interval = query.interval.unwrap_or_else(|| "1d".to_string());
+    if !SUPPORTED_INTERVALS.contains(&interval.as_str()) {
+        return Err(ApiError::InvalidInterval(interval));
+    }
+    let adjust: Adjust = query
+        .adjust
+        .as_deref()
+        .map(str::parse)
+        .transpose()
+        .map_err(ApiError::BadRequest)?
+        .unwrap_or_default();
+
+    let listed = state.securities().get(&symbol).await.and_then(|s| s.listed);
+    let end = match query.end.as_deref() {
+        Some(raw) => parse_date(raw)?,
+        None => market_today(&symbol),
+    };
+    let mut start = match query.start.as_deref() {
+        Some(raw) => parse_date(raw)?,
+        None => default_start(&interval, listed, end),
+    };
+    if let Some(listed) = listed {
+        start = start.max(listed);
+    }
+    if start > end {
+        return Err(ApiError::BadRequest(format!(
+            "start {start} is after end {end}"
+        )));
     }
+
+    let req = ExportRequest {
+        symbol,
+        interval,
+        adjust,
+        start,
+        end,
+    };
+    let file_name = req.file_name(format);
+    let export = KlineExport::start(state.ashare().clone(), req, format)
+        .await
+        .map_err(ApiError::upstream)?;
+    Ok((
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{file_name}\""),
+            ),
+        ],
+        Body::from_stream(export.into_stream()),
+    )
+        .into_response())
+}
+
+fn parse_date(raw: &str) -> Result<NaiveDate, ApiError> {
+    NaiveDate::parse_from_str(raw, "%Y-%m-%d")
+        .or_else(|_| NaiveDate::parse_from_str(raw, "%Y%m%d"))
+        .map_err(|_| ApiError::BadRequest(format!("invalid date {raw}, expected YYYY-MM-DD")))
 }
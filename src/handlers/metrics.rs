@@ -0,0 +1,44 @@
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::header,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::time::Instant;
+
+use crate::state::AppState;
+
+/// Prometheus 文本格式的指标。
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    tag = "health",
+    responses((status = 200, description = "Prometheus 文本格式", body = String, content_type = "text/plain"))
+)]
+pub async fn metrics(State(state): State<AppState>) -> impl IntoResponse {
+    let now = chrono::Utc::now().timestamp_millis();
+    let quotes: Vec<(String, f64)> = state
+        .latest_all()
+        .await
+        .into_iter()
+        .map(|u| (u.symbol, (now - u.ts_ms).max(0) as f64 / 1000.0))
+        .collect();
+    let body = state.metrics().render(state.ws_clients().len(), &quotes);
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body)
+}
+
+/// 按路由模板（而不是实际路径）统计 HTTP 请求耗时，避免 symbol 之类的参数撑爆标签。
+pub async fn track_http(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    let started = Instant::now();
+    let method = req.method().clone();
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| p.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+    let resp = next.run(req).await;
+    state
+        .metrics()
+        .observe_http(method.as_str(), &route, resp.status().as_u16(), started);
+    resp
+}
@@ -1,6 +1,12 @@
+use axum::Extension;
+use axum::extract::{Query, State};
 use axum::response::Html as AxumHtml;
 use yew::prelude::*;
 
+use crate::handlers::auth::AuthenticatedKey;
+use crate::services::watchlists::DEFAULT_USER;
+use crate::state::AppState;
+
 const INLINE_WS_JS: &str = r#"
       const priceEl = document.getElementById('price');
       const statusDotEl = document.getElementById('status-dot');
@@ -10,6 +16,24 @@ const INLINE_WS_JS: &str = r#"
       // 按 symbol 缓存最近一次价格和时间，方便切换指数时立即展示
       const lastPriceBySymbol = {};
       const lastTimeBySymbol = {};
+      // 服务端判定为 stale 的 symbol -> 最近一次报价前进的时间
+      const staleSinceBySymbol = {};
+      // 每个 symbol 已处理到的序号；发现跳号时请求服务端补发
+      const lastSeqBySymbol = {};
+      // 服务端启用 API key 时，页面地址带上 ?token=<key>
+      const apiToken = new URLSearchParams(location.search).get('token');
+      const resyncPending = {};
+
+      function renderStatus(symbol) {
+        const since = staleSinceBySymbol[symbol];
+        if (since) {
+          statusDotEl.classList.add('stale');
+          statusTextEl.textContent = '报价已停止更新，最后变动：' + formatTs(since);
+        } else {
+          statusDotEl.classList.remove('stale');
+          statusTextEl.textContent = '实时价格推送中';
+        }
+      }
 
       function formatTs(tsMs) {
         if (!tsMs) return '';
@@ -24,10 +48,11 @@ const INLINE_WS_JS: &str = r#"
         if (typeof p === 'number') {
           priceEl.textContent = p.toFixed(2);
           timeEl.textContent = ts ? '更新时间：' + formatTs(ts) : '';
-          statusTextEl.textContent = '实时价格推送中';
+          renderStatus(symbol);
         } else {
           priceEl.textContent = '--.--';
           timeEl.textContent = '';
+          statusDotEl.classList.remove('stale');
           statusTextEl.textContent = '等待该指数价格推送...';
         }
       };
@@ -36,22 +61,68 @@ const INLINE_WS_JS: &str = r#"
         const wsUrl =
           (location.protocol === 'https:' ? 'wss://' : 'ws://') +
           location.host +
-          '/ws/prices';
+          '/ws/prices' +
+          (apiToken ? '?token=' + encodeURIComponent(apiToken) : '');
         const ws = new WebSocket(wsUrl);
 
         ws.onopen = () => {
+          // 新连接会先收到快照，序号从快照重新开始
+          for (const k of Object.keys(lastSeqBySymbol)) delete lastSeqBySymbol[k];
+          for (const k of Object.keys(resyncPending)) delete resyncPending[k];
           statusDotEl.classList.add('connected');
           statusTextEl.textContent = '已连接，等待价格更新...';
         };
 
         ws.onmessage = (event) => {
           try {
-            const data = JSON.parse(event.data);
+            let data = JSON.parse(event.data);
+            if (data && data.symbol && typeof data.seq === 'number') {
+              const sym = data.symbol;
+              const last = lastSeqBySymbol[sym];
+              if (data.type === 'snapshot') {
+                lastSeqBySymbol[sym] = data.seq;
+                delete resyncPending[sym];
+                if (!data.latest) return;
+                data = Object.assign({ type: 'price', seq: data.seq }, data.latest);
+              } else if (last !== undefined && data.seq <= last) {
+                return;
+              } else if (
+                last !== undefined &&
+                data.seq > last + 1 &&
+                // 服务端因积压合并掉的消息，跳号是预期的
+                !(typeof data.conflated_from === 'number' && data.conflated_from <= last + 1)
+              ) {
+                if (!resyncPending[sym]) {
+                  resyncPending[sym] = true;
+                  ws.send(JSON.stringify({ type: 'resync', symbol: sym, after_seq: last }));
+                }
+                return;
+              } else {
+                lastSeqBySymbol[sym] = data.seq;
+                delete resyncPending[sym];
+              }
+            }
+            if (data && (data.type === 'stale' || data.type === 'recovered')) {
+              if (data.type === 'stale') {
+                staleSinceBySymbol[data.symbol] = data.last_fresh_ts_ms;
+              } else {
+                delete staleSinceBySymbol[data.symbol];
+              }
+              if (data.symbol === window.currentSymbol) {
+                renderStatus(data.symbol);
+              }
+              return;
+            }
             if (!data || typeof data.price === 'undefined') {
               return;
             }
 
             const sym = data.symbol || window.currentSymbol || '000001.SH';
+            if (data.stale && !staleSinceBySymbol[sym]) {
+              staleSinceBySymbol[sym] = data.source_ts_ms || data.ts_ms;
+            } else if (data.stale === false) {
+              delete staleSinceBySymbol[sym];
+            }
 
             let priceNum;
             if (typeof data.price === 'number') {
@@ -72,7 +143,7 @@ const INLINE_WS_JS: &str = r#"
               timeEl.textContent = data.ts_ms
                 ? '更新时间：' + formatTs(data.ts_ms)
                 : '';
-              statusTextEl.textContent = '实时价格推送中';
+              renderStatus(sym);
             }
           } catch (e) {
             console.error('invalid message', e);
@@ -95,9 +166,13 @@ const INLINE_WS_JS: &str = r#"
 "#;
 
 const INLINE_KLINE_JS: &str = r#"
-      let currentSymbol = '000001.SH';
+      const firstSymbolTab = document.querySelector('[data-symbol-tab]');
+      let currentSymbol = firstSymbolTab
+        ? firstSymbolTab.getAttribute('data-symbol-tab')
+        : '000001.SH';
       window.currentSymbol = currentSymbol;
       let currentInterval = '1m';
+      const klineToken = new URLSearchParams(location.search).get('token');
       let lastKlines = [];
       let hoverIndex = null;
       let crossX = null;
@@ -305,10 +380,12 @@ const INLINE_KLINE_JS: &str = r#"
           const res = await fetch(
             `/api/klines/${currentSymbol}?interval=${encodeURIComponent(
               currentInterval
-            )}`
+            )}`,
+            { headers: klineToken ? { Authorization: 'Bearer ' + klineToken } : {} }
           );
           if (!res.ok) {
-            throw new Error('HTTP ' + res.status);
+            const err = await res.json().catch(() => null);
+            throw new Error(err && err.message ? err.message : 'HTTP ' + res.status);
           }
           const data = await res.json();
           lastKlines = Array.isArray(data) ? data : [];
@@ -682,8 +759,39 @@ const INLINE_KLINE_JS: &str = r#"
 
       window.addEventListener('load', initKline);
 "#;
+#[derive(Clone, PartialEq)]
+struct SymbolTab {
+    symbol: String,
+    label: String,
+}
+
+#[derive(Clone, PartialEq)]
+struct WatchlistLink {
+    name: String,
+    href: String,
+    active: bool,
+}
+
+#[derive(Properties, PartialEq)]
+struct AppProps {
+    tabs: Vec<SymbolTab>,
+    watchlists: Vec<WatchlistLink>,
+}
+
+/// 标签文字：证券简称 + 代码，未收录的 symbol 直接显示代码。
+async fn display_label(state: &AppState, symbol: &str) -> String {
+    match state.securities().get(symbol).await {
+        Some(sec) => format!("{} {}", sec.name, sec.symbol),
+        None => symbol.to_string(),
+    }
+}
+
 #[function_component(App)]
-fn app() -> Html {
+fn app(props: &AppProps) -> Html {
+    let first = props.tabs.first();
+    let first_label = first.map_or_else(|| "上证指数 000001.SH".to_string(), |t| t.label.clone());
+    let first_symbol = first.map_or_else(|| "000001.SH".to_string(), |t| t.symbol.clone());
+
     html! {
         <html lang="en">
           <head>
@@ -696,7 +804,7 @@ fn app() -> Html {
               <div class="card">
                 <div>
                   <div class="label">{ "Realtime Index" }</div>
-                  <div class="symbol" id="chart-symbol-label">{ "上证指数 000001.SH" }</div>
+                  <div class="symbol" id="chart-symbol-label">{ first_label }</div>
                 </div>
                 <div class="price" id="price">{ "--.--" }</div>
                 <div class="status-row">
@@ -711,7 +819,7 @@ fn app() -> Html {
               <div class="panel">
                 <div class="panel-header">
                   <div class="panel-title">
-                    <span class="panel-title-main" id="chart-symbol">{ "000001.SH" }</span>
+                    <span class="panel-title-main" id="chart-symbol">{ first_symbol }</span>
                     <span class="panel-title-sub" id="chart-interval">{ "1m" }</span>
                   </div>
                   <div class="legend">
@@ -729,10 +837,28 @@ fn app() -> Html {
                     </div>
                   </div>
                 </div>
+                if props.watchlists.len() > 1 {
+                  <div class="watchlist-tabs">
+                    <span class="interval-label">{ "自选" }</span>
+                    { for props.watchlists.iter().map(|w| html! {
+                      <a
+                        class={classes!("interval-tab", w.active.then_some("active"))}
+                        href={w.href.clone()}
+                      >
+                        { &w.name }
+                      </a>
+                    }) }
+                  </div>
+                }
                 <div class="symbol-tabs">
-                  <button class="tab active" data-symbol-tab="000001.SH">{ "上证指数 000001.SH" }</button>
-                  <button class="tab" data-symbol-tab="399001.SZ">{ "深证成指 399001.SZ" }</button>
-                  <button class="tab" data-symbol-tab="399006.SZ">{ "创业板指 399006.SZ" }</button>
+                  { for props.tabs.iter().enumerate().map(|(i, t)| html! {
+                    <button
+                      class={classes!("tab", (i == 0).then_some("active"))}
+                      data-symbol-tab={t.symbol.clone()}
+                    >
+                      { &t.label }
+                    </button>
+                  }) }
                 </div>
                 <div class="interval-tabs">
                   <span class="interval-label">{ "周期" }</span>
@@ -762,7 +888,68 @@ fn app() -> Html {
     }
 }
 
-pub async fn index() -> AxumHtml<String> {
-    let rendered = yew::ServerRenderer::<App>::new().render().await;
+#[derive(serde::Deserialize)]
+pub struct PageQuery {
+    /// 要展示的自选列表，缺省为该用户的第一个列表
+    pub watchlist: Option<String>,
+    /// API key；页面内的链接会原样带上，脚本据此连接推送与 K 线接口
+    pub token: Option<String>,
+}
+
+/// 首页公开访问，自选列表按 key 归属：启用鉴权时只展示所带 key 的列表，未带有效 key 则不展示。
+pub async fn index(
+    State(state): State<AppState>,
+    key: Option<Extension<AuthenticatedKey>>,
+    Query(PageQuery { watchlist, token }): Query<PageQuery>,
+) -> AxumHtml<String> {
+    let owner = match key {
+        Some(Extension(AuthenticatedKey(name))) => Some(name),
+        None if !state.api_keys().enabled() => Some(DEFAULT_USER.to_string()),
+        None => None,
+    };
+    let token = token.filter(|t| !t.is_empty());
+    let selected = match &owner {
+        Some(owner) => {
+            state
+                .watchlists()
+                .resolve(owner, watchlist.as_deref())
+                .await
+        }
+        None => None,
+    };
+
+    let mut tabs = Vec::new();
+    for symbol in selected.iter().flat_map(|w| w.symbols.iter()) {
+        tabs.push(SymbolTab {
+            symbol: symbol.clone(),
+            label: display_label(&state, symbol).await,
+        });
+    }
+
+    let lists = match &owner {
+        Some(owner) => state.watchlists().list(owner).await,
+        None => Vec::new(),
+    };
+    let watchlists = lists
+        .into_iter()
+        .map(|w| {
+            let mut query = vec![("watchlist", w.name.as_str())];
+            if let Some(token) = &token {
+                query.push(("token", token.as_str()));
+            }
+            WatchlistLink {
+                href: format!(
+                    "/?{}",
+                    serde_urlencoded::to_string(&query).unwrap_or_default()
+                ),
+                active: selected.as_ref().is_some_and(|s| s.name == w.name),
+                name: w.name,
+            }
+        })
+        .collect();
+
+    let rendered = yew::ServerRenderer::<App>::with_props(move || AppProps { tabs, watchlists })
+        .render()
+        .await;
     AxumHtml(rendered)
 }
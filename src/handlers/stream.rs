@@ -0,0 +1,157 @@
+use axum::{
+    extract::{Query, State},
+    http::HeaderMap,
+    response::{
+        IntoResponse,
+        sse::{Event, KeepAlive, Sse},
+    },
+};
+use futures_util::stream::{self, Stream, StreamExt};
+use std::collections::{HashSet, VecDeque};
+use std::convert::Infallible;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::error::{ApiError, ErrorBody};
+use crate::models::encoding::WireFormat;
+use crate::models::event::{Published, StreamEvent};
+use crate::models::market::parse_symbol;
+use crate::state::AppState;
+use utoipa::IntoParams;
+
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct StreamQuery {
+    /// 逗号分隔的 symbol，为空时推送全部
+    pub symbols: Option<String>,
+}
+
+/// `GET /api/stream/prices`：SSE 版本的 `/ws/prices`，消息体与 WebSocket 相同。
+///
+/// 每条事件带 `id:`，客户端重连时带上 `Last-Event-ID`，若仍在回放缓冲区内则从断点补发，
+/// 否则先发一份最新报价快照。
+#[utoipa::path(
+    get,
+    path = "/api/stream/prices",
+    tag = "stream",
+    params(
+        StreamQuery,
+        ("Last-Event-ID" = Option<u64>, Header, description = "上次收到的事件 id，从断点续传"),
+    ),
+    responses(
+        (status = 200, description = "SSE，每条 `data:` 为一个 `Published` 的 JSON", body = Published, content_type = "text/event-stream"),
+        (status = 400, description = "`invalid_symbol`", body = ErrorBody),
+    )
+)]
+pub async fn stream_prices(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<StreamQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let symbols = parse_symbols(query.symbols.as_deref())?;
+
+    let last_event_id = headers
+        .get("last-event-id")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok());
+
+    // 先订阅再读缓冲区，两者之间发布的事件靠 id 去重
+    let rx = state.subscribe();
+    let resumed = last_event_id.and_then(|id| Some((id, state.replay().since(id)?)));
+    let (backlog, cursor) = match resumed {
+        Some((id, missed)) => {
+            let cursor = missed.last().map_or(id, |p| p.id);
+            (missed, cursor)
+        }
+        // 快照的 id 取当前最新 id：之后的事件都会从广播里收到
+        None => {
+            let cursor = state.replay().last_id();
+            (state.snapshot().await, cursor)
+        }
+    };
+
+    let live = live_events(state.clone(), rx, cursor);
+    let stream = stream::iter(backlog)
+        .chain(live)
+        .filter(move |p| std::future::ready(wanted(&symbols, &p.event)))
+        .filter_map(|p| std::future::ready(to_sse(&p)))
+        .map(Ok::<_, Infallible>);
+    Ok(Sse::new(stream)
+        .keep_alive(KeepAlive::new().interval(state.config().stream.sse_keepalive())))
+}
+
+/// 广播中 id 大于 `cursor` 的消息；落后被跳过时从回放缓冲区补上，补不上就发快照。
+fn live_events(
+    state: AppState,
+    rx: tokio::sync::broadcast::Receiver<Published>,
+    cursor: u64,
+) -> impl Stream<Item = Published> {
+    let pending = VecDeque::new();
+    stream::unfold(
+        (rx, cursor, pending),
+        move |(mut rx, mut cursor, mut pending)| {
+            let state = state.clone();
+            async move {
+                loop {
+                    if let Some(published) = pending.pop_front() {
+                        return Some((published, (rx, cursor, pending)));
+                    }
+                    // 退出时结束响应，否则优雅关闭要一直等到超时
+                    let msg = tokio::select! {
+                        msg = rx.recv() => msg,
+                        _ = state.shutdown_token().cancelled() => return None,
+                    };
+                    match msg {
+                        Ok(published) if published.id <= cursor => {}
+                        Ok(published) => {
+                            cursor = published.id;
+                            return Some((published, (rx, cursor, pending)));
+                        }
+                        Err(RecvError::Lagged(n)) => {
+                            state.metrics().broadcast_lagged("sse", n);
+                            let missed = match state.replay().since(cursor) {
+                                Some(missed) => missed,
+                                None => state.snapshot().await,
+                            };
+                            if let Some(last) = missed.last() {
+                                cursor = cursor.max(last.id);
+                            }
+                            pending.extend(missed);
+                        }
+                        Err(RecvError::Closed) => return None,
+                    }
+                }
+            }
+        },
+    )
+}
+
+/// 解析逗号分隔的 symbol 列表，空集合表示全部。
+pub(crate) fn parse_symbols(raw: Option<&str>) -> Result<HashSet<String>, ApiError> {
+    let mut symbols = HashSet::new();
+    for s in raw.unwrap_or_default().split(',') {
+        let s = s.trim();
+        if s.is_empty() {
+            continue;
+        }
+        if parse_symbol(s).is_none() {
+            return Err(ApiError::InvalidSymbol(s.to_string()));
+        }
+        symbols.insert(s.to_string());
+    }
+    Ok(symbols)
+}
+
+pub(crate) fn wanted(symbols: &HashSet<String>, event: &StreamEvent) -> bool {
+    symbols.is_empty() || symbols.contains(event.symbol())
+}
+
+fn to_sse(published: &Published) -> Option<Event> {
+    // 与 WebSocket 的 JSON 连接共用同一份序列化结果
+    let data = published.encoded(WireFormat::default())?;
+    let event = Event::default().data(std::str::from_utf8(&data).ok()?);
+    Some(if published.id > 0 {
+        event.id(published.id.to_string())
+    } else {
+        event
+    })
+}
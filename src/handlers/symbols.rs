@@ -0,0 +1,56 @@
+use axum::{
+    Json,
+    extract::{Query, State},
+    response::IntoResponse,
+};
+
+use crate::error::ApiError;
+use crate::models::market::{Market, MarketInfo, parse_symbol};
+use crate::models::security::Security;
+use crate::state::AppState;
+use utoipa::IntoParams;
+
+/// 校验 symbol 格式并确认在证券列表中。
+pub async fn check_symbol(state: &AppState, symbol: &str) -> Result<(), ApiError> {
+    if parse_symbol(symbol).is_none() {
+        return Err(ApiError::InvalidSymbol(symbol.to_string()));
+    }
+    if !state.securities().contains(symbol).await {
+        return Err(ApiError::UnknownSecurity(symbol.to_string()));
+    }
+    Ok(())
+}
+
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SearchQuery {
+    pub q: String,
+    pub limit: Option<usize>,
+}
+
+/// 按代码、拼音首字母或中文名搜索证券。
+#[utoipa::path(
+    get,
+    path = "/api/symbols/search",
+    tag = "symbols",
+    params(SearchQuery),
+    responses((status = 200, body = [Security]))
+)]
+pub async fn search_symbols(
+    State(state): State<AppState>,
+    Query(SearchQuery { q, limit }): Query<SearchQuery>,
+) -> impl IntoResponse {
+    let limit = limit.unwrap_or(20).clamp(1, 100);
+    Json(state.securities().search(&q, limit).await)
+}
+
+/// 支持的市场及其币种、价格精度与交易时段。
+#[utoipa::path(
+    get,
+    path = "/api/markets",
+    tag = "symbols",
+    responses((status = 200, body = [MarketInfo]))
+)]
+pub async fn list_markets() -> impl IntoResponse {
+    Json(Market::ALL.map(MarketInfo::from))
+}
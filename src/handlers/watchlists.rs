@@ -0,0 +1,179 @@
+use axum::{
+    Extension, Json,
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+
+use crate::error::{ApiError, ErrorBody};
+use crate::handlers::auth::AuthenticatedKey;
+use crate::handlers::symbols::check_symbol;
+use crate::models::watchlist::{AddSymbol, NewWatchlist, ReorderSymbols, Watchlist};
+use crate::services::watchlists::DEFAULT_USER;
+use crate::state::AppState;
+
+type Owner = Option<Extension<AuthenticatedKey>>;
+
+/// 自选列表归属于通过鉴权的 API key；未启用鉴权时都归 `default`。
+fn user_id(key: &Owner) -> &str {
+    key.as_ref()
+        .map_or(DEFAULT_USER, |Extension(k)| k.0.as_str())
+}
+
+async fn check_symbols(state: &AppState, symbols: &[String]) -> Result<(), ApiError> {
+    for s in symbols {
+        check_symbol(state, s).await?;
+    }
+    Ok(())
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/watchlists",
+    tag = "watchlists",
+    responses((status = 200, body = [Watchlist]))
+)]
+pub async fn list_watchlists(State(state): State<AppState>, key: Owner) -> impl IntoResponse {
+    Json(state.watchlists().list(user_id(&key)).await)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/watchlists",
+    tag = "watchlists",
+    request_body = NewWatchlist,
+    responses(
+        (status = 201, body = Watchlist),
+        (status = 400, description = "`invalid_symbol` 或 `bad_request`", body = ErrorBody),
+        (status = 404, description = "`unknown_security`", body = ErrorBody),
+        (status = 409, description = "`conflict`：同名列表已存在", body = ErrorBody),
+    )
+)]
+pub async fn create_watchlist(
+    State(state): State<AppState>,
+    key: Owner,
+    Json(new): Json<NewWatchlist>,
+) -> Result<impl IntoResponse, ApiError> {
+    check_symbols(&state, &new.symbols).await?;
+    let list = state
+        .watchlists()
+        .create(user_id(&key), new.name, new.symbols)
+        .await?;
+    Ok((StatusCode::CREATED, Json(list)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/watchlists/{name}",
+    tag = "watchlists",
+    params(("name" = String, Path)),
+    responses(
+        (status = 200, body = Watchlist),
+        (status = 404, description = "`not_found`", body = ErrorBody),
+    )
+)]
+pub async fn get_watchlist(
+    State(state): State<AppState>,
+    key: Owner,
+    Path(name): Path<String>,
+) -> Result<Json<Watchlist>, ApiError> {
+    state
+        .watchlists()
+        .get(user_id(&key), &name)
+        .await
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound("watchlist not found".to_string()))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/watchlists/{name}",
+    tag = "watchlists",
+    params(("name" = String, Path)),
+    responses(
+        (status = 204, description = "已删除"),
+        (status = 404, description = "`not_found`", body = ErrorBody),
+    )
+)]
+pub async fn delete_watchlist(
+    State(state): State<AppState>,
+    key: Owner,
+    Path(name): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    state.watchlists().delete(user_id(&key), &name).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/watchlists/{name}/symbols",
+    tag = "watchlists",
+    params(("name" = String, Path)),
+    request_body = AddSymbol,
+    responses(
+        (status = 200, body = Watchlist),
+        (status = 400, description = "`invalid_symbol` 或 `bad_request`", body = ErrorBody),
+        (status = 404, description = "`not_found` 或 `unknown_security`", body = ErrorBody),
+        (status = 409, description = "`conflict`：symbol 已在列表中", body = ErrorBody),
+    )
+)]
+pub async fn add_symbol(
+    State(state): State<AppState>,
+    key: Owner,
+    Path(name): Path<String>,
+    Json(body): Json<AddSymbol>,
+) -> Result<Json<Watchlist>, ApiError> {
+    check_symbol(&state, &body.symbol).await?;
+    let list = state
+        .watchlists()
+        .add_symbol(user_id(&key), &name, body.symbol, body.position)
+        .await?;
+    Ok(Json(list))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/watchlists/{name}/symbols",
+    tag = "watchlists",
+    params(("name" = String, Path)),
+    request_body = ReorderSymbols,
+    responses(
+        (status = 200, body = Watchlist),
+        (status = 400, description = "`bad_request`：与现有 symbol 不一致", body = ErrorBody),
+        (status = 404, description = "`not_found`", body = ErrorBody),
+    )
+)]
+pub async fn reorder_symbols(
+    State(state): State<AppState>,
+    key: Owner,
+    Path(name): Path<String>,
+    Json(body): Json<ReorderSymbols>,
+) -> Result<Json<Watchlist>, ApiError> {
+    let list = state
+        .watchlists()
+        .reorder(user_id(&key), &name, body.symbols)
+        .await?;
+    Ok(Json(list))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/watchlists/{name}/symbols/{symbol}",
+    tag = "watchlists",
+    params(("name" = String, Path), ("symbol" = String, Path)),
+    responses(
+        (status = 200, body = Watchlist),
+        (status = 404, description = "`not_found`", body = ErrorBody),
+    )
+)]
+pub async fn remove_symbol(
+    State(state): State<AppState>,
+    key: Owner,
+    Path((name, symbol)): Path<(String, String)>,
+) -> Result<Json<Watchlist>, ApiError> {
+    let list = state
+        .watchlists()
+        .remove_symbol(user_id(&key), &name, &symbol)
+        .await?;
+    Ok(Json(list))
+}
@@ -1,51 +1,322 @@
+use crate::error::{ApiError, ErrorBody};
+use crate::handlers::auth::AuthenticatedKey;
+use crate::handlers::stream::{parse_symbols, wanted};
+use crate::handlers::ws_upgrade::{WebSocket, WsUpgrade};
+use crate::models::encoding::{Encoding, WireFormat};
+use crate::models::event::{ClientMessage, Published};
+use crate::services::metrics::Metrics;
+use crate::services::outbox::Outbox;
 use crate::state::AppState;
-use axum::extract::ws::{Message, WebSocket};
 use axum::{
-    extract::{State, WebSocketUpgrade},
+    Extension,
+    extract::{Query, State},
     response::IntoResponse,
 };
+use bytes::Bytes;
+use futures_util::stream::{SplitSink, SplitStream};
+use futures_util::{SinkExt, StreamExt};
+use std::collections::HashSet;
+use std::time::Duration;
 use tokio::select;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tokio::time::{Instant, interval_at, timeout};
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::tungstenite::protocol::CloseFrame;
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_util::sync::CancellationToken;
+use utoipa::IntoParams;
 
-pub async fn ws_prices(ws: WebSocketUpgrade, State(state): State<AppState>) -> impl IntoResponse {
-    ws.on_upgrade(move |socket| handle_socket(socket, state))
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct WsQuery {
+    /// `json`（默认）、`msgpack` 或 `cbor`，优先于子协议
+    pub encoding: Option<String>,
+    /// 逗号分隔的 symbol，为空时推送全部
+    pub symbols: Option<String>,
 }
 
-async fn handle_socket(mut socket: WebSocket, state: AppState) {
-    // Send latest immediately if we have it.
-    if let Some(latest) = state.latest().await {
-        if let Ok(txt) = serde_json::to_string(&latest) {
-            let _ = socket.send(Message::Text(txt.into())).await;
+/// 编码可通过 `?encoding=` 或 `Sec-WebSocket-Protocol: showmarket.<encoding>` 协商；
+/// 客户端在 `Sec-WebSocket-Extensions` 中提供 permessage-deflate 时启用压缩（RFC 7692）。
+#[utoipa::path(
+    get,
+    path = "/ws/prices",
+    tag = "stream",
+    params(WsQuery),
+    responses(
+        (status = 101, description = "升级为 WebSocket；服务端推送 `Published`，客户端可发送 `ClientMessage`"),
+        (status = 400, description = "`bad_request` 或 `invalid_symbol`", body = ErrorBody),
+    )
+)]
+pub async fn ws_prices(
+    ws: WsUpgrade,
+    State(state): State<AppState>,
+    key: Option<Extension<AuthenticatedKey>>,
+    Query(query): Query<WsQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let symbols = parse_symbols(query.symbols.as_deref())?;
+    let ws = ws.protocols(Encoding::ALL.map(Encoding::subprotocol));
+    let encoding = match query.encoding.as_deref() {
+        Some(name) => name
+            .parse()
+            .map_err(|e: anyhow::Error| ApiError::BadRequest(e.to_string()))?,
+        None => ws
+            .selected_protocol()
+            .and_then(|p| p.to_str().ok())
+            .and_then(Encoding::from_subprotocol)
+            .unwrap_or_default(),
+    };
+    let format = WireFormat {
+        encoding,
+        deflate: ws.deflate(),
+    };
+    let client = Client {
+        key: key.map(|Extension(AuthenticatedKey(name))| name),
+        format,
+        symbols,
+    };
+
+    // 连接纳入 TaskTracker，退出时等待关闭帧发送完毕
+    let tasks = state.tasks().clone();
+    Ok(ws.on_upgrade(move |socket| tasks.track_future(handle_socket(socket, state, client))))
+}
+
+/// 连接断开的原因，用作关闭帧的 reason、日志和 `ws_disconnects_total` 的标签。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Disconnect {
+    ClientClosed,
+    Shutdown,
+    /// `ws_idle_timeout_ms` 内没有收到任何消息（含 pong）
+    IdleTimeout,
+    /// 落后（消息在合并）超过 `ws_max_behind_ms`
+    SlowConsumer,
+    /// 被管理接口踢掉
+    Kicked,
+    SendFailed,
+    ReceiveError,
+}
+
+impl Disconnect {
+    fn as_str(self) -> &'static str {
+        match self {
+            Disconnect::ClientClosed => "client_closed",
+            Disconnect::Shutdown => "shutdown",
+            Disconnect::IdleTimeout => "idle_timeout",
+            Disconnect::SlowConsumer => "slow_consumer",
+            Disconnect::Kicked => "kicked",
+            Disconnect::SendFailed => "send_failed",
+            Disconnect::ReceiveError => "receive_error",
+        }
+    }
+
+    /// 由服务端主动断开时发给客户端的关闭帧。
+    fn close_frame(self) -> Option<CloseFrame> {
+        let (code, reason) = match self {
+            Disconnect::Shutdown => (CloseCode::Away, "server shutting down"),
+            Disconnect::IdleTimeout => (CloseCode::Policy, "idle timeout"),
+            Disconnect::SlowConsumer => (CloseCode::Again, "slow consumer"),
+            Disconnect::Kicked => (CloseCode::Policy, "kicked by admin"),
+            Disconnect::ClientClosed | Disconnect::SendFailed | Disconnect::ReceiveError => {
+                return None;
+            }
+        };
+        Some(CloseFrame {
+            code,
+            reason: reason.into(),
+        })
+    }
+}
+
+/// 连接建立前协商好的参数。
+struct Client {
+    key: Option<String>,
+    format: WireFormat,
+    symbols: HashSet<String>,
+}
+
+async fn handle_socket(socket: WebSocket, state: AppState, client: Client) {
+    let Client {
+        key,
+        format,
+        symbols,
+    } = client;
+    let mut listed: Vec<String> = symbols.iter().cloned().collect();
+    listed.sort();
+    let client = state.ws_clients().connect(key, format, listed);
+    let cfg = &state.config().stream;
+
+    // 写出放到单独的任务里，经有界队列交接，对端读得慢也不会卡住这里
+    let (sink, mut stream) = socket.split();
+    let (out, out_rx) = mpsc::channel(cfg.ws_send_queue);
+    let mut writer = tokio::spawn(write_loop(sink, out_rx, state.metrics().clone()));
+
+    let mut outbox = Outbox::new(out, format, state.metrics().clone());
+    let reason = run(&state, &mut stream, &mut outbox, &symbols, client.kicked()).await;
+    let id = client.id();
+    // 决定断开就不再计入连接数，下面只是收尾
+    drop(client);
+
+    state.metrics().ws_disconnected(reason.as_str());
+    match reason {
+        Disconnect::ClientClosed | Disconnect::Shutdown => {
+            tracing::debug!(
+                client = id,
+                reason = reason.as_str(),
+                "websocket client disconnected"
+            );
+        }
+        _ => tracing::info!(
+            client = id,
+            reason = reason.as_str(),
+            "websocket client disconnected"
+        ),
+    }
+
+    let close = reason.close_frame();
+    let server_closed = close.is_some();
+    if let Some(frame) = close {
+        let _ = outbox.sender().try_send(Message::Close(Some(frame)));
+    }
+    drop(outbox);
+    // 给写任务一点时间把关闭帧写出去并等客户端回关闭帧，超时就直接丢掉连接
+    let finished = timeout(Duration::from_secs(1), async {
+        let _ = (&mut writer).await;
+        if server_closed {
+            while let Some(Ok(_)) = stream.next().await {}
         }
+    })
+    .await;
+    if finished.is_err() {
+        writer.abort();
     }
+}
+
+async fn run(
+    state: &AppState,
+    stream: &mut SplitStream<WebSocket>,
+    outbox: &mut Outbox,
+    symbols: &HashSet<String>,
+    kicked: &CancellationToken,
+) -> Disconnect {
+    let cfg = &state.config().stream;
+    let max_behind = cfg.ws_max_behind();
+    let shutdown = state.shutdown_token().clone();
+    // 单独一个 sender 用来等队列空位，避免与 outbox 的可变借用冲突
+    let tx = outbox.sender().clone();
 
+    // 先订阅再取快照，之间发布的消息按 id 去重
     let mut rx = state.subscribe();
 
+    // Send latest of every symbol immediately if we have it.
+    let mut cursor = state.replay().last_id();
+    if outbox
+        .push_all(only(symbols, state.snapshot().await))
+        .is_err()
+    {
+        return Disconnect::SendFailed;
+    }
+
+    let mut last_seen = Instant::now();
+    let mut ping = interval_at(
+        Instant::now() + cfg.ws_ping_interval(),
+        cfg.ws_ping_interval(),
+    );
+
     loop {
+        if let Some(max) = max_behind
+            && outbox.behind_for() > max
+        {
+            return Disconnect::SlowConsumer;
+        }
         select! {
+            _ = shutdown.cancelled() => return Disconnect::Shutdown,
+            _ = kicked.cancelled() => return Disconnect::Kicked,
+            _ = ping.tick() => {
+                if last_seen.elapsed() >= cfg.ws_idle_timeout() {
+                    return Disconnect::IdleTimeout;
+                }
+                // 队列满时跳过这次 ping：存活只看客户端有没有消息过来
+                if let Err(TrySendError::Closed(_)) = tx.try_send(Message::Ping(Bytes::new())) {
+                    return Disconnect::SendFailed;
+                }
+            }
+            // 队列有空位时补发合并后的积压消息
+            permit = tx.reserve(), if outbox.is_behind() => {
+                let Ok(permit) = permit else {
+                    return Disconnect::SendFailed;
+                };
+                outbox.flush_one(permit);
+            }
             // Broadcast -> client
             msg = rx.recv() => {
-                match msg {
-                    Ok(update) => {
-                        let Ok(txt) = serde_json::to_string(&update) else { continue };
-                        if socket.send(Message::Text(txt.into())).await.is_err() {
-                            break;
+                let batch = match msg {
+                    Ok(published) if published.id <= cursor => continue,
+                    Ok(published) => vec![published],
+                    Err(RecvError::Lagged(n)) => {
+                        // 从回放缓冲区补上跳过的消息，补不上就整体发一次快照
+                        state.metrics().broadcast_lagged("ws", n);
+                        match state.replay().since(cursor) {
+                            Some(missed) => missed,
+                            None => state.snapshot().await,
                         }
                     }
-                    Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => {
-                        // skip missed messages
-                        continue;
-                    }
-                    Err(_) => break,
+                    Err(RecvError::Closed) => return Disconnect::Shutdown,
+                };
+                if let Some(last) = batch.last() {
+                    cursor = cursor.max(last.id);
+                }
+                if outbox.push_all(only(symbols, batch)).is_err() {
+                    return Disconnect::SendFailed;
                 }
             }
-            // Client -> server (we just drain; allow close)
-            incoming = socket.recv() => {
-                match incoming {
-                    Some(Ok(Message::Close(_))) | None => break,
-                    Some(Ok(_)) => continue,
-                    Some(Err(_)) => break,
+            // Client -> server: 任何消息都算活跃；目前只处理 resync 请求
+            incoming = stream.next() => {
+                let msg = match incoming {
+                    Some(Ok(msg)) => msg,
+                    None => return Disconnect::ClientClosed,
+                    Some(Err(_)) => return Disconnect::ReceiveError,
+                };
+                last_seen = Instant::now();
+                // 文本帧总按 JSON 解析，二进制帧按连接的编码解析
+                let decoded = match msg {
+                    Message::Text(txt) => Encoding::Json.decode(txt.as_bytes()),
+                    Message::Binary(data) => outbox.format().encoding.decode(&data),
+                    Message::Close(_) => return Disconnect::ClientClosed,
+                    Message::Ping(_) | Message::Pong(_) | Message::Frame(_) => continue,
+                };
+                let Ok(ClientMessage::Resync { symbol, after_seq }) = decoded else {
+                    continue;
+                };
+                if outbox.push_all(state.resync(&symbol, after_seq).await).is_err() {
+                    return Disconnect::SendFailed;
                 }
             }
         }
     }
 }
+
+/// 只保留订阅了的 symbol。
+fn only(symbols: &HashSet<String>, mut batch: Vec<Published>) -> Vec<Published> {
+    batch.retain(|p| wanted(symbols, &p.event));
+    batch
+}
+
+async fn write_loop(
+    mut sink: SplitSink<WebSocket, Message>,
+    mut rx: mpsc::Receiver<Message>,
+    metrics: Metrics,
+) {
+    while let Some(msg) = rx.recv().await {
+        let counted = matches!(
+            msg,
+            Message::Text(_) | Message::Binary(_) | Message::Frame(_)
+        );
+        if sink.send(msg).await.is_err() {
+            return;
+        }
+        if counted {
+            metrics.ws_message_sent();
+        }
+    }
+    let _ = sink.close().await;
+}
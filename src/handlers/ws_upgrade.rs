@@ -0,0 +1,397 @@
+use crate::error::ApiError;
+use axum::body::Body;
+use axum::extract::FromRequestParts;
+use axum::http::{HeaderMap, HeaderName, HeaderValue, Method, StatusCode, header, request::Parts};
+use axum::response::Response;
+use bytes::BytesMut;
+use flate2::{Decompress, FlushDecompress, Status};
+use hyper::upgrade::{OnUpgrade, Upgraded};
+use hyper_util::rt::TokioIo;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio_tungstenite::WebSocketStream;
+use tokio_tungstenite::tungstenite::handshake::derive_accept_key;
+use tokio_tungstenite::tungstenite::protocol::Role;
+
+/// 升级后的连接。
+pub type WebSocket = WebSocketStream<InflateIo<TokioIo<Upgraded>>>;
+
+/// 接受 permessage-deflate 时的响应：双方都不跨消息保留压缩上下文，
+/// 这样服务端每条消息只需压缩一次，所有连接共用同一份结果。
+pub const DEFLATE_RESPONSE: &str =
+    "permessage-deflate; server_no_context_takeover; client_no_context_takeover";
+
+/// 客户端单条压缩消息（压缩前后）的上限，与 tungstenite 默认的单帧上限一致。
+const MAX_MESSAGE: usize = 16 << 20;
+
+/// WebSocket 握手（HTTP/1.1）。
+///
+/// 用法与 axum 的 `WebSocketUpgrade` 相同，另外按 RFC 7692 协商 permessage-deflate：
+/// 客户端在 `Sec-WebSocket-Extensions` 中提供了可接受的参数时启用。
+pub struct WsUpgrade {
+    key: HeaderValue,
+    on_upgrade: OnUpgrade,
+    offered_protocols: Vec<String>,
+    protocol: Option<HeaderValue>,
+    deflate: bool,
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for WsUpgrade {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let bad = |msg: &str| ApiError::BadRequest(msg.to_string());
+        if parts.method != Method::GET {
+            return Err(bad("websocket upgrade must use GET"));
+        }
+        if !has_token(&parts.headers, header::CONNECTION, "upgrade")
+            || !has_token(&parts.headers, header::UPGRADE, "websocket")
+        {
+            return Err(bad("expected a websocket upgrade request"));
+        }
+        if parts
+            .headers
+            .get(header::SEC_WEBSOCKET_VERSION)
+            .is_none_or(|v| v != "13")
+        {
+            return Err(bad("unsupported websocket version, expected 13"));
+        }
+        let key = parts
+            .headers
+            .get(header::SEC_WEBSOCKET_KEY)
+            .cloned()
+            .ok_or_else(|| bad("missing sec-websocket-key"))?;
+        let on_upgrade = parts
+            .extensions
+            .remove::<OnUpgrade>()
+            .ok_or_else(|| bad("connection is not upgradable"))?;
+        let deflate = tokens(&parts.headers, header::SEC_WEBSOCKET_EXTENSIONS)
+            .any(|offer| accepts_deflate(&offer));
+        Ok(Self {
+            key,
+            on_upgrade,
+            offered_protocols: tokens(&parts.headers, header::SEC_WEBSOCKET_PROTOCOL).collect(),
+            protocol: None,
+            deflate,
+        })
+    }
+}
+
+impl WsUpgrade {
+    /// 从客户端提供的子协议中选第一个服务端支持的。
+    pub fn protocols(mut self, supported: impl IntoIterator<Item = &'static str>) -> Self {
+        let supported: Vec<_> = supported.into_iter().collect();
+        self.protocol = self
+            .offered_protocols
+            .iter()
+            .find(|p| supported.contains(&p.as_str()))
+            .and_then(|p| HeaderValue::from_str(p).ok());
+        self
+    }
+
+    pub fn selected_protocol(&self) -> Option<&HeaderValue> {
+        self.protocol.as_ref()
+    }
+
+    /// 是否协商了 permessage-deflate。
+    pub fn deflate(&self) -> bool {
+        self.deflate
+    }
+
+    /// 返回 101 响应，连接升级完成后在新任务中调用 `callback`。
+    pub fn on_upgrade<C, Fut>(self, callback: C) -> Response
+    where
+        C: FnOnce(WebSocket) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let deflate = self.deflate;
+        let on_upgrade = self.on_upgrade;
+        tokio::spawn(async move {
+            let upgraded = match on_upgrade.await {
+                Ok(upgraded) => upgraded,
+                Err(err) => {
+                    tracing::debug!(error = %err, "websocket upgrade failed");
+                    return;
+                }
+            };
+            let io = InflateIo::new(TokioIo::new(upgraded), deflate);
+            callback(WebSocketStream::from_raw_socket(io, Role::Server, None).await).await;
+        });
+
+        let mut resp = Response::new(Body::empty());
+        *resp.status_mut() = StatusCode::SWITCHING_PROTOCOLS;
+        let headers = resp.headers_mut();
+        headers.insert(header::CONNECTION, HeaderValue::from_static("upgrade"));
+        headers.insert(header::UPGRADE, HeaderValue::from_static("websocket"));
+        if let Ok(accept) = HeaderValue::from_str(&derive_accept_key(self.key.as_bytes())) {
+            headers.insert(header::SEC_WEBSOCKET_ACCEPT, accept);
+        }
+        if let Some(protocol) = self.protocol {
+            headers.insert(header::SEC_WEBSOCKET_PROTOCOL, protocol);
+        }
+        if deflate {
+            headers.insert(
+                header::SEC_WEBSOCKET_EXTENSIONS,
+                HeaderValue::from_static(DEFLATE_RESPONSE),
+            );
+        }
+        resp
+    }
+}
+
+/// 请求头中逗号分隔的各项，可能分布在多个同名头里。
+fn tokens(headers: &HeaderMap, name: HeaderName) -> impl Iterator<Item = String> + '_ {
+    headers
+        .get_all(name)
+        .into_iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+}
+
+fn has_token(headers: &HeaderMap, name: HeaderName, token: &str) -> bool {
+    tokens(headers, name).any(|t| t.eq_ignore_ascii_case(token))
+}
+
+/// 客户端的一个 permessage-deflate 提议能否接受。
+///
+/// 服务端压缩固定用 15 位窗口、每条消息独立压缩，所以只接受不限制或限制为 15 的 `server_max_window_bits`；
+/// 解压用 15 位窗口，任意 `client_max_window_bits` 都能处理。
+fn accepts_deflate(offer: &str) -> bool {
+    let mut params = offer.split(';').map(str::trim);
+    if !params
+        .next()
+        .is_some_and(|name| name.eq_ignore_ascii_case("permessage-deflate"))
+    {
+        return false;
+    }
+    params.all(|param| {
+        let (name, value) = match param.split_once('=') {
+            Some((name, value)) => (name.trim(), Some(value.trim().trim_matches('"'))),
+            None => (param, None),
+        };
+        let bits = |v: &str| v.parse::<u8>().is_ok_and(|b| (8..=15).contains(&b));
+        match name {
+            "server_no_context_takeover" | "client_no_context_takeover" => value.is_none(),
+            "client_max_window_bits" => value.is_none_or(bits),
+            "server_max_window_bits" => value == Some("15"),
+            _ => false,
+        }
+    })
+}
+
+/// 位于 tungstenite 之下：协商了 permessage-deflate 时，把客户端的压缩消息（首帧 RSV1）
+/// 解压并重新组成一个普通帧，其余帧原样透传。未协商时完全透传，RSV1 交给 tungstenite 拒绝。
+pub struct InflateIo<S> {
+    inner: S,
+    enabled: bool,
+    /// 从连接读到、还不够一个完整帧的字节
+    raw: BytesMut,
+    /// 处理好、等待 tungstenite 读取的字节
+    ready: BytesMut,
+    /// 正在接收的压缩消息：opcode 与去掉掩码后的负载
+    message: Option<(u8, Vec<u8>)>,
+    eof: bool,
+}
+
+impl<S> InflateIo<S> {
+    pub fn new(inner: S, enabled: bool) -> Self {
+        Self {
+            inner,
+            enabled,
+            raw: BytesMut::new(),
+            ready: BytesMut::new(),
+            message: None,
+            eof: false,
+        }
+    }
+
+    fn on_frame(&mut self, frame: BytesMut) -> io::Result<()> {
+        let (fin, rsv1, opcode) = (frame[0] & 0x80 != 0, frame[0] & 0x40 != 0, frame[0] & 0x0f);
+        let masked = frame[1] & 0x80 != 0;
+        match (opcode, &mut self.message) {
+            // 控制帧可以插在分片之间，且不压缩
+            (op, _) if op & 0x08 != 0 => {
+                self.ready.extend_from_slice(&frame);
+                return Ok(());
+            }
+            (0, Some((_, data))) if !rsv1 => {
+                data.extend_from_slice(&payload(&frame));
+                if data.len() > MAX_MESSAGE {
+                    return Err(invalid("compressed message too large"));
+                }
+            }
+            (1 | 2, None) if rsv1 => self.message = Some((opcode, payload(&frame))),
+            (_, None) if !rsv1 => {
+                self.ready.extend_from_slice(&frame);
+                return Ok(());
+            }
+            _ => return Err(invalid("unexpected frame in compressed message")),
+        }
+        if fin && let Some((opcode, data)) = self.message.take() {
+            let data = inflate(data)?;
+            write_frame(&mut self.ready, opcode, masked, &data);
+        }
+        Ok(())
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for InflateIo<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if !this.enabled {
+            return Pin::new(&mut this.inner).poll_read(cx, buf);
+        }
+        loop {
+            if !this.ready.is_empty() {
+                let n = this.ready.len().min(buf.remaining());
+                buf.put_slice(&this.ready.split_to(n));
+                return Poll::Ready(Ok(()));
+            }
+            if this.eof {
+                // 末尾不完整的帧原样交出去，由 tungstenite 报错
+                this.ready = this.raw.split();
+                if this.ready.is_empty() {
+                    return Poll::Ready(Ok(()));
+                }
+                continue;
+            }
+            let mut chunk = [0u8; 8192];
+            let mut read = ReadBuf::new(&mut chunk);
+            ready!(Pin::new(&mut this.inner).poll_read(cx, &mut read))?;
+            if read.filled().is_empty() {
+                this.eof = true;
+                continue;
+            }
+            this.raw.extend_from_slice(read.filled());
+            while let Some(frame) = take_frame(&mut this.raw)? {
+                this.on_frame(frame)?;
+            }
+        }
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for InflateIo<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
+
+/// 帧头长度与负载长度；字节不够时返回 `None`。
+fn frame_len(raw: &[u8]) -> Option<(usize, usize)> {
+    let b1 = *raw.get(1)?;
+    let (mut header, len) = match b1 & 0x7f {
+        126 => (
+            4,
+            u16::from_be_bytes(raw.get(2..4)?.try_into().ok()?) as usize,
+        ),
+        127 => (
+            10,
+            usize::try_from(u64::from_be_bytes(raw.get(2..10)?.try_into().ok()?))
+                .unwrap_or(usize::MAX),
+        ),
+        n => (2, n as usize),
+    };
+    if b1 & 0x80 != 0 {
+        header += 4;
+    }
+    Some((header, len))
+}
+
+fn take_frame(raw: &mut BytesMut) -> io::Result<Option<BytesMut>> {
+    let Some((header, len)) = frame_len(raw) else {
+        return Ok(None);
+    };
+    if len > MAX_MESSAGE {
+        return Err(invalid("frame too large"));
+    }
+    if raw.len() < header + len {
+        return Ok(None);
+    }
+    Ok(Some(raw.split_to(header + len)))
+}
+
+/// 去掉掩码后的负载。
+fn payload(frame: &[u8]) -> Vec<u8> {
+    let (header, _) = frame_len(frame).expect("complete frame");
+    let mut data = frame[header..].to_vec();
+    if frame[1] & 0x80 != 0 {
+        let key = &frame[header - 4..header];
+        for (i, b) in data.iter_mut().enumerate() {
+            *b ^= key[i % 4];
+        }
+    }
+    data
+}
+
+/// 单帧、无 RSV 的消息；客户端发来的帧保持带掩码（全零掩码），满足服务端对客户端帧的要求。
+fn write_frame(out: &mut BytesMut, opcode: u8, masked: bool, data: &[u8]) {
+    let mask = if masked { 0x80 } else { 0 };
+    out.extend_from_slice(&[0x80 | opcode]);
+    match data.len() {
+        n if n < 126 => out.extend_from_slice(&[mask | n as u8]),
+        n if n <= u16::MAX as usize => {
+            out.extend_from_slice(&[mask | 126]);
+            out.extend_from_slice(&(n as u16).to_be_bytes());
+        }
+        n => {
+            out.extend_from_slice(&[mask | 127]);
+            out.extend_from_slice(&(n as u64).to_be_bytes());
+        }
+    }
+    if masked {
+        out.extend_from_slice(&[0; 4]);
+    }
+    out.extend_from_slice(data);
+}
+
+/// 按 RFC 7692 解压一条消息：补上同步刷新的尾部 `00 00 ff ff` 后做 raw inflate。
+fn inflate(mut data: Vec<u8>) -> io::Result<Vec<u8>> {
+    data.extend_from_slice(&[0, 0, 0xff, 0xff]);
+    let mut inflater = Decompress::new(false);
+    let mut out = Vec::with_capacity(data.len() * 4);
+    loop {
+        if out.len() == out.capacity() {
+            out.reserve(out.capacity());
+        }
+        let consumed = inflater.total_in() as usize;
+        let status = inflater
+            .decompress_vec(&data[consumed..], &mut out, FlushDecompress::Sync)
+            .map_err(|e| invalid(&e.to_string()))?;
+        if out.len() > MAX_MESSAGE {
+            return Err(invalid("inflated message too large"));
+        }
+        let done = inflater.total_in() as usize == data.len() && out.len() < out.capacity();
+        match status {
+            Status::StreamEnd => return Ok(out),
+            _ if done => return Ok(out),
+            Status::BufError if out.len() < out.capacity() => {
+                return Err(invalid("truncated compressed message"));
+            }
+            _ => {}
+        }
+    }
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
@@ -1,20 +1,151 @@
+pub mod config;
+pub mod error;
 pub mod handlers;
 pub mod models;
+pub mod openapi;
 pub mod services;
 pub mod state;
 
 use axum::{
-    Router,
-    routing::{get, get_service},
+    Router, middleware,
+    routing::{delete, get, get_service, post, put},
 };
 use state::AppState;
+use std::future::{Future, IntoFuture};
+use tokio::net::TcpListener;
 use tower_http::services::ServeDir;
+use utoipa::OpenApi;
+use utoipa_swagger_ui::SwaggerUi;
 
 pub fn app(state: AppState) -> Router {
+    let static_dir = state.config().server.static_dir.clone();
     Router::new()
         .route("/", get(handlers::page::index))
+        .route("/health", get(handlers::health::health))
+        .route("/ready", get(handlers::health::ready))
+        .route("/api/status", get(handlers::health::status))
+        .route("/metrics", get(handlers::metrics::metrics))
         .route("/ws/prices", get(handlers::ws::ws_prices))
+        .route("/api/stream/prices", get(handlers::stream::stream_prices))
         .route("/api/klines/{symbol}", get(handlers::klines::get_klines))
-        .nest_service("/static", get_service(ServeDir::new("static")))
+        .route(
+            "/api/klines/{symbol}/export",
+            get(handlers::klines::export_klines),
+        )
+        .route(
+            "/api/symbols/search",
+            get(handlers::symbols::search_symbols),
+        )
+        .route("/api/markets", get(handlers::symbols::list_markets))
+        .route(
+            "/api/alerts",
+            get(handlers::alerts::list_alerts).post(handlers::alerts::create_alert),
+        )
+        .route("/api/alerts/{id}", delete(handlers::alerts::delete_alert))
+        .route(
+            "/api/watchlists",
+            get(handlers::watchlists::list_watchlists).post(handlers::watchlists::create_watchlist),
+        )
+        .route(
+            "/api/watchlists/{name}",
+            get(handlers::watchlists::get_watchlist).delete(handlers::watchlists::delete_watchlist),
+        )
+        .route(
+            "/api/watchlists/{name}/symbols",
+            post(handlers::watchlists::add_symbol).put(handlers::watchlists::reorder_symbols),
+        )
+        .route(
+            "/api/watchlists/{name}/symbols/{symbol}",
+            delete(handlers::watchlists::remove_symbol),
+        )
+        .route("/api/admin/poller", get(handlers::admin::get_poller))
+        .route(
+            "/api/admin/poller/interval",
+            put(handlers::admin::set_poll_interval),
+        )
+        .route(
+            "/api/admin/poller/symbols",
+            post(handlers::admin::add_poll_symbol),
+        )
+        .route(
+            "/api/admin/poller/symbols/{symbol}",
+            delete(handlers::admin::remove_poll_symbol),
+        )
+        .route(
+            "/api/admin/klines/cache",
+            delete(handlers::admin::flush_kline_cache),
+        )
+        .route("/api/admin/provider", put(handlers::admin::set_provider))
+        .route("/api/admin/clients", get(handlers::admin::list_clients))
+        .route(
+            "/api/admin/clients/{id}",
+            delete(handlers::admin::kick_client),
+        )
+        .route("/api/jobs", get(handlers::jobs::list_jobs))
+        .route("/api/jobs/{id}", get(handlers::jobs::get_job))
+        .route(
+            "/api/admin/jobs/backfill",
+            post(handlers::jobs::create_backfill),
+        )
+        .route("/api/admin/jobs/{id}", delete(handlers::jobs::cancel_job))
+        .merge(
+            SwaggerUi::new(openapi::DOCS_PATH).url(openapi::SPEC_PATH, openapi::ApiDoc::openapi()),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            handlers::auth::authenticate,
+        ))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            handlers::metrics::track_http,
+        ))
+        .nest_service("/static", get_service(ServeDir::new(static_dir)))
+        .layer(middleware::from_fn(error::request_id))
         .with_state(state)
 }
+
+/// 在 `listener` 上提供服务，`signal` 完成或 shutdown token 被取消后优雅退出。
+///
+/// 退出时停止接受新连接，等待进行中的请求、WebSocket 会话（收到 1001 关闭帧）与后台任务收尾，
+/// 最长等待 `server.shutdown_timeout_ms`，之后落盘告警和自选列表。返回是否在时限内收尾完毕。
+pub async fn serve(
+    state: AppState,
+    listener: TcpListener,
+    signal: impl Future<Output = ()> + Send + 'static,
+) -> anyhow::Result<bool> {
+    let shutdown = state.shutdown_token().clone();
+    let server = tokio::spawn(
+        axum::serve(listener, app(state.clone()).into_make_service())
+            .with_graceful_shutdown(async move {
+                tokio::select! {
+                    _ = signal => shutdown.cancel(),
+                    _ = shutdown.cancelled() => {}
+                }
+            })
+            .into_future(),
+    );
+
+    state.shutdown_token().cancelled().await;
+    let deadline = state.config().server.shutdown_timeout();
+    tracing::info!(?deadline, "shutting down");
+
+    // 取消后：轮询停止，WebSocket 客户端收到关闭帧，HTTP 不再接受新连接
+    let drained = tokio::time::timeout(deadline, async {
+        if let Ok(Err(err)) = server.await {
+            tracing::warn!(error = %err, "server error during shutdown");
+        }
+        state.tasks().close();
+        state.tasks().wait().await;
+    })
+    .await
+    .is_ok();
+    if !drained {
+        tracing::warn!(
+            remaining = state.tasks().len(),
+            "shutdown deadline exceeded, exiting anyway"
+        );
+    }
+
+    state.flush().await?;
+    Ok(drained)
+}
@@ -1,46 +1,210 @@
-use axum::Router;
-use std::net::SocketAddr;
+use chrono::Utc;
+use clap::Parser;
+use showmarket::config::{BackfillArgs, Cli, Command, Config};
+use showmarket::models::job::BackfillSpec;
+use showmarket::models::market::HolidayCalendar;
+use showmarket::services::backfill;
+use showmarket::services::securities::spawn_security_refresh;
+use showmarket::services::staleness::StalenessTracker;
+use showmarket::services::webhook::{WebhookConfig, WebhookSink, spawn_webhook_sink};
 use std::time::Duration;
 use tokio::net::TcpListener;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+    let config = Config::load(&cli)?;
+    if cli.print_config {
+        print!("{}", config.to_toml_redacted()?);
+        return Ok(());
+    }
+
     tracing_subscriber::registry()
         .with(
+            // RUST_LOG 优先，便于临时调试
             tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "showmarket=info,tower_http=info,axum=info".into()),
+                .unwrap_or_else(|_| config.log.filter.as_str().into()),
         )
         .with(tracing_subscriber::fmt::layer())
         .init();
 
-    let state = showmarket::state::AppState::new();
+    if let Some(Command::Backfill(args)) = &cli.command {
+        return run_backfill(config, args).await;
+    }
+
+    let state = showmarket::state::AppState::new(config.clone())?;
     spawn_binance_price_task(state.clone());
+    showmarket::services::alerts::spawn_alert_engine(state.clone());
+    backfill::resume_jobs(&state).await;
+    showmarket::services::quotes::spawn_reconciler(state.clone());
+    spawn_security_refresh(
+        state.securities().clone(),
+        state.ashare().clone(),
+        Duration::from_secs(config.securities.refresh_interval_secs),
+        config.securities.min_refresh_ratio,
+    );
+    let dead_letters = config.server.data_dir.join("webhook_dead_letters.jsonl");
+    if let Some(cfg) = WebhookConfig::from_settings(&config.webhook, dead_letters) {
+        tracing::info!(urls = ?cfg.urls, "webhook notifications enabled");
+        spawn_webhook_sink(state.clone(), WebhookSink::new(cfg));
+    }
 
-    let app: Router = showmarket::app(state);
-    let addr = SocketAddr::from(([127, 0, 0, 1], 3000));
+    let addr = config.server.bind;
     let listener = TcpListener::bind(addr).await?;
     tracing::info!("listening on {}", addr);
-    axum::serve(listener, app.into_make_service()).await?;
+
+    if let Err(err) = showmarket::serve(state, listener, shutdown_signal()).await {
+        tracing::error!(error = %err, "failed to flush stores on shutdown");
+    }
+    tracing::info!("bye");
+    Ok(())
+}
+
+/// `showmarket backfill`：不启动 HTTP 服务，前台执行下载任务并打印进度。
+///
+/// 与服务共用 `data_dir`，不要在服务运行时对同一目录执行。
+async fn run_backfill(config: Config, args: &BackfillArgs) -> anyhow::Result<()> {
+    let state = showmarket::state::AppState::new(config)?;
+    let ids = if args.symbols.is_empty() {
+        let ids = state.jobs().unfinished().await;
+        if ids.is_empty() {
+            println!("no unfinished backfill jobs");
+        }
+        ids
+    } else {
+        let spec = BackfillSpec {
+            symbols: args.symbols.clone(),
+            intervals: args.intervals.clone(),
+            adjust: args.adjust,
+            start: args.start,
+            end: args.end,
+        };
+        let tasks = backfill::plan(&spec, state.securities()).await?;
+        vec![state.jobs().submit(spec, tasks).await?.id]
+    };
+
+    let shutdown = state.shutdown_token().clone();
+    tokio::spawn(async move {
+        shutdown_signal().await;
+        shutdown.cancel();
+    });
+    for id in ids {
+        let run = backfill::run_job(&state, id);
+        tokio::pin!(run);
+        let mut progress = tokio::time::interval(Duration::from_secs(5));
+        let job = loop {
+            tokio::select! {
+                job = &mut run => break job?,
+                _ = progress.tick() => {
+                    if let Some(job) = state.jobs().get(id).await {
+                        println!(
+                            "job {id}: {}/{} windows, {} bars",
+                            job.windows_done, job.windows_total, job.bars
+                        );
+                    }
+                }
+            }
+        };
+        if state.shutdown_token().is_cancelled() {
+            println!("interrupted; run `showmarket backfill` again to resume");
+            break;
+        }
+        if let Some(job) = job {
+            println!(
+                "job {id}: {:?}, {} bars in {} files",
+                job.state,
+                job.bars,
+                job.tasks.len()
+            );
+            for task in job.tasks.iter().filter(|t| t.error.is_some()) {
+                println!(
+                    "  {} {}: {}",
+                    task.symbol,
+                    task.interval,
+                    task.error.as_deref().unwrap_or_default()
+                );
+            }
+        }
+    }
     Ok(())
 }
 
+/// 等待 Ctrl-C（SIGINT）或 SIGTERM。
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(err) = tokio::signal::ctrl_c().await {
+            tracing::error!(error = %err, "failed to listen for ctrl-c");
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut sig) => {
+                sig.recv().await;
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "failed to listen for SIGTERM");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => tracing::info!("received SIGINT"),
+        _ = terminate => tracing::info!("received SIGTERM"),
+    }
+}
+
 fn spawn_binance_price_task(state: showmarket::state::AppState) {
-    let svc = showmarket::services::ashare::AshareService::new();
     tokio::spawn(async move {
-        // 先只推送上证指数，避免价格在多个标的之间来回闪烁。
-        let symbols = vec!["000001.SH".to_string()];
-        let mut interval = tokio::time::interval(Duration::from_millis(800));
+        let mut period = state.poller().watch_interval();
+        let mut interval = tokio::time::interval(*period.borrow_and_update());
+        let shutdown = state.shutdown_token().clone();
+        let mut staleness = StalenessTracker::new(
+            state.config().poller.stale_after_ms,
+            HolidayCalendar::new(&state.config().calendar.holidays),
+        );
 
         loop {
-            interval.tick().await;
+            tokio::select! {
+                _ = interval.tick() => {}
+                // 管理接口改了间隔：重建定时器，从下一个完整间隔开始
+                Ok(()) = period.changed() => {
+                    let every = *period.borrow_and_update();
+                    interval = tokio::time::interval_at(tokio::time::Instant::now() + every, every);
+                    continue;
+                }
+                _ = shutdown.cancelled() => break,
+            }
+            // 每轮重新取一次，自选列表与管理接口的增删会在下一轮生效
+            let mut symbols = state.poller().symbols();
+            for sym in state.watchlists().all_symbols().await {
+                if !symbols.contains(&sym) {
+                    symbols.push(sym);
+                }
+            }
             for sym in &symbols {
-                match svc.fetch_realtime_quote(sym).await {
-                    Ok(update) => {
+                match state.quotes().fetch_quote(sym).await {
+                    Ok(mut update) => {
+                        state.poll_status().record_ok(sym, update.ts_ms);
+                        let change = staleness.observe(&mut update, Utc::now());
                         state.set_latest(update).await;
+                        if let Some(event) = change {
+                            state.publish(event);
+                        }
                     }
                     Err(err) => {
+                        state.poll_status().record_err(sym, &err);
                         tracing::warn!(%sym, error = %err, "failed to fetch realtime quote");
+                        if let Some(event) = staleness.check_failed(sym, Utc::now()) {
+                            state.set_stale(sym, true).await;
+                            state.publish(event);
+                        }
                     }
                 }
             }
@@ -1,2 +1,11 @@
+pub mod admin;
+pub mod alert;
+pub mod encoding;
+pub mod event;
+pub mod job;
 pub mod kline;
+pub mod market;
 pub mod price;
+pub mod security;
+pub mod status;
+pub mod watchlist;
@@ -0,0 +1,50 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// `GET /api/admin/clients` 中的一个 WebSocket 连接。
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct WsClientInfo {
+    pub id: u64,
+    /// 建立连接所用的 API key 名称，未开启鉴权时为空
+    pub key: Option<String>,
+    pub encoding: &'static str,
+    /// `permessage-deflate` 或 `none`
+    pub compression: &'static str,
+    /// 订阅的 symbol，为空表示全部
+    pub symbols: Vec<String>,
+    pub connected_at_ms: i64,
+}
+
+/// `GET /api/admin/poller` 的响应。
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct PollerSettings {
+    pub interval_ms: u64,
+    /// 除自选列表外始终轮询的 symbol
+    pub symbols: Vec<String>,
+    /// 实际轮询的全部 symbol（含自选列表）
+    pub effective_symbols: Vec<String>,
+}
+
+/// `PUT /api/admin/poller/interval` 的请求体。
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct SetInterval {
+    pub interval_ms: u64,
+}
+
+/// `POST /api/admin/poller/symbols` 的请求体。
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct PollSymbol {
+    pub symbol: String,
+}
+
+/// `PUT /api/admin/provider` 的请求体，`auto` 恢复自动主备切换。
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct SetProvider {
+    pub provider: String,
+}
+
+/// `DELETE /api/admin/klines/cache` 的响应。
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct CacheFlushed {
+    pub flushed: usize,
+}
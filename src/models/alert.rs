@@ -0,0 +1,86 @@
+use crate::models::price::PriceUpdate;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// 告警触发条件。
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AlertRule {
+    /// 价格上穿 `level`
+    CrossAbove { level: f64 },
+    /// 价格下穿 `level`
+    CrossBelow { level: f64 },
+    /// 涨跌幅高于 `pct`（百分比，相对昨收）
+    ChangePctAbove { pct: f64 },
+    /// 涨跌幅低于 `pct`（百分比，相对昨收）
+    ChangePctBelow { pct: f64 },
+    /// 价格离开布林带：最近 `period` 个报价的均值 ± `k` 倍标准差
+    BollingerBreak { period: usize, k: f64 },
+}
+
+impl AlertRule {
+    pub fn validate(&self) -> Result<(), String> {
+        let finite = |v: f64, name: &str| {
+            if v.is_finite() {
+                Ok(())
+            } else {
+                Err(format!("{name} must be a finite number"))
+            }
+        };
+        match self {
+            AlertRule::CrossAbove { level } | AlertRule::CrossBelow { level } => {
+                finite(*level, "level")
+            }
+            AlertRule::ChangePctAbove { pct } | AlertRule::ChangePctBelow { pct } => {
+                finite(*pct, "pct")
+            }
+            AlertRule::BollingerBreak { period, k } => {
+                if *period < 2 {
+                    return Err("period must be at least 2".to_string());
+                }
+                if !(k.is_finite() && *k > 0.0) {
+                    return Err("k must be a positive number".to_string());
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct Alert {
+    pub id: u64,
+    pub symbol: String,
+    pub rule: AlertRule,
+    /// 条件需要持续满足多久才触发（ms），用于过滤瞬时抖动
+    #[serde(default)]
+    pub debounce_ms: u64,
+    /// 触发后至少间隔多久才允许再次触发（ms）；条件解除后才会重新布防
+    #[serde(default)]
+    pub cooldown_ms: u64,
+    /// Unix timestamp (ms)
+    pub created_ts_ms: i64,
+}
+
+/// `POST /api/alerts` 的请求体。
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct NewAlert {
+    pub symbol: String,
+    pub rule: AlertRule,
+    #[serde(default)]
+    pub debounce_ms: u64,
+    #[serde(default)]
+    pub cooldown_ms: u64,
+}
+
+/// 告警触发事件。
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct AlertFired {
+    pub alert_id: u64,
+    pub symbol: String,
+    pub rule: AlertRule,
+    /// 触发告警的那条报价
+    pub update: PriceUpdate,
+    /// Unix timestamp (ms)
+    pub ts_ms: i64,
+}
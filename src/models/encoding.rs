@@ -0,0 +1,138 @@
+use anyhow::{Context, bail};
+use bytes::Bytes;
+use flate2::{Compression, write::DeflateEncoder};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::io::Write;
+use std::str::FromStr;
+use std::sync::{Arc, OnceLock};
+
+/// 推送消息的编码，每个 WebSocket 连接建立时协商一次。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Encoding {
+    /// 文本帧，与早期客户端兼容
+    #[default]
+    Json,
+    /// MessagePack（字段名保留），二进制帧
+    Msgpack,
+    /// CBOR，二进制帧
+    Cbor,
+}
+
+impl Encoding {
+    pub const ALL: [Encoding; 3] = [Encoding::Json, Encoding::Msgpack, Encoding::Cbor];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::Json => "json",
+            Encoding::Msgpack => "msgpack",
+            Encoding::Cbor => "cbor",
+        }
+    }
+
+    /// 对应的 `Sec-WebSocket-Protocol`，如 `showmarket.msgpack`。
+    pub fn subprotocol(self) -> &'static str {
+        match self {
+            Encoding::Json => "showmarket.json",
+            Encoding::Msgpack => "showmarket.msgpack",
+            Encoding::Cbor => "showmarket.cbor",
+        }
+    }
+
+    pub fn from_subprotocol(protocol: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|e| e.subprotocol() == protocol)
+    }
+
+    /// 解码客户端发来的消息（不压缩）。
+    pub fn decode<T: DeserializeOwned>(self, data: &[u8]) -> anyhow::Result<T> {
+        Ok(match self {
+            Encoding::Json => serde_json::from_slice(data)?,
+            Encoding::Msgpack => rmp_serde::from_slice(data)?,
+            Encoding::Cbor => ciborium::from_reader(data)?,
+        })
+    }
+}
+
+impl FromStr for Encoding {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "json" => Ok(Encoding::Json),
+            "msgpack" | "messagepack" => Ok(Encoding::Msgpack),
+            "cbor" => Ok(Encoding::Cbor),
+            other => bail!("unsupported encoding {other}, expected json, msgpack or cbor"),
+        }
+    }
+}
+
+/// 一个连接上消息的最终格式：编码 + 是否协商了 permessage-deflate。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WireFormat {
+    pub encoding: Encoding,
+    /// RFC 7692 permessage-deflate，每条消息独立压缩（不保留上下文）
+    pub deflate: bool,
+}
+
+impl WireFormat {
+    pub fn compression(self) -> &'static str {
+        if self.deflate {
+            "permessage-deflate"
+        } else {
+            "none"
+        }
+    }
+
+    pub fn encode(self, value: &impl Serialize) -> anyhow::Result<Bytes> {
+        let raw = match self.encoding {
+            Encoding::Json => serde_json::to_vec(value)?,
+            Encoding::Msgpack => rmp_serde::to_vec_named(value)?,
+            Encoding::Cbor => {
+                let mut buf = Vec::new();
+                ciborium::into_writer(value, &mut buf)?;
+                buf
+            }
+        };
+        if !self.deflate {
+            return Ok(raw.into());
+        }
+        // RFC 7692：同步刷新后去掉末尾的 00 00 ff ff
+        let mut enc = DeflateEncoder::new(Vec::with_capacity(raw.len() / 2), Compression::fast());
+        enc.write_all(&raw).context("deflate failed")?;
+        enc.flush().context("deflate failed")?;
+        let mut out = std::mem::take(enc.get_mut());
+        if out.ends_with(&[0, 0, 0xff, 0xff]) {
+            out.truncate(out.len() - 4);
+        }
+        Ok(out.into())
+    }
+
+    fn slot(self) -> usize {
+        let encoding = match self.encoding {
+            Encoding::Json => 0,
+            Encoding::Msgpack => 1,
+            Encoding::Cbor => 2,
+        };
+        encoding * 2 + usize::from(self.deflate)
+    }
+}
+
+/// 一条消息在各格式下的编码结果。随消息 clone 共享，
+/// 同一条广播无论有多少连接，每种格式只序列化一次。
+#[derive(Debug, Clone, Default)]
+pub struct EncodedFrames(Arc<[OnceLock<Option<Bytes>>; 6]>);
+
+impl EncodedFrames {
+    /// 取 `format` 下的编码结果，第一次调用时序列化 `value`；序列化失败返回 `None`。
+    pub fn get_or_encode(&self, format: WireFormat, value: &impl Serialize) -> Option<Bytes> {
+        self.0[format.slot()]
+            .get_or_init(|| match format.encode(value) {
+                Ok(bytes) => Some(bytes),
+                Err(err) => {
+                    tracing::warn!(error = %err, encoding = format.encoding.name(), "encode stream message failed");
+                    None
+                }
+            })
+            .clone()
+    }
+}
@@ -0,0 +1,115 @@
+use crate::models::alert::AlertFired;
+use crate::models::encoding::{EncodedFrames, WireFormat};
+use crate::models::price::PriceUpdate;
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// 推送给 WebSocket 客户端的消息，按 `type` 字段区分。
+///
+/// 价格消息仍保留原有的 `symbol` / `price` / `ts_ms` 字段，旧客户端不受影响。
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StreamEvent {
+    Price(PriceUpdate),
+    Alert(AlertFired),
+    /// 某 symbol 的报价变旧
+    Stale(Freshness),
+    /// 之前变旧的 symbol 重新收到新报价
+    Recovered(Freshness),
+    /// 请求补发的消息已不在回放缓冲区时，单独发给该客户端的最新状态；不会广播
+    Snapshot(SymbolSnapshot),
+}
+
+/// `stale` / `recovered` 事件的内容。
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct Freshness {
+    pub symbol: String,
+    /// 最近一次看到报价前进（价格或源时间变化）的时间
+    pub last_fresh_ts_ms: i64,
+    /// 状态变化的时间
+    pub ts_ms: i64,
+}
+
+/// `snapshot` 消息的内容，收到后以其 `seq` 作为该 symbol 的新起点。
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct SymbolSnapshot {
+    pub symbol: String,
+    /// 还没有报价时为空
+    pub latest: Option<PriceUpdate>,
+}
+
+impl StreamEvent {
+    /// 与序列化时的 `type` 字段一致。
+    pub fn kind(&self) -> &'static str {
+        match self {
+            StreamEvent::Price(_) => "price",
+            StreamEvent::Alert(_) => "alert",
+            StreamEvent::Stale(_) => "stale",
+            StreamEvent::Recovered(_) => "recovered",
+            StreamEvent::Snapshot(_) => "snapshot",
+        }
+    }
+
+    pub fn symbol(&self) -> &str {
+        match self {
+            StreamEvent::Price(update) => &update.symbol,
+            StreamEvent::Alert(fired) => &fired.symbol,
+            StreamEvent::Stale(f) | StreamEvent::Recovered(f) => &f.symbol,
+            StreamEvent::Snapshot(s) => &s.symbol,
+        }
+    }
+}
+
+/// 广播通道里的一条消息：事件加上发布时分配的全局 id 和按 symbol 递增的序号。
+///
+/// id 即 SSE 的 `id:` 字段，客户端重连时通过 `Last-Event-ID` 带回；
+/// `seq` 随消息一起下发，客户端发现不连续时可请求重新同步。
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Published {
+    #[serde(skip)]
+    pub id: u64,
+    pub seq: u64,
+    #[serde(flatten)]
+    pub event: StreamEvent,
+    /// 客户端积压时，该 symbol 序号在 `[conflated_from, seq)` 之间的消息已被合并掉，
+    /// 这段跳号是预期的，无需 resync。只出现在 WebSocket 合并后补发的消息上
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub conflated_from: Option<u64>,
+    /// 各编码下序列化好的消息，所有连接共享
+    #[serde(skip)]
+    pub frames: EncodedFrames,
+}
+
+impl Published {
+    pub fn new(id: u64, seq: u64, event: StreamEvent) -> Self {
+        Self {
+            id,
+            seq,
+            event,
+            conflated_from: None,
+            frames: EncodedFrames::default(),
+        }
+    }
+
+    /// 标上 `conflated_from` 的副本；它只发给一个连接，不共享已编码的结果。
+    pub fn conflated_from(&self, from: u64) -> Self {
+        Self {
+            conflated_from: Some(from),
+            ..Self::new(self.id, self.seq, self.event.clone())
+        }
+    }
+
+    /// 按 `format` 编码后的消息，同一条消息每种格式只序列化一次。
+    pub fn encoded(&self, format: WireFormat) -> Option<Bytes> {
+        self.frames.get_or_encode(format, self)
+    }
+}
+
+/// 客户端发给 `/ws/prices` 的消息。
+#[derive(Debug, Clone, Deserialize, PartialEq, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    /// 补发 `symbol` 序号 `after_seq` 之后的消息
+    Resync { symbol: String, after_seq: u64 },
+}
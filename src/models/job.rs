@@ -0,0 +1,87 @@
+use crate::models::kline::Adjust;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum JobState {
+    /// 已创建，尚未开始
+    Queued,
+    /// 下载中；进程退出时处于该状态的任务在下次启动时续传
+    Running,
+    Completed,
+    /// 至少有一个 (symbol, interval) 重试耗尽
+    Failed,
+    Cancelled,
+}
+
+impl JobState {
+    pub fn is_finished(self) -> bool {
+        matches!(
+            self,
+            JobState::Completed | JobState::Failed | JobState::Cancelled
+        )
+    }
+}
+
+/// `POST /api/admin/jobs/backfill` 的请求体。
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BackfillSpec {
+    pub symbols: Vec<String>,
+    /// 缺省 `["1d"]`
+    #[serde(default)]
+    pub intervals: Vec<String>,
+    #[serde(default)]
+    pub adjust: Adjust,
+    /// 起始日期（含），缺省为上市日期（分钟线为最近 30 天）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start: Option<NaiveDate>,
+    /// 结束日期（含），缺省为交易所当地的今天
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end: Option<NaiveDate>,
+}
+
+/// 任务中的一个 (symbol, interval)，按窗口顺序下载。
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BackfillTask {
+    pub symbol: String,
+    pub interval: String,
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    /// 已写入本地的最后一个窗口的结束日期，续传从其次日开始
+    pub cursor: Option<NaiveDate>,
+    pub windows_done: usize,
+    pub windows_total: usize,
+    pub bars: u64,
+    /// 本地分片中已确认的字节数，续传前截断到此长度，丢弃崩溃时写了一半的窗口；任务结束后清零
+    pub committed_bytes: u64,
+    pub state: JobState,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// `GET /api/jobs/{id}` 的响应，同时是 `data/jobs.json` 中保存的记录。
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Job {
+    pub id: u64,
+    pub spec: BackfillSpec,
+    pub state: JobState,
+    pub windows_done: usize,
+    pub windows_total: usize,
+    pub bars: u64,
+    pub tasks: Vec<BackfillTask>,
+    pub created_at_ms: i64,
+    pub updated_at_ms: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finished_at_ms: Option<i64>,
+}
+
+impl Job {
+    /// 按各 task 重新汇总进度。
+    pub fn refresh_progress(&mut self) {
+        self.windows_done = self.tasks.iter().map(|t| t.windows_done).sum();
+        self.windows_total = self.tasks.iter().map(|t| t.windows_total).sum();
+        self.bars = self.tasks.iter().map(|t| t.bars).sum();
+    }
+}
@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct Kline {
     pub open_time: i64,
     pub open: f64,
@@ -8,4 +9,62 @@ pub struct Kline {
     pub low: f64,
     pub close: f64,
     pub volume: f64,
+    /// 成交额
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub amount: Option<f64>,
+    /// 振幅（%）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub amplitude_pct: Option<f64>,
+    /// 涨跌幅（%）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub change_pct: Option<f64>,
+    /// 涨跌额
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub change: Option<f64>,
+    /// 换手率（%）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub turnover_pct: Option<f64>,
+}
+
+/// 复权方式。`/api/klines` 固定为前复权。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Adjust {
+    /// 不复权
+    None,
+    /// 前复权
+    #[default]
+    Forward,
+    /// 后复权
+    Backward,
+}
+
+impl Adjust {
+    pub fn name(self) -> &'static str {
+        match self {
+            Adjust::None => "none",
+            Adjust::Forward => "forward",
+            Adjust::Backward => "backward",
+        }
+    }
+
+    /// 是否为复权价格。
+    pub fn is_adjusted(self) -> bool {
+        self != Adjust::None
+    }
+}
+
+impl std::str::FromStr for Adjust {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Adjust::None),
+            "forward" => Ok(Adjust::Forward),
+            "backward" => Ok(Adjust::Backward),
+            other => Err(format!(
+                "unsupported adjust {other}, expected none, forward or backward"
+            )),
+        }
+    }
 }
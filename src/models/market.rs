@@ -0,0 +1,224 @@
+use chrono::{DateTime, Datelike, NaiveDate, Timelike, Utc, Weekday};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use utoipa::ToSchema;
+
+/// 交易市场。symbol 的后缀决定市场，如 "600000.SH"、"00700.HK"、"AAPL.O"。
+///
+/// 美股沿用万得的后缀习惯：`.O` 纳斯达克、`.N` 纽交所、`.A` 美交所。
+#[derive(
+    Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, ToSchema,
+)]
+pub enum Market {
+    /// 上交所
+    SH,
+    /// 深交所
+    SZ,
+    /// 北交所
+    BJ,
+    /// 港交所
+    HK,
+    /// 纳斯达克
+    O,
+    /// 纽交所
+    N,
+    /// 美交所
+    A,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+pub enum Currency {
+    CNY,
+    HKD,
+    USD,
+}
+
+/// 一个连续交易时段，交易所当地时间，自 0 点起的分钟数。
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq, ToSchema)]
+pub struct Session {
+    pub open_min: u32,
+    pub close_min: u32,
+}
+
+const fn session(open_h: u32, open_m: u32, close_h: u32, close_m: u32) -> Session {
+    Session {
+        open_min: open_h * 60 + open_m,
+        close_min: close_h * 60 + close_m,
+    }
+}
+
+const CN_SESSIONS: &[Session] = &[session(9, 30, 11, 30), session(13, 0, 15, 0)];
+const HK_SESSIONS: &[Session] = &[session(9, 30, 12, 0), session(13, 0, 16, 0)];
+const US_SESSIONS: &[Session] = &[session(9, 30, 16, 0)];
+
+impl Market {
+    pub const ALL: [Market; 7] = [
+        Market::SH,
+        Market::SZ,
+        Market::BJ,
+        Market::HK,
+        Market::O,
+        Market::N,
+        Market::A,
+    ];
+
+    pub fn suffix(self) -> &'static str {
+        match self {
+            Market::SH => "SH",
+            Market::SZ => "SZ",
+            Market::BJ => "BJ",
+            Market::HK => "HK",
+            Market::O => "O",
+            Market::N => "N",
+            Market::A => "A",
+        }
+    }
+
+    pub fn from_suffix(s: &str) -> Option<Self> {
+        Market::ALL.into_iter().find(|m| m.suffix() == s)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Market::SH => "上海证券交易所",
+            Market::SZ => "深圳证券交易所",
+            Market::BJ => "北京证券交易所",
+            Market::HK => "香港交易所",
+            Market::O => "NASDAQ",
+            Market::N => "NYSE",
+            Market::A => "NYSE American",
+        }
+    }
+
+    /// 东方财富 secid 中的市场编号。
+    pub fn em_market(self) -> u32 {
+        match self {
+            Market::SH => 1,
+            Market::SZ | Market::BJ => 0,
+            Market::HK => 116,
+            Market::O => 105,
+            Market::N => 106,
+            Market::A => 107,
+        }
+    }
+
+    /// 行情接口未返回小数位数（f59）时使用的默认价格精度。
+    pub fn price_decimals(self) -> u32 {
+        match self {
+            Market::SH | Market::SZ | Market::BJ => 2,
+            Market::HK | Market::O | Market::N | Market::A => 3,
+        }
+    }
+
+    pub fn currency(self) -> Currency {
+        match self {
+            Market::SH | Market::SZ | Market::BJ => Currency::CNY,
+            Market::HK => Currency::HKD,
+            Market::O | Market::N | Market::A => Currency::USD,
+        }
+    }
+
+    pub fn timezone(self) -> Tz {
+        match self {
+            Market::SH | Market::SZ | Market::BJ => chrono_tz::Asia::Shanghai,
+            Market::HK => chrono_tz::Asia::Hong_Kong,
+            Market::O | Market::N | Market::A => chrono_tz::America::New_York,
+        }
+    }
+
+    /// 常规交易时段（不含盘前盘后与集合竞价）。
+    pub fn sessions(self) -> &'static [Session] {
+        match self {
+            Market::SH | Market::SZ | Market::BJ => CN_SESSIONS,
+            Market::HK => HK_SESSIONS,
+            Market::O | Market::N | Market::A => US_SESSIONS,
+        }
+    }
+
+    /// `at` 是否处于交易时段内。只排除周末，节假日见 [`HolidayCalendar`]。
+    pub fn is_trading(self, at: DateTime<Utc>) -> bool {
+        let local = at.with_timezone(&self.timezone());
+        if matches!(local.weekday(), Weekday::Sat | Weekday::Sun) {
+            return false;
+        }
+        let minute = local.hour() * 60 + local.minute();
+        self.sessions()
+            .iter()
+            .any(|s| minute >= s.open_min && minute < s.close_min)
+    }
+
+    fn is_valid_code(self, code: &str) -> bool {
+        let digits = |n: usize| code.len() == n && code.bytes().all(|b| b.is_ascii_digit());
+        match self {
+            Market::SH | Market::SZ | Market::BJ => digits(6),
+            Market::HK => digits(5),
+            Market::O | Market::N | Market::A => {
+                (1..=10).contains(&code.len())
+                    && code
+                        .bytes()
+                        .all(|b| b.is_ascii_uppercase() || b.is_ascii_digit() || b == b'.')
+            }
+        }
+    }
+}
+
+/// 各市场的休市日（交易所当地日期），来自配置 `calendar.holidays`。
+#[derive(Debug, Clone, Default)]
+pub struct HolidayCalendar {
+    holidays: HashMap<Market, HashSet<NaiveDate>>,
+}
+
+impl HolidayCalendar {
+    pub fn new(holidays: &BTreeMap<Market, Vec<NaiveDate>>) -> Self {
+        Self {
+            holidays: holidays
+                .iter()
+                .map(|(market, dates)| (*market, dates.iter().copied().collect()))
+                .collect(),
+        }
+    }
+
+    pub fn is_holiday(&self, market: Market, date: NaiveDate) -> bool {
+        self.holidays
+            .get(&market)
+            .is_some_and(|dates| dates.contains(&date))
+    }
+
+    /// `at` 是否处于交易时段内，且当地日期不是休市日。
+    pub fn is_trading(&self, market: Market, at: DateTime<Utc>) -> bool {
+        let date = at.with_timezone(&market.timezone()).date_naive();
+        !self.is_holiday(market, date) && market.is_trading(at)
+    }
+}
+
+/// 拆分 "600000.SH" 为 ("600000", SH)，并按市场校验代码格式。
+pub fn parse_symbol(symbol: &str) -> Option<(&str, Market)> {
+    let (code, suffix) = symbol.rsplit_once('.')?;
+    let market = Market::from_suffix(suffix)?;
+    market.is_valid_code(code).then_some((code, market))
+}
+
+/// `GET /api/markets` 中的一项。
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct MarketInfo {
+    pub market: Market,
+    pub name: &'static str,
+    pub currency: Currency,
+    pub timezone: &'static str,
+    pub price_decimals: u32,
+    pub sessions: &'static [Session],
+}
+
+impl From<Market> for MarketInfo {
+    fn from(market: Market) -> Self {
+        Self {
+            market,
+            name: market.name(),
+            currency: market.currency(),
+            timezone: market.timezone().name(),
+            price_decimals: market.price_decimals(),
+            sessions: market.sessions(),
+        }
+    }
+}
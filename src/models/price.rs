@@ -1,11 +1,30 @@
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
 pub struct PriceUpdate {
     pub symbol: String,
     pub price: f64,
-    /// Unix timestamp (ms)
+    /// 本服务收到报价的时间，Unix timestamp (ms)
     pub ts_ms: i64,
+    /// 昨收价，用于计算涨跌幅
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prev_close: Option<f64>,
+    /// 行情源给出的报价时间（ms），源未提供时为空
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_ts_ms: Option<i64>,
+    /// 交易时段内报价超过 `poller.stale_after_ms` 没有变化，或一直拉取失败
+    #[serde(default)]
+    pub stale: bool,
+}
+
+impl PriceUpdate {
+    /// 相对昨收的涨跌幅（百分比）。
+    pub fn change_pct(&self) -> Option<f64> {
+        self.prev_close
+            .filter(|p| *p > 0.0)
+            .map(|p| (self.price - p) / p * 100.0)
+    }
 }
 
 #[derive(Debug, Clone, Deserialize)]
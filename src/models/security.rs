@@ -0,0 +1,32 @@
+use crate::models::market::Market;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SecurityKind {
+    Stock,
+    Index,
+    Etf,
+    Bond,
+}
+
+/// 证券主数据中的一条记录。
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct Security {
+    /// 形如 "600000.SH"
+    pub symbol: String,
+    /// 交易所代码，如 "600000"
+    pub code: String,
+    #[serde(alias = "exchange")]
+    pub market: Market,
+    /// 中文简称
+    pub name: String,
+    /// 拼音首字母（大写），如 "PFYH"
+    pub pinyin: String,
+    pub kind: SecurityKind,
+    /// 上市日期
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub listed: Option<NaiveDate>,
+}
@@ -0,0 +1,63 @@
+use crate::config::ProviderKind;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+/// `GET /ready` 的响应。
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Readiness {
+    pub ready: bool,
+    /// 最近 `poller.fresh_within_ms` 内收到过报价
+    pub fresh_quote: bool,
+    /// 最近 `poller.fresh_within_ms` 内至少有一次行情请求成功
+    pub provider_reachable: bool,
+}
+
+/// `GET /api/status` 的响应。
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct StatusReport {
+    pub version: &'static str,
+    pub uptime_secs: u64,
+    pub ws_clients: usize,
+    pub poll_interval_ms: u64,
+    /// 主备报价源及当前在用的一个
+    pub provider: FeedStatus,
+    /// 熔断打开中的上游 host
+    pub open_circuits: Vec<String>,
+    pub symbols: Vec<SymbolStatus>,
+}
+
+/// 单个轮询 symbol 的状态。
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct SymbolStatus {
+    pub symbol: String,
+    /// 最近一次成功拉到报价的时间
+    pub last_update_ts_ms: Option<i64>,
+    pub last_update_age_ms: Option<i64>,
+    /// 启动以来失败次数
+    pub errors: u64,
+    /// 连续失败次数，成功一次即清零
+    pub consecutive_errors: u64,
+    pub last_error: Option<String>,
+}
+
+/// 对账发现的主备价差。
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Divergence {
+    pub symbol: String,
+    pub primary_price: f64,
+    pub backup_price: f64,
+    pub diff_pct: f64,
+    pub ts_ms: i64,
+}
+
+/// `/api/status` 中的报价源状态。
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct FeedStatus {
+    pub primary: ProviderKind,
+    pub backup: Option<ProviderKind>,
+    pub active: ProviderKind,
+    /// 管理接口手动指定的报价源，为空时自动主备切换
+    pub pinned: Option<ProviderKind>,
+    /// 最近一次对账超出容差的 symbol
+    pub divergences: Vec<Divergence>,
+}
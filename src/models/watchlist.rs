@@ -0,0 +1,32 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct Watchlist {
+    pub name: String,
+    /// 按页面标签顺序排列
+    pub symbols: Vec<String>,
+}
+
+/// `POST /api/watchlists` 的请求体。
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct NewWatchlist {
+    pub name: String,
+    #[serde(default)]
+    pub symbols: Vec<String>,
+}
+
+/// `POST /api/watchlists/{name}/symbols` 的请求体。
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct AddSymbol {
+    pub symbol: String,
+    /// 插入位置，缺省追加到末尾
+    #[serde(default)]
+    pub position: Option<usize>,
+}
+
+/// `PUT /api/watchlists/{name}/symbols` 的请求体：完整的新顺序。
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct ReorderSymbols {
+    pub symbols: Vec<String>,
+}
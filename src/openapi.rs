@@ -0,0 +1,91 @@
+//! OpenAPI 3 文档，由 handler 上的 `#[utoipa::path]` 和模型上的 `ToSchema` 生成。
+
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+
+use crate::handlers;
+use crate::models::event::{ClientMessage, Published, StreamEvent};
+
+/// 文档地址，交互式文档页面从这里加载。
+pub const SPEC_PATH: &str = "/api/openapi.json";
+/// 交互式文档页面（Swagger UI，资源打包在二进制里）。
+pub const DOCS_PATH: &str = "/api/docs";
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "showmarket",
+        description = "A 股等市场的实时报价、K 线、告警与自选列表接口。\n\n\
+            错误响应统一为 `ErrorBody`；配置了 API key 时，除探针外的接口需要带 key。"
+    ),
+    paths(
+        handlers::health::health,
+        handlers::health::ready,
+        handlers::health::status,
+        handlers::metrics::metrics,
+        handlers::ws::ws_prices,
+        handlers::stream::stream_prices,
+        handlers::klines::get_klines,
+        handlers::klines::export_klines,
+        handlers::symbols::search_symbols,
+        handlers::symbols::list_markets,
+        handlers::alerts::list_alerts,
+        handlers::alerts::create_alert,
+        handlers::alerts::delete_alert,
+        handlers::watchlists::list_watchlists,
+        handlers::watchlists::create_watchlist,
+        handlers::watchlists::get_watchlist,
+        handlers::watchlists::delete_watchlist,
+        handlers::watchlists::add_symbol,
+        handlers::watchlists::reorder_symbols,
+        handlers::watchlists::remove_symbol,
+        handlers::admin::get_poller,
+        handlers::admin::set_poll_interval,
+        handlers::admin::add_poll_symbol,
+        handlers::admin::remove_poll_symbol,
+        handlers::admin::flush_kline_cache,
+        handlers::admin::set_provider,
+        handlers::admin::list_clients,
+        handlers::admin::kick_client,
+        handlers::jobs::list_jobs,
+        handlers::jobs::get_job,
+        handlers::jobs::create_backfill,
+        handlers::jobs::cancel_job,
+    ),
+    // WebSocket 消息不经过 HTTP 响应体，单独列出
+    components(schemas(Published, StreamEvent, ClientMessage)),
+    modifiers(&Security),
+    security(("bearer" = []), ("api_key" = []), ("token" = [])),
+    tags(
+        (name = "health", description = "探针、运行状态与指标"),
+        (name = "stream", description = "实时推送（WebSocket 与 SSE）"),
+        (name = "klines", description = "K 线"),
+        (name = "symbols", description = "证券搜索与市场信息"),
+        (name = "alerts", description = "价格告警"),
+        (name = "watchlists", description = "自选列表"),
+        (name = "admin", description = "运行时管理，需要 admin 权限"),
+        (name = "jobs", description = "历史 K 线下载任务，需要 admin 权限"),
+    )
+)]
+pub struct ApiDoc;
+
+/// API key 的三种传法，对应 `handlers::auth::authenticate`。
+struct Security;
+
+impl Modify for Security {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+        );
+        components.add_security_scheme(
+            "api_key",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("X-API-Key"))),
+        );
+        components.add_security_scheme(
+            "token",
+            SecurityScheme::ApiKey(ApiKey::Query(ApiKeyValue::new("token"))),
+        );
+    }
+}
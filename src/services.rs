@@ -1 +1,19 @@
+pub mod alerts;
 pub mod ashare;
+pub mod auth;
+pub mod backfill;
+pub mod export;
+pub mod kline_cache;
+pub mod metrics;
+pub mod outbox;
+pub mod pinyin;
+pub mod poller;
+pub mod quotes;
+pub mod replay;
+pub mod securities;
+pub mod staleness;
+pub mod status;
+pub mod tencent;
+pub mod upstream;
+pub mod watchlists;
+pub mod webhook;
@@ -0,0 +1,260 @@
+use crate::models::alert::{Alert, AlertFired, AlertRule, NewAlert};
+use crate::models::event::StreamEvent;
+use crate::models::price::PriceUpdate;
+use crate::state::AppState;
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tokio::sync::broadcast::error::RecvError;
+
+/// 告警规则存储，变更后整体写回 JSON 文件。
+#[derive(Clone)]
+pub struct AlertStore {
+    path: PathBuf,
+    book: Arc<RwLock<AlertBook>>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+struct AlertBook {
+    next_id: u64,
+    alerts: Vec<Alert>,
+}
+
+impl AlertStore {
+    /// 从 `path` 加载告警；文件不存在时视为空。
+    pub fn load(path: impl Into<PathBuf>) -> anyhow::Result<Self> {
+        let path = path.into();
+        let book = match std::fs::read_to_string(&path) {
+            Ok(body) => serde_json::from_str(&body)
+                .with_context(|| format!("parse alerts file {} failed", path.display()))?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => AlertBook::default(),
+            Err(err) => {
+                return Err(err).with_context(|| format!("read {} failed", path.display()));
+            }
+        };
+        Ok(Self {
+            path,
+            book: Arc::new(RwLock::new(book)),
+        })
+    }
+
+    pub async fn list(&self) -> Vec<Alert> {
+        self.book.read().await.alerts.clone()
+    }
+
+    pub async fn create(&self, new: NewAlert, now_ms: i64) -> anyhow::Result<Alert> {
+        let mut book = self.book.write().await;
+        // 副本落盘成功后才替换内存，写失败时不留下未持久化的告警
+        let mut next = book.clone();
+        next.next_id += 1;
+        let alert = Alert {
+            id: next.next_id,
+            symbol: new.symbol,
+            rule: new.rule,
+            debounce_ms: new.debounce_ms,
+            cooldown_ms: new.cooldown_ms,
+            created_ts_ms: now_ms,
+        };
+        next.alerts.push(alert.clone());
+        persist(&self.path, &next).await?;
+        *book = next;
+        Ok(alert)
+    }
+
+    /// 删除告警，返回是否存在。
+    pub async fn delete(&self, id: u64) -> anyhow::Result<bool> {
+        let mut book = self.book.write().await;
+        let mut next = book.clone();
+        next.alerts.retain(|a| a.id != id);
+        if next.alerts.len() == book.alerts.len() {
+            return Ok(false);
+        }
+        persist(&self.path, &next).await?;
+        *book = next;
+        Ok(true)
+    }
+
+    pub async fn flush(&self) -> anyhow::Result<()> {
+        let book = self.book.read().await;
+        persist(&self.path, &book).await
+    }
+}
+
+async fn persist(path: &Path, book: &AlertBook) -> anyhow::Result<()> {
+    if let Some(dir) = path.parent() {
+        tokio::fs::create_dir_all(dir).await?;
+    }
+    let body = serde_json::to_vec_pretty(book)?;
+    // 先写临时文件再 rename，避免进程中途退出留下半个文件
+    let tmp = path.with_extension("json.tmp");
+    tokio::fs::write(&tmp, body).await?;
+    tokio::fs::rename(&tmp, path)
+        .await
+        .with_context(|| format!("write {} failed", path.display()))
+}
+
+/// 单条告警的运行时状态，不落盘。
+#[derive(Debug)]
+struct AlertRuntime {
+    /// 条件解除后才重新布防，保证“穿越”只触发一次。
+    ///
+    /// 穿越类规则初始不布防：要先见到价格在 level 另一侧，之后越过才算穿越，
+    /// 创建时价格已在目标一侧不会触发。
+    armed: bool,
+    /// 条件开始满足的时间，用于去抖
+    pending_since: Option<i64>,
+    last_fired_ms: Option<i64>,
+    /// 布林带使用的最近报价
+    window: VecDeque<f64>,
+}
+
+impl AlertRuntime {
+    fn new(rule: &AlertRule) -> Self {
+        Self {
+            armed: !matches!(
+                rule,
+                AlertRule::CrossAbove { .. } | AlertRule::CrossBelow { .. }
+            ),
+            pending_since: None,
+            last_fired_ms: None,
+            window: VecDeque::new(),
+        }
+    }
+}
+
+/// 告警求值器：对每条报价检查所有同 symbol 的告警。
+#[derive(Debug, Default)]
+pub struct AlertEngine {
+    runtime: HashMap<u64, AlertRuntime>,
+}
+
+impl AlertEngine {
+    pub fn on_update(&mut self, alerts: &[Alert], update: &PriceUpdate) -> Vec<AlertFired> {
+        // 已删除告警的状态一并清理
+        self.runtime
+            .retain(|id, _| alerts.iter().any(|a| a.id == *id));
+
+        let mut fired = Vec::new();
+        for alert in alerts.iter().filter(|a| a.symbol == update.symbol) {
+            let rt = self
+                .runtime
+                .entry(alert.id)
+                .or_insert_with(|| AlertRuntime::new(&alert.rule));
+            let holds = condition_holds(&alert.rule, rt, update);
+
+            if !holds {
+                rt.armed = true;
+                rt.pending_since = None;
+                continue;
+            }
+            if !rt.armed {
+                continue;
+            }
+            let since = *rt.pending_since.get_or_insert(update.ts_ms);
+            if update.ts_ms - since < alert.debounce_ms as i64 {
+                continue;
+            }
+            if let Some(last) = rt.last_fired_ms
+                && update.ts_ms - last < alert.cooldown_ms as i64
+            {
+                continue;
+            }
+
+            rt.armed = false;
+            rt.pending_since = None;
+            rt.last_fired_ms = Some(update.ts_ms);
+            fired.push(AlertFired {
+                alert_id: alert.id,
+                symbol: alert.symbol.clone(),
+                rule: alert.rule.clone(),
+                update: update.clone(),
+                ts_ms: update.ts_ms,
+            });
+        }
+        fired
+    }
+}
+
+fn condition_holds(rule: &AlertRule, rt: &mut AlertRuntime, update: &PriceUpdate) -> bool {
+    match rule {
+        AlertRule::CrossAbove { level } => update.price > *level,
+        AlertRule::CrossBelow { level } => update.price < *level,
+        AlertRule::ChangePctAbove { pct } => update.change_pct().is_some_and(|c| c > *pct),
+        AlertRule::ChangePctBelow { pct } => update.change_pct().is_some_and(|c| c < *pct),
+        AlertRule::BollingerBreak { period, k } => {
+            // 先用之前的窗口判断，再把当前价放进窗口
+            let outside = if rt.window.len() >= *period {
+                let n = rt.window.len() as f64;
+                let mean = rt.window.iter().sum::<f64>() / n;
+                let var = rt.window.iter().map(|p| (p - mean).powi(2)).sum::<f64>() / n;
+                let band = k * var.sqrt();
+                update.price > mean + band || update.price < mean - band
+            } else {
+                false
+            };
+            rt.window.push_back(update.price);
+            while rt.window.len() > *period {
+                rt.window.pop_front();
+            }
+            outside
+        }
+    }
+}
+
+/// 后台任务：订阅广播中的价格，触发的告警再以 `alert` 事件广播出去。
+pub fn spawn_alert_engine(state: AppState) {
+    tokio::spawn(async move {
+        let mut rx = state.subscribe();
+        let mut engine = AlertEngine::default();
+        let shutdown = state.shutdown_token().clone();
+        let mut cursor = state.replay().last_id();
+
+        loop {
+            let msg = tokio::select! {
+                msg = rx.recv() => msg,
+                _ = shutdown.cancelled() => break,
+            };
+            let batch = match msg {
+                Ok(published) if published.id <= cursor => continue,
+                Ok(published) => vec![published],
+                Err(RecvError::Lagged(n)) => {
+                    // 从回放缓冲区补上跳过的行情，否则穿越类规则会漏判
+                    state.metrics().broadcast_lagged("alerts", n);
+                    match state.replay().since(cursor) {
+                        Some(missed) => missed,
+                        None => {
+                            tracing::warn!(
+                                skipped = n,
+                                "alert engine lagged beyond the replay buffer"
+                            );
+                            continue;
+                        }
+                    }
+                }
+                Err(RecvError::Closed) => break,
+            };
+            if let Some(last) = batch.last() {
+                cursor = cursor.max(last.id);
+            }
+
+            let alerts = state.alerts().list().await;
+            for published in batch {
+                let StreamEvent::Price(update) = published.event else {
+                    continue;
+                };
+                for fired in engine.on_update(&alerts, &update) {
+                    tracing::info!(
+                        alert_id = fired.alert_id,
+                        symbol = %fired.symbol,
+                        price = fired.update.price,
+                        "alert fired"
+                    );
+                    state.publish(StreamEvent::Alert(fired));
+                }
+            }
+        }
+    });
+}
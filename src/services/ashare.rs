@@ -1,7 +1,11 @@
-use crate::models::kline::Kline;
+use crate::config::{KlinesConfig, ProvidersConfig, SecuritiesConfig, UpstreamConfig};
+use crate::models::kline::{Adjust, Kline};
+use crate::models::market::{Market, parse_symbol};
 use crate::models::price::PriceUpdate;
+use crate::models::security::{Security, SecurityKind};
+use crate::services::upstream::UpstreamClient;
 use anyhow::{Context, anyhow};
-use chrono::{Local, NaiveDate, NaiveDateTime, TimeZone};
+use chrono::{NaiveDate, NaiveDateTime, TimeZone};
 use serde::Deserialize;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
@@ -11,22 +15,59 @@ use std::time::{Duration, SystemTime, UNIX_EPOCH};
 /// - 实时价格：使用东方财富 push2 实时行情接口
 ///
 /// 后续如果你有自己的行情中台，只需在这里替换调用即可。
+/// 服务内由 `AppState` 持有一个实例，通过 `state.ashare()` 共享。
 #[derive(Clone)]
 pub struct AshareService {
-    client: reqwest::Client,
+    http: UpstreamClient,
+    /// 历史 K 线接口地址，见 `klines.history_url`
+    kline_url: String,
+    /// 证券列表接口地址，见 `securities.list_url`
+    list_url: String,
+    /// 实时行情接口地址
+    quote_url: String,
+}
+
+impl Default for AshareService {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl AshareService {
     pub fn new() -> Self {
-        let client = reqwest::Client::builder()
-            .timeout(Duration::from_secs(5))
-            .user_agent("showmarket-ashare/0.1")
-            .build()
-            .expect("failed to build reqwest client");
-        Self { client }
+        Self::with_config(&UpstreamConfig::default())
+    }
+
+    pub fn with_config(cfg: &UpstreamConfig) -> Self {
+        Self::with_client(UpstreamClient::new(cfg).expect("failed to build upstream client"))
+    }
+
+    /// 使用共享的上游客户端（连接池、限流、熔断、指标都随之共享）。
+    pub fn with_client(http: UpstreamClient) -> Self {
+        Self {
+            http,
+            kline_url: KlinesConfig::default().history_url,
+            list_url: SecuritiesConfig::default().list_url,
+            quote_url: ProvidersConfig::default().eastmoney_url,
+        }
     }
 
-    /// 获取真实 A 股 K 线数据。
+    pub fn with_kline_url(mut self, url: impl Into<String>) -> Self {
+        self.kline_url = url.into();
+        self
+    }
+
+    pub fn with_list_url(mut self, url: impl Into<String>) -> Self {
+        self.list_url = url.into();
+        self
+    }
+
+    pub fn with_quote_url(mut self, url: impl Into<String>) -> Self {
+        self.quote_url = url.into();
+        self
+    }
+
+    /// 获取真实 A 股 K 线数据（前复权，最近 `limit` 根）。
     ///
     /// `symbol` 形如 "600000.SH" / "000001.SZ" / "300750.SZ"
     /// `interval` 映射为东方财富的 klt 参数：
@@ -38,77 +79,231 @@ impl AshareService {
         interval: &str,
         limit: u16,
     ) -> anyhow::Result<Vec<Kline>> {
+        let limit = limit.min(500);
+        self.request_klines(
+            symbol,
+            interval,
+            Adjust::Forward,
+            &format!("end=20500101&lmt={limit}"),
+        )
+        .await?
+        .ok_or_else(|| anyhow!("empty kline data"))
+    }
+
+    /// 获取 `[start, end]`（交易所日期，含两端）内的全部 K 线，按时间升序。
+    ///
+    /// 长区间由调用方按窗口分段调用，避免单次响应过大。
+    pub async fn fetch_kline_range(
+        &self,
+        symbol: &str,
+        interval: &str,
+        adjust: Adjust,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> anyhow::Result<Vec<Kline>> {
+        let range = format!(
+            "beg={}&end={}&lmt=1000000",
+            start.format("%Y%m%d"),
+            end.format("%Y%m%d")
+        );
+        // 区间内没有数据（如上市前）时 data 为 null
+        Ok(self
+            .request_klines(symbol, interval, adjust, &range)
+            .await?
+            .unwrap_or_default())
+    }
+
+    async fn request_klines(
+        &self,
+        symbol: &str,
+        interval: &str,
+        adjust: Adjust,
+        range: &str,
+    ) -> anyhow::Result<Option<Vec<Kline>>> {
+        let (_, market) = parse_symbol(symbol).context("unsupported symbol")?;
         let secid = to_secid(symbol).context("unsupported symbol")?;
         let klt = to_klt(interval)?;
-        let limit = limit.min(500);
+        let fqt = to_fqt(adjust);
 
         let url = format!(
-            "https://push2his.eastmoney.com/api/qt/stock/kline/get\
-             ?secid={secid}&klt={klt}&fqt=1&end=20500101&lmt={limit}\
-             &fields1=f1,f2,f3,f4,f5&fields2=f51,f52,f53,f54,f55,f56,f57,f58"
+            "{}?secid={secid}&klt={klt}&fqt={fqt}&{range}\
+             &fields1=f1,f2,f3,f4,f5&fields2=f51,f52,f53,f54,f55,f56,f57,f58,f59,f60,f61",
+            self.kline_url
         );
 
-        let resp = self.client.get(url).send().await?.error_for_status()?;
-
-        let body = resp.text().await?;
+        let body = self.http.get_text("klines", &url).await?;
         let em: EmKlineResp = serde_json::from_str(&body)
             .with_context(|| format!("parse kline response failed: {body}"))?;
 
-        let data = em.data.ok_or_else(|| anyhow!("empty kline data"))?;
+        let Some(data) = em.data else {
+            return Ok(None);
+        };
         let mut out = Vec::with_capacity(data.klines.len());
 
         for s in data.klines {
-            // "2024-02-10 09:30,open,close,high,low,volume,amount,amplitude"
+            // "2024-02-10 09:30,open,close,high,low,volume,amount,amplitude,change_pct,change,turnover"
             let parts: Vec<&str> = s.split(',').collect();
             if parts.len() < 6 {
                 continue;
             }
-            let ts_ms = parse_em_time(parts[0]);
-            let open = parts[1].parse::<f64>().unwrap_or(0.0);
-            let close = parts[2].parse::<f64>().unwrap_or(0.0);
-            let high = parts[3].parse::<f64>().unwrap_or(0.0);
-            let low = parts[4].parse::<f64>().unwrap_or(0.0);
-            let volume = parts[5].parse::<f64>().unwrap_or(0.0);
+            let Some(open_time) = parse_em_time(parts[0], market) else {
+                continue;
+            };
+            let num = |i: usize| parts[i].parse::<f64>().unwrap_or(0.0);
+            let opt = |i: usize| parts.get(i).and_then(|v| v.parse::<f64>().ok());
 
             out.push(Kline {
-                open_time: ts_ms,
-                open,
-                high,
-                low,
-                close,
-                volume,
+                open_time,
+                open: num(1),
+                high: num(3),
+                low: num(4),
+                close: num(2),
+                volume: num(5),
+                amount: opt(6),
+                amplitude_pct: opt(7),
+                change_pct: opt(8),
+                change: opt(9),
+                turnover_pct: opt(10),
             });
         }
 
-        Ok(out)
+        Ok(Some(out))
     }
 
     /// 获取某支股票的真实最新价（东方财富推送接口）。
     pub async fn fetch_realtime_quote(&self, symbol: &str) -> anyhow::Result<PriceUpdate> {
+        let (_, market) = parse_symbol(symbol).context("unsupported symbol")?;
         let secid = to_secid(symbol).context("unsupported symbol")?;
-        // 只取最新价 f43
-        let url = format!(
-            "https://push2.eastmoney.com/api/qt/stock/get\
-             ?secid={secid}&fields=f43"
-        );
-
-        let resp = self.client.get(url).send().await?.error_for_status()?;
+        // 最新价 f43，价格小数位数 f59，昨收 f60，行情时间 f86（Unix 秒）
+        let url = format!("{}?secid={secid}&fields=f43,f59,f60,f86", self.quote_url);
 
-        let body = resp.text().await?;
+        let body = self.http.get_text("quote", &url).await?;
         let em: EmQuoteResp =
             serde_json::from_str(&body).with_context(|| format!("parse quote failed: {body}"))?;
 
         let data = em.data.ok_or_else(|| anyhow!("empty quote data"))?;
-        // 东方财富推送的 f43 通常是价格 * 100，单位为“分”
-        let raw = data.f43;
-        let price = raw / 100.0;
+        // 东方财富推送的价格是放大后的整数：A 股通常 * 100，ETF、港股、美股通常 * 1000，
+        // 以 f59 给出的小数位数为准
+        let decimals = data
+            .f59
+            .map(|d| d as u32)
+            .unwrap_or_else(|| market.price_decimals());
+        let scale = 10f64.powi(decimals as i32);
 
         Ok(PriceUpdate {
             symbol: symbol.to_string(),
-            price,
+            price: data.f43 / scale,
             ts_ms: now_ms(),
+            prev_close: data.f60.map(|v| v / scale),
+            source_ts_ms: data.f86.filter(|t| *t > 0.0).map(|t| t as i64 * 1000),
+            stale: false,
         })
     }
+
+    /// 从东方财富列表接口拉取沪深京、港股与美股的证券列表。
+    ///
+    /// 接口不提供拼音，返回的 `pinyin` 为空，由调用方按需补全。
+    pub async fn fetch_securities(&self) -> anyhow::Result<Vec<Security>> {
+        const PAGE_SIZE: usize = 1000;
+        // 北交所与深市在东方财富中同为市场 0，按板块强制指定
+        let boards = [
+            (
+                SecurityKind::Stock,
+                "m:0+t:6,m:0+t:80,m:1+t:2,m:1+t:23",
+                None,
+            ),
+            (SecurityKind::Stock, "m:0+t:81+s:2048", Some(Market::BJ)),
+            (SecurityKind::Stock, "m:116+t:3,m:116+t:4", None),
+            (SecurityKind::Stock, "m:105,m:106,m:107", None),
+            (SecurityKind::Index, "m:1+s:2,m:0+t:5", None),
+            (
+                SecurityKind::Etf,
+                "b:MK0021,b:MK0022,b:MK0023,b:MK0024",
+                None,
+            ),
+            (SecurityKind::Bond, "b:MK0354", None),
+        ];
+
+        let mut out = Vec::new();
+        for (kind, fs, forced) in boards {
+            let mut page = 1;
+            // 接口可能把 pz 截到比请求的更小，只按已取行数与 total 判断是否取完
+            let mut rows = 0;
+            loop {
+                let url = format!(
+                    "{}?pn={page}&pz={PAGE_SIZE}&po=0&np=1&fid=f12&fs={fs}&fields=f12,f13,f14,f26",
+                    self.list_url
+                );
+                let body = self.http.get_text("securities", &url).await?;
+                let em: EmListResp = serde_json::from_str(&body)
+                    .with_context(|| format!("parse security list failed: {body}"))?;
+                let Some(data) = em.data else { break };
+
+                let fetched = data.diff.len();
+                for row in data.diff {
+                    let market = match forced {
+                        Some(m) => m,
+                        None => match Market::ALL
+                            .into_iter()
+                            .find(|m| m.em_market() == row.f13 && *m != Market::BJ)
+                        {
+                            Some(m) => m,
+                            None => continue,
+                        },
+                    };
+                    let code = row.f12.replace('_', ".");
+                    let symbol = format!("{code}.{}", market.suffix());
+                    if parse_symbol(&symbol).is_none() {
+                        continue;
+                    }
+                    let listed = row
+                        .f26
+                        .as_u64()
+                        .and_then(|d| NaiveDate::parse_from_str(&d.to_string(), "%Y%m%d").ok());
+                    out.push(Security {
+                        symbol,
+                        code,
+                        market,
+                        name: row.f14,
+                        pinyin: String::new(),
+                        kind,
+                        listed,
+                    });
+                }
+
+                rows += fetched;
+                if fetched == 0 || rows >= data.total {
+                    break;
+                }
+                page += 1;
+            }
+        }
+        Ok(out)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct EmListResp {
+    data: Option<EmListData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EmListData {
+    total: usize,
+    diff: Vec<EmListRow>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EmListRow {
+    /// 代码
+    f12: String,
+    /// 市场编号，见 `Market::em_market`
+    f13: u32,
+    /// 名称
+    f14: String,
+    /// 上市日期 yyyymmdd，指数等为 "-"
+    #[serde(default)]
+    f26: serde_json::Value,
 }
 
 #[derive(Debug, Deserialize)]
@@ -130,6 +325,21 @@ struct EmQuoteResp {
 struct EmQuoteData {
     #[serde(rename = "f43")]
     f43: f64,
+    #[serde(rename = "f59", default, deserialize_with = "de_opt_num")]
+    f59: Option<f64>,
+    /// 停牌或新股时可能为 "-"，按缺失处理
+    #[serde(rename = "f60", default, deserialize_with = "de_opt_num")]
+    f60: Option<f64>,
+    #[serde(rename = "f86", default, deserialize_with = "de_opt_num")]
+    f86: Option<f64>,
+}
+
+fn de_opt_num<'de, D>(de: D) -> Result<Option<f64>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let v = serde_json::Value::deserialize(de)?;
+    Ok(v.as_f64())
 }
 
 fn now_ms() -> i64 {
@@ -139,16 +349,17 @@ fn now_ms() -> i64 {
         .as_millis() as i64
 }
 
-fn to_secid(symbol: &str) -> Option<String> {
-    if let Some(code) = symbol.strip_suffix(".SH") {
-        Some(format!("1.{}", code))
-    } else if let Some(code) = symbol.strip_suffix(".SZ") {
-        Some(format!("0.{}", code))
-    } else {
-        None
-    }
+/// 东方财富的证券 id，如 "1.600000"、"116.00700"、"105.AAPL"。
+pub fn to_secid(symbol: &str) -> Option<String> {
+    let (code, market) = parse_symbol(symbol)?;
+    // 东方财富美股代码中的 "." 写作 "_"，如 BRK.B -> BRK_B
+    let code = code.replace('.', "_");
+    Some(format!("{}.{code}", market.em_market()))
 }
 
+/// `fetch_klines` 支持的周期。
+pub const SUPPORTED_INTERVALS: [&str; 8] = ["1m", "5m", "15m", "30m", "1h", "1d", "1w", "1M"];
+
 fn to_klt(interval: &str) -> anyhow::Result<u32> {
     let v = match interval {
         "1m" => 101,
@@ -164,24 +375,32 @@ fn to_klt(interval: &str) -> anyhow::Result<u32> {
     Ok(v)
 }
 
-fn parse_em_time(s: &str) -> i64 {
-    // 支持 "YYYY-MM-DD HH:MM"（分时/分钟）和 "YYYY-MM-DD"（日/周/月）
-    if let Ok(ndt) = NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M") {
-        let dt = Local
-            .from_local_datetime(&ndt)
-            .single()
-            .unwrap_or_else(|| Local.timestamp_millis_opt(0).single().unwrap());
-        dt.timestamp_millis()
-    } else if let Ok(nd) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
-        let ndt = nd
-            .and_hms_opt(15, 0, 0)
-            .unwrap_or_else(|| nd.and_hms_opt(0, 0, 0).unwrap());
-        let dt = Local
-            .from_local_datetime(&ndt)
-            .single()
-            .unwrap_or_else(|| Local.timestamp_millis_opt(0).single().unwrap());
-        dt.timestamp_millis()
-    } else {
-        now_ms()
+fn to_fqt(adjust: Adjust) -> u8 {
+    match adjust {
+        Adjust::None => 0,
+        Adjust::Forward => 1,
+        Adjust::Backward => 2,
     }
 }
+
+/// 解析东方财富 K 线时间，得到 Unix 毫秒。
+///
+/// 时间为交易所当地时间：分钟线形如 "YYYY-MM-DD HH:MM"；日/周/月线只有日期，
+/// 取当天收盘时刻（A 股 15:00、港股 16:00、美股 16:00，按各自时区）。
+pub fn parse_em_time(s: &str, market: Market) -> Option<i64> {
+    let ndt = match NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M") {
+        Ok(ndt) => ndt,
+        Err(_) => {
+            let close = market.sessions().last()?.close_min;
+            NaiveDate::parse_from_str(s, "%Y-%m-%d")
+                .ok()?
+                .and_hms_opt(close / 60, close % 60, 0)?
+        }
+    };
+    // 夏令时切换造成的重复时刻取较早的一个
+    market
+        .timezone()
+        .from_local_datetime(&ndt)
+        .earliest()
+        .map(|dt| dt.timestamp_millis())
+}
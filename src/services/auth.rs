@@ -0,0 +1,196 @@
+use crate::config::{AuthConfig, Scope};
+use chrono::{NaiveDate, Utc};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// 鉴权失败的原因。
+#[derive(Debug, Clone, PartialEq)]
+pub enum AuthError {
+    /// 没有携带 key
+    Missing,
+    /// key 不存在
+    Invalid,
+    /// key 没有该接口需要的权限
+    Forbidden { key: String, scope: Scope },
+    /// 超过每秒请求数
+    RateLimited { key: String, retry_after: Duration },
+    /// 超过当天的请求配额
+    QuotaExceeded { key: String, retry_after: Duration },
+}
+
+impl fmt::Display for AuthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuthError::Missing => f.write_str("missing API key"),
+            AuthError::Invalid => f.write_str("invalid API key"),
+            AuthError::Forbidden { key, scope } => {
+                write!(f, "API key {key} lacks the {} scope", scope.name())
+            }
+            AuthError::RateLimited { key, .. } => {
+                write!(f, "rate limit exceeded for API key {key}")
+            }
+            AuthError::QuotaExceeded { key, .. } => {
+                write!(f, "daily quota exhausted for API key {key}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for AuthError {}
+
+/// 某个 key 当天的用量，供管理接口查看。
+#[derive(Debug, Clone, Serialize)]
+pub struct KeyUsage {
+    pub name: String,
+    pub scopes: Vec<Scope>,
+    pub requests_today: u64,
+    pub daily_quota: Option<u64>,
+}
+
+struct KeyState {
+    name: String,
+    scopes: Vec<Scope>,
+    rate_per_sec: Option<f64>,
+    burst: f64,
+    daily_quota: Option<u64>,
+    usage: Mutex<Usage>,
+}
+
+struct Usage {
+    tokens: f64,
+    refilled_at: Instant,
+    day: NaiveDate,
+    requests: u64,
+}
+
+/// 配置中的 API key，按 key 的 SHA-256 索引；同时负责每个 key 的限流与配额计数。
+///
+/// 配额只记在内存里，重启后清零。
+#[derive(Clone, Default)]
+pub struct ApiKeys {
+    keys: Arc<HashMap<[u8; 32], KeyState>>,
+}
+
+impl ApiKeys {
+    pub fn new(cfg: &AuthConfig) -> Self {
+        let now = Instant::now();
+        let today = Utc::now().date_naive();
+        let keys = cfg
+            .keys
+            .iter()
+            .map(|k| {
+                let burst = k
+                    .rate_limit_burst
+                    .map(f64::from)
+                    .or(k.rate_limit_per_sec)
+                    .unwrap_or(1.0)
+                    .max(1.0);
+                let state = KeyState {
+                    name: k.name.clone(),
+                    scopes: k.scopes.clone(),
+                    rate_per_sec: k.rate_limit_per_sec,
+                    burst,
+                    daily_quota: k.daily_quota,
+                    usage: Mutex::new(Usage {
+                        tokens: burst,
+                        refilled_at: now,
+                        day: today,
+                        requests: 0,
+                    }),
+                };
+                (digest(&k.key), state)
+            })
+            .collect();
+        Self {
+            keys: Arc::new(keys),
+        }
+    }
+
+    /// 没有配置任何 key 时不鉴权。
+    pub fn enabled(&self) -> bool {
+        !self.keys.is_empty()
+    }
+
+    /// 只识别 key 属于谁，不检查权限也不计数；公开页面据此展示该 key 的自选列表。
+    pub fn identify(&self, token: Option<&str>) -> Option<String> {
+        let token = token.filter(|t| !t.is_empty())?;
+        self.keys.get(&digest(token)).map(|key| key.name.clone())
+    }
+
+    /// 校验 key 与权限，通过则计入限流和配额，返回 key 的名字。
+    pub fn authorize(&self, token: Option<&str>, scope: Scope) -> Result<String, AuthError> {
+        let token = token.filter(|t| !t.is_empty()).ok_or(AuthError::Missing)?;
+        let key = self.keys.get(&digest(token)).ok_or(AuthError::Invalid)?;
+        if !(key.scopes.contains(&scope) || key.scopes.contains(&Scope::Admin)) {
+            return Err(AuthError::Forbidden {
+                key: key.name.clone(),
+                scope,
+            });
+        }
+
+        let mut usage = key.usage.lock().unwrap();
+        let now = Utc::now();
+        if usage.day != now.date_naive() {
+            usage.day = now.date_naive();
+            usage.requests = 0;
+        }
+        if let Some(quota) = key.daily_quota
+            && usage.requests >= quota
+        {
+            let midnight = (now.date_naive() + chrono::Days::new(1))
+                .and_hms_opt(0, 0, 0)
+                .unwrap()
+                .and_utc();
+            return Err(AuthError::QuotaExceeded {
+                key: key.name.clone(),
+                retry_after: (midnight - now).to_std().unwrap_or_default(),
+            });
+        }
+        if let Some(rate) = key.rate_per_sec {
+            let elapsed = usage.refilled_at.elapsed().as_secs_f64();
+            usage.tokens = (usage.tokens + elapsed * rate).min(key.burst);
+            usage.refilled_at = Instant::now();
+            if usage.tokens < 1.0 {
+                return Err(AuthError::RateLimited {
+                    key: key.name.clone(),
+                    retry_after: Duration::from_secs_f64((1.0 - usage.tokens) / rate),
+                });
+            }
+            usage.tokens -= 1.0;
+        }
+        usage.requests += 1;
+        Ok(key.name.clone())
+    }
+
+    /// 各 key 当天的请求数（按名字排序）。
+    pub fn usage(&self) -> Vec<KeyUsage> {
+        let today = Utc::now().date_naive();
+        let mut out: Vec<KeyUsage> = self
+            .keys
+            .values()
+            .map(|k| {
+                let usage = k.usage.lock().unwrap();
+                KeyUsage {
+                    name: k.name.clone(),
+                    scopes: k.scopes.clone(),
+                    requests_today: if usage.day == today {
+                        usage.requests
+                    } else {
+                        0
+                    },
+                    daily_quota: k.daily_quota,
+                }
+            })
+            .collect();
+        out.sort_by(|a, b| a.name.cmp(&b.name));
+        out
+    }
+}
+
+fn digest(key: &str) -> [u8; 32] {
+    Sha256::digest(key.as_bytes()).into()
+}
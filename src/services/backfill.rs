@@ -0,0 +1,536 @@
+use crate::config::BackfillConfig;
+use crate::models::job::{BackfillSpec, BackfillTask, Job, JobState};
+use crate::models::kline::{Adjust, Kline};
+use crate::models::market::parse_symbol;
+use crate::services::ashare::{AshareService, SUPPORTED_INTERVALS};
+use crate::services::export::{default_start, market_today, windows};
+use crate::services::securities::SecurityMaster;
+use crate::services::upstream::UpstreamUnavailable;
+use crate::state::AppState;
+use anyhow::Context;
+use chrono::{NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncSeekExt, AsyncWriteExt};
+use tokio::sync::{Mutex as AsyncMutex, RwLock};
+use tokio_util::sync::CancellationToken;
+
+/// 历史 K 线下载任务，记录保存在 `data/jobs.json`，K 线写到 `data/history/` 下。
+///
+/// 每个任务先写自己的分片文件，每写完一个窗口就记下游标和分片长度，进程崩溃或退出后从下一个窗口续传；
+/// 分片结束后再并入历史文件，同一序列上的多个任务互不干扰。
+#[derive(Clone)]
+pub struct BackfillJobs {
+    path: PathBuf,
+    history_dir: PathBuf,
+    book: Arc<RwLock<JobBook>>,
+    /// 正在执行的任务，取消时触发对应的 token
+    running: Arc<Mutex<HashMap<u64, CancellationToken>>>,
+    /// 合并分片时持有，避免两个任务同时改写同一个历史文件
+    merging: Arc<AsyncMutex<()>>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct JobBook {
+    next_id: u64,
+    jobs: BTreeMap<u64, Job>,
+}
+
+impl BackfillJobs {
+    /// 从 `data_dir/jobs.json` 加载，文件不存在时为空。
+    pub fn load(data_dir: &Path) -> anyhow::Result<Self> {
+        let path = data_dir.join("jobs.json");
+        let book = match std::fs::read_to_string(&path) {
+            Ok(body) => serde_json::from_str(&body)
+                .with_context(|| format!("parse jobs file {} failed", path.display()))?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => JobBook::default(),
+            Err(err) => {
+                return Err(err).with_context(|| format!("read {} failed", path.display()));
+            }
+        };
+        Ok(Self {
+            path,
+            history_dir: data_dir.join("history"),
+            book: Arc::new(RwLock::new(book)),
+            running: Arc::default(),
+            merging: Arc::default(),
+        })
+    }
+
+    /// 本地历史文件：`history/{symbol}/{interval}_{adjust}.jsonl`，每行一根 K 线。
+    ///
+    /// 月线写作 `1mo`，避免在不区分大小写的文件系统上与 `1m` 冲突。
+    pub fn history_path(&self, symbol: &str, interval: &str, adjust: Adjust) -> PathBuf {
+        let interval = if interval == "1M" { "1mo" } else { interval };
+        self.history_dir
+            .join(symbol)
+            .join(format!("{interval}_{}.jsonl", adjust.name()))
+    }
+
+    /// 任务 `id` 下载中的分片：`history/{symbol}/{interval}_{adjust}.job{id}.part`，合并后删除。
+    pub fn part_path(&self, id: u64, symbol: &str, interval: &str, adjust: Adjust) -> PathBuf {
+        self.history_path(symbol, interval, adjust)
+            .with_extension(format!("job{id}.part"))
+    }
+
+    /// 把分片并入历史文件：按 `open_time` 去重（分片中的新数据优先）并排序，写临时文件后 rename。
+    ///
+    /// 分片由调用方在登记任务结束后删除；重复合并同一分片结果不变，中途崩溃后续传时可以再合并一次。
+    async fn merge(&self, part: &Path, history: &Path) -> anyhow::Result<()> {
+        let _guard = self.merging.lock().await;
+        let mut bars = BTreeMap::new();
+        for path in [history, part] {
+            for bar in read_bars(path).await? {
+                bars.insert(bar.open_time, bar);
+            }
+        }
+        let mut buf = Vec::new();
+        for bar in bars.values() {
+            serde_json::to_writer(&mut buf, bar)?;
+            buf.push(b'\n');
+        }
+        let tmp = history.with_extension("jsonl.tmp");
+        tokio::fs::write(&tmp, buf).await?;
+        tokio::fs::rename(&tmp, history)
+            .await
+            .with_context(|| format!("write {} failed", history.display()))?;
+        Ok(())
+    }
+
+    pub async fn get(&self, id: u64) -> Option<Job> {
+        self.book.read().await.jobs.get(&id).cloned()
+    }
+
+    /// 全部任务，按 id 升序。
+    pub async fn list(&self) -> Vec<Job> {
+        self.book.read().await.jobs.values().cloned().collect()
+    }
+
+    /// 未结束的任务 id，启动时据此续传。
+    pub async fn unfinished(&self) -> Vec<u64> {
+        self.book
+            .read()
+            .await
+            .jobs
+            .values()
+            .filter(|job| !job.state.is_finished())
+            .map(|job| job.id)
+            .collect()
+    }
+
+    /// 创建任务并写盘，尚未开始执行。
+    pub async fn submit(
+        &self,
+        spec: BackfillSpec,
+        tasks: Vec<BackfillTask>,
+    ) -> anyhow::Result<Job> {
+        let mut book = self.book.write().await;
+        book.next_id += 1;
+        let now = Utc::now().timestamp_millis();
+        let mut job = Job {
+            id: book.next_id,
+            spec,
+            state: JobState::Queued,
+            windows_done: 0,
+            windows_total: 0,
+            bars: 0,
+            tasks,
+            created_at_ms: now,
+            updated_at_ms: now,
+            finished_at_ms: None,
+        };
+        job.refresh_progress();
+        book.jobs.insert(job.id, job.clone());
+        persist(&self.path, &book).await?;
+        Ok(job)
+    }
+
+    /// 取消任务；已结束的任务保持原状态。任务不存在时返回 `None`。
+    pub async fn cancel(&self, id: u64) -> anyhow::Result<Option<Job>> {
+        let job = self
+            .update(id, |job| {
+                if !job.state.is_finished() {
+                    job.state = JobState::Cancelled;
+                    job.finished_at_ms = Some(job.updated_at_ms);
+                }
+            })
+            .await?;
+        if let Some(token) = self.running.lock().unwrap().get(&id) {
+            token.cancel();
+        }
+        Ok(job)
+    }
+
+    async fn update(&self, id: u64, f: impl FnOnce(&mut Job)) -> anyhow::Result<Option<Job>> {
+        let mut book = self.book.write().await;
+        let Some(job) = book.jobs.get_mut(&id) else {
+            return Ok(None);
+        };
+        job.updated_at_ms = Utc::now().timestamp_millis();
+        f(job);
+        job.refresh_progress();
+        let job = job.clone();
+        persist(&self.path, &book).await?;
+        Ok(Some(job))
+    }
+
+    /// 登记为执行中；同一任务已在执行时返回 `None`。
+    fn claim(&self, id: u64, parent: &CancellationToken) -> Option<Claim> {
+        let mut running = self.running.lock().unwrap();
+        if running.contains_key(&id) {
+            return None;
+        }
+        let token = parent.child_token();
+        running.insert(id, token.clone());
+        Some(Claim {
+            id,
+            token,
+            running: self.running.clone(),
+        })
+    }
+}
+
+/// 执行权，drop 时从执行中列表移除。
+struct Claim {
+    id: u64,
+    token: CancellationToken,
+    running: Arc<Mutex<HashMap<u64, CancellationToken>>>,
+}
+
+impl Drop for Claim {
+    fn drop(&mut self) {
+        self.running.lock().unwrap().remove(&self.id);
+    }
+}
+
+/// 下载请求不合法。
+#[derive(Debug)]
+pub enum PlanError {
+    Invalid(String),
+    /// symbol 格式不对或市场不支持
+    InvalidSymbol(String),
+    InvalidInterval(String),
+    /// 证券列表中没有
+    UnknownSecurity(String),
+}
+
+impl std::fmt::Display for PlanError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PlanError::Invalid(msg) => write!(f, "{msg}"),
+            PlanError::InvalidSymbol(symbol) => write!(f, "unsupported symbol {symbol}"),
+            PlanError::InvalidInterval(interval) => write!(f, "unsupported interval {interval}"),
+            PlanError::UnknownSecurity(symbol) => write!(f, "unknown security {symbol}"),
+        }
+    }
+}
+
+impl std::error::Error for PlanError {}
+
+/// 校验请求并展开为 (symbol, interval) 列表，日期缺省规则与导出接口一致。
+pub async fn plan(
+    spec: &BackfillSpec,
+    securities: &SecurityMaster,
+) -> Result<Vec<BackfillTask>, PlanError> {
+    if spec.symbols.is_empty() {
+        return Err(PlanError::Invalid("symbols must not be empty".to_string()));
+    }
+    let default_intervals = ["1d".to_string()];
+    let intervals = if spec.intervals.is_empty() {
+        &default_intervals[..]
+    } else {
+        &spec.intervals[..]
+    };
+    if let Some(bad) = intervals
+        .iter()
+        .find(|i| !SUPPORTED_INTERVALS.contains(&i.as_str()))
+    {
+        return Err(PlanError::InvalidInterval(bad.clone()));
+    }
+
+    let mut tasks = Vec::new();
+    for symbol in &spec.symbols {
+        if parse_symbol(symbol).is_none() {
+            return Err(PlanError::InvalidSymbol(symbol.clone()));
+        }
+        let Some(security) = securities.get(symbol).await else {
+            return Err(PlanError::UnknownSecurity(symbol.clone()));
+        };
+        for interval in intervals {
+            if tasks
+                .iter()
+                .any(|t: &BackfillTask| &t.symbol == symbol && &t.interval == interval)
+            {
+                continue;
+            }
+            let end = spec.end.unwrap_or_else(|| market_today(symbol));
+            let mut start = spec
+                .start
+                .unwrap_or_else(|| default_start(interval, security.listed, end));
+            if let Some(listed) = security.listed {
+                start = start.max(listed);
+            }
+            if start > end {
+                return Err(PlanError::Invalid(format!(
+                    "{symbol}: start {start} is after end {end}"
+                )));
+            }
+            tasks.push(BackfillTask {
+                symbol: symbol.clone(),
+                interval: interval.clone(),
+                start,
+                end,
+                cursor: None,
+                windows_done: 0,
+                windows_total: windows(interval, start, end).len(),
+                bars: 0,
+                committed_bytes: 0,
+                state: JobState::Queued,
+                error: None,
+            });
+        }
+    }
+    Ok(tasks)
+}
+
+/// 在后台执行任务，随服务退出而暂停，下次启动时续传。
+pub fn spawn_job(state: AppState, id: u64) {
+    let tasks = state.tasks().clone();
+    tasks.spawn(async move {
+        if let Err(err) = run_job(&state, id).await {
+            tracing::error!(job = id, error = %format!("{err:#}"), "backfill job failed");
+        }
+    });
+}
+
+/// 启动时续传上次未完成的任务。
+pub async fn resume_jobs(state: &AppState) {
+    for id in state.jobs().unfinished().await {
+        tracing::info!(job = id, "resuming backfill job");
+        spawn_job(state.clone(), id);
+    }
+}
+
+/// 依次下载任务中的每个 (symbol, interval)，直到完成、被取消或服务退出。
+///
+/// 返回时任务的最新状态；服务退出时任务保持 `running`，留待续传。
+pub async fn run_job(state: &AppState, id: u64) -> anyhow::Result<Option<Job>> {
+    let jobs = state.jobs();
+    let Some(claim) = jobs.claim(id, state.shutdown_token()) else {
+        return Ok(jobs.get(id).await);
+    };
+    let Some(job) = jobs
+        .update(id, |job| {
+            if job.state == JobState::Queued {
+                job.state = JobState::Running;
+            }
+        })
+        .await?
+    else {
+        return Ok(None);
+    };
+    if job.state.is_finished() {
+        return Ok(Some(job));
+    }
+
+    let adjust = job.spec.adjust;
+    for (idx, task) in job.tasks.iter().enumerate() {
+        if task.state.is_finished() {
+            continue;
+        }
+        let history = jobs.history_path(&task.symbol, &task.interval, adjust);
+        let part = jobs.part_path(id, &task.symbol, &task.interval, adjust);
+        let outcome = run_task(state, id, idx, task, adjust, &part, &claim.token).await;
+        if claim.token.is_cancelled() {
+            let job = jobs.get(id).await;
+            // 用户取消时已下载的部分并入历史文件；服务退出时分片留待续传
+            if job.as_ref().is_some_and(|j| j.state == JobState::Cancelled) {
+                jobs.merge(&part, &history).await?;
+                remove_part(&part).await?;
+            }
+            return Ok(job);
+        }
+        // 失败的任务也保留已下载的窗口
+        let outcome = outcome.and(jobs.merge(&part, &history).await);
+        let (task_state, error) = match outcome {
+            Ok(()) => (JobState::Completed, None),
+            Err(err) => {
+                tracing::warn!(
+                    job = id,
+                    symbol = %task.symbol,
+                    interval = %task.interval,
+                    error = %format!("{err:#}"),
+                    "backfill task failed"
+                );
+                (JobState::Failed, Some(format!("{err:#}")))
+            }
+        };
+        // 先登记结束并清零偏移再删分片：中间崩溃的话续传会跳过该任务，不会对新建的空分片 set_len
+        jobs.update(id, |job| {
+            job.tasks[idx].state = task_state;
+            job.tasks[idx].error = error;
+            job.tasks[idx].committed_bytes = 0;
+        })
+        .await?;
+        remove_part(&part).await?;
+    }
+
+    let job = jobs
+        .update(id, |job| {
+            if job.state.is_finished() {
+                return;
+            }
+            let failed = job.tasks.iter().any(|t| t.state == JobState::Failed);
+            job.state = if failed {
+                JobState::Failed
+            } else {
+                JobState::Completed
+            };
+            job.finished_at_ms = Some(job.updated_at_ms);
+        })
+        .await?;
+    if let Some(job) = &job {
+        tracing::info!(job = id, state = ?job.state, bars = job.bars, "backfill job finished");
+    }
+    Ok(job)
+}
+
+async fn remove_part(part: &Path) -> anyhow::Result<()> {
+    match tokio::fs::remove_file(part).await {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+        _ => Ok(()),
+    }
+}
+
+async fn run_task(
+    state: &AppState,
+    id: u64,
+    idx: usize,
+    task: &BackfillTask,
+    adjust: Adjust,
+    path: &Path,
+    cancel: &CancellationToken,
+) -> anyhow::Result<()> {
+    if let Some(dir) = path.parent() {
+        tokio::fs::create_dir_all(dir).await?;
+    }
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path)
+        .await
+        .with_context(|| format!("open {} failed", path.display()))?;
+    // 丢弃上次崩溃时没来得及登记的半个窗口
+    file.set_len(task.committed_bytes).await?;
+    let mut committed = task.committed_bytes;
+    file.seek(std::io::SeekFrom::Start(committed)).await?;
+
+    state
+        .jobs()
+        .update(id, |job| job.tasks[idx].state = JobState::Running)
+        .await?;
+    let remaining = windows(&task.interval, task.start, task.end)
+        .into_iter()
+        .filter(|(from, _)| task.cursor.is_none_or(|cursor| *from > cursor));
+    for (from, to) in remaining {
+        let Some(bars) = fetch_window(state, task, adjust, from, to, cancel).await? else {
+            return Ok(());
+        };
+        let mut buf = Vec::new();
+        for bar in &bars {
+            serde_json::to_writer(&mut buf, bar)?;
+            buf.push(b'\n');
+        }
+        file.write_all(&buf).await?;
+        file.sync_data().await?;
+        committed += buf.len() as u64;
+        state
+            .jobs()
+            .update(id, |job| {
+                let task = &mut job.tasks[idx];
+                task.cursor = Some(to);
+                task.windows_done += 1;
+                task.bars += bars.len() as u64;
+                task.committed_bytes = committed;
+            })
+            .await?;
+    }
+    Ok(())
+}
+
+/// 拉取一个窗口，失败时按配置重试；本地限流或熔断只等待不计次。取消时返回 `None`。
+async fn fetch_window(
+    state: &AppState,
+    task: &BackfillTask,
+    adjust: Adjust,
+    from: NaiveDate,
+    to: NaiveDate,
+    cancel: &CancellationToken,
+) -> anyhow::Result<Option<Vec<Kline>>> {
+    let cfg: &BackfillConfig = &state.config().backfill;
+    let ashare: &AshareService = state.ashare();
+    let mut failures = 0;
+    loop {
+        let result = tokio::select! {
+            result = ashare.fetch_kline_range(&task.symbol, &task.interval, adjust, from, to) => result,
+            _ = cancel.cancelled() => return Ok(None),
+        };
+        let err = match result {
+            Ok(bars) => return Ok(Some(bars)),
+            Err(err) => err,
+        };
+        let wait = match err.downcast_ref::<UpstreamUnavailable>() {
+            Some(UpstreamUnavailable::CircuitOpen { retry_after, .. }) => *retry_after,
+            Some(UpstreamUnavailable::RateLimited { .. }) => cfg.retry_delay(),
+            None => {
+                failures += 1;
+                if failures > cfg.max_retries {
+                    return Err(err.context(format!("window {from}..{to}")));
+                }
+                cfg.retry_delay()
+            }
+        };
+        tracing::debug!(
+            symbol = %task.symbol,
+            interval = %task.interval,
+            %from,
+            failures,
+            error = %format!("{err:#}"),
+            "backfill window failed, retrying"
+        );
+        tokio::select! {
+            _ = tokio::time::sleep(wait) => {}
+            _ = cancel.cancelled() => return Ok(None),
+        }
+    }
+}
+
+/// 读出 JSONL 文件中的 K 线，文件不存在时为空。
+async fn read_bars(path: &Path) -> anyhow::Result<Vec<Kline>> {
+    let body = match tokio::fs::read_to_string(path).await {
+        Ok(body) => body,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err).with_context(|| format!("read {} failed", path.display())),
+    };
+    body.lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            serde_json::from_str(line).with_context(|| format!("parse {} failed", path.display()))
+        })
+        .collect()
+}
+
+async fn persist(path: &Path, book: &JobBook) -> anyhow::Result<()> {
+    if let Some(dir) = path.parent() {
+        tokio::fs::create_dir_all(dir).await?;
+    }
+    let body = serde_json::to_vec_pretty(book)?;
+    let tmp = path.with_extension("json.tmp");
+    tokio::fs::write(&tmp, body).await?;
+    tokio::fs::rename(&tmp, path)
+        .await
+        .with_context(|| format!("write {} failed", path.display()))
+}
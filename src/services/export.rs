@@ -0,0 +1,457 @@
+use crate::models::kline::{Adjust, Kline};
+use crate::models::market::parse_symbol;
+use crate::services::ashare::AshareService;
+use anyhow::Context;
+use arrow_array::builder::{
+    BooleanBuilder, Float64Builder, StringBuilder, TimestampMillisecondBuilder,
+};
+use arrow_array::{ArrayRef, RecordBatch};
+use arrow_schema::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use bytes::Bytes;
+use chrono::{Days, NaiveDate, Utc};
+use futures_util::stream::{self, Stream};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::io::Write;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+/// 导出文件中的列，CSV 标题行、JSONL 字段与 Parquet schema 均按此顺序。
+pub const COLUMNS: [&str; 15] = [
+    "symbol",
+    "interval",
+    "adjust",
+    "adjusted",
+    "open_time",
+    "open",
+    "high",
+    "low",
+    "close",
+    "volume",
+    "amount",
+    "amplitude_pct",
+    "change_pct",
+    "change",
+    "turnover_pct",
+];
+
+/// K 线导出格式。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    /// 每行一个 JSON 对象
+    Jsonl,
+    Parquet,
+}
+
+impl ExportFormat {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Jsonl => "application/x-ndjson",
+            ExportFormat::Parquet => "application/vnd.apache.parquet",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Jsonl => "jsonl",
+            ExportFormat::Parquet => "parquet",
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(ExportFormat::Csv),
+            "jsonl" | "ndjson" => Ok(ExportFormat::Jsonl),
+            "parquet" => Ok(ExportFormat::Parquet),
+            other => Err(format!(
+                "unsupported format {other}, expected csv, jsonl or parquet"
+            )),
+        }
+    }
+}
+
+/// 一次导出的范围，日期为交易所日期，含两端。
+#[derive(Debug, Clone)]
+pub struct ExportRequest {
+    pub symbol: String,
+    pub interval: String,
+    pub adjust: Adjust,
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+}
+
+impl ExportRequest {
+    /// 下载文件名，如 `600000.SH_1d_forward_20200101-20241231.csv`。
+    pub fn file_name(&self, format: ExportFormat) -> String {
+        format!(
+            "{}_{}_{}_{}-{}.{}",
+            self.symbol,
+            self.interval,
+            self.adjust.name(),
+            self.start.format("%Y%m%d"),
+            self.end.format("%Y%m%d"),
+            format.extension()
+        )
+    }
+}
+
+/// 没有上市日期时日线及以上周期的默认起点。
+const EARLIEST: NaiveDate = NaiveDate::from_ymd_opt(1990, 1, 1).unwrap();
+/// 分钟线上游只保留最近一段，缺省只取最近 30 天。
+const INTRADAY_DEFAULT_DAYS: u64 = 30;
+
+/// symbol 所在交易所的当地日期。
+pub fn market_today(symbol: &str) -> NaiveDate {
+    match parse_symbol(symbol) {
+        Some((_, market)) => Utc::now().with_timezone(&market.timezone()).date_naive(),
+        None => Utc::now().date_naive(),
+    }
+}
+
+/// 未指定起始日期时的起点：分钟线为 `end` 前 30 天，其余为上市日期。
+pub fn default_start(interval: &str, listed: Option<NaiveDate>, end: NaiveDate) -> NaiveDate {
+    if matches!(interval, "1m" | "5m" | "15m" | "30m" | "1h") {
+        end - Days::new(INTRADAY_DEFAULT_DAYS)
+    } else {
+        listed.unwrap_or(EARLIEST)
+    }
+}
+
+/// 每次向上游请求覆盖的天数，分钟线每天最多 240 根，窗口随周期缩放。
+pub fn window_days(interval: &str) -> u64 {
+    match interval {
+        "1m" => 10,
+        "5m" => 60,
+        "15m" | "30m" | "1h" => 180,
+        "1d" => 366,
+        "1w" => 5 * 366,
+        _ => 20 * 366,
+    }
+}
+
+/// 把 `[start, end]` 切成按时间先后、互不重叠的窗口。
+pub fn windows(interval: &str, start: NaiveDate, end: NaiveDate) -> Vec<(NaiveDate, NaiveDate)> {
+    let span = window_days(interval);
+    let mut out = Vec::new();
+    let mut from = start;
+    while from <= end {
+        let to = from
+            .checked_add_days(Days::new(span - 1))
+            .map_or(end, |d| d.min(end));
+        out.push((from, to));
+        match to.checked_add_days(Days::new(1)) {
+            Some(next) => from = next,
+            None => break,
+        }
+    }
+    out
+}
+
+/// 流式导出：按窗口依次拉取、编码、发出，内存中最多只有一个窗口的 K 线。
+pub struct KlineExport {
+    ashare: AshareService,
+    req: ExportRequest,
+    windows: VecDeque<(NaiveDate, NaiveDate)>,
+    encoder: Encoder,
+    first: Bytes,
+}
+
+impl KlineExport {
+    /// 先拉第一个窗口，symbol 不支持、上游不可用等错误能在发出响应头前返回。
+    pub async fn start(
+        ashare: AshareService,
+        req: ExportRequest,
+        format: ExportFormat,
+    ) -> anyhow::Result<Self> {
+        let mut windows: VecDeque<_> = windows(&req.interval, req.start, req.end).into();
+        let mut encoder = Encoder::new(format, &req)?;
+        let mut first = encoder.header()?.to_vec();
+        if let Some((from, to)) = windows.pop_front() {
+            let bars = ashare
+                .fetch_kline_range(&req.symbol, &req.interval, req.adjust, from, to)
+                .await?;
+            first.extend_from_slice(&encoder.encode(&bars)?);
+        }
+        Ok(Self {
+            ashare,
+            req,
+            windows,
+            encoder,
+            first: first.into(),
+        })
+    }
+
+    /// 响应体。中途出错时流以错误结束，客户端会看到连接被中断而不是一个不完整的文件。
+    pub fn into_stream(self) -> impl Stream<Item = anyhow::Result<Bytes>> + Send + 'static {
+        let first = self.first.clone();
+        let rest = stream::try_unfold(Some(self), |export| async move {
+            let Some(mut export) = export else {
+                return Ok(None);
+            };
+            while let Some((from, to)) = export.windows.pop_front() {
+                let bars = export
+                    .ashare
+                    .fetch_kline_range(
+                        &export.req.symbol,
+                        &export.req.interval,
+                        export.req.adjust,
+                        from,
+                        to,
+                    )
+                    .await
+                    .inspect_err(|err| {
+                        tracing::warn!(
+                            symbol = %export.req.symbol,
+                            %from,
+                            %to,
+                            error = %format!("{err:#}"),
+                            "kline export aborted"
+                        );
+                    })?;
+                let chunk = export.encoder.encode(&bars)?;
+                if !chunk.is_empty() {
+                    return Ok(Some((chunk, Some(export))));
+                }
+            }
+            let tail = export.encoder.finish()?;
+            Ok(Some((tail, None)))
+        });
+        futures_util::StreamExt::chain(stream::once(async move { Ok(first) }), rest)
+    }
+}
+
+/// 把 K 线编码为导出格式。
+pub struct Encoder {
+    req: ExportRequest,
+    inner: Inner,
+}
+
+enum Inner {
+    Csv,
+    Jsonl,
+    Parquet {
+        writer: Box<ArrowWriter<SharedBuf>>,
+        buf: SharedBuf,
+        schema: SchemaRef,
+    },
+}
+
+#[derive(Serialize)]
+struct JsonRow<'a> {
+    symbol: &'a str,
+    interval: &'a str,
+    adjust: Adjust,
+    adjusted: bool,
+    #[serde(flatten)]
+    kline: &'a Kline,
+}
+
+impl Encoder {
+    pub fn new(format: ExportFormat, req: &ExportRequest) -> anyhow::Result<Self> {
+        let inner = match format {
+            ExportFormat::Csv => Inner::Csv,
+            ExportFormat::Jsonl => Inner::Jsonl,
+            ExportFormat::Parquet => {
+                let schema = parquet_schema();
+                let buf = SharedBuf::default();
+                let props = WriterProperties::builder()
+                    .set_compression(Compression::SNAPPY)
+                    .build();
+                let writer = ArrowWriter::try_new(buf.clone(), schema.clone(), Some(props))
+                    .context("create parquet writer failed")?;
+                Inner::Parquet {
+                    writer: Box::new(writer),
+                    buf,
+                    schema,
+                }
+            }
+        };
+        Ok(Self {
+            req: req.clone(),
+            inner,
+        })
+    }
+
+    /// 文件开头：CSV 为标题行，Parquet 为文件头。
+    pub fn header(&mut self) -> anyhow::Result<Bytes> {
+        Ok(match &self.inner {
+            Inner::Csv => format!("{}\n", COLUMNS.join(",")).into(),
+            Inner::Jsonl => Bytes::new(),
+            Inner::Parquet { buf, .. } => buf.take(),
+        })
+    }
+
+    /// 编码一批 K 线；Parquet 每批写成一个 row group。
+    pub fn encode(&mut self, bars: &[Kline]) -> anyhow::Result<Bytes> {
+        if bars.is_empty() {
+            return Ok(Bytes::new());
+        }
+        let req = &self.req;
+        match &mut self.inner {
+            Inner::Csv => {
+                let mut out = String::new();
+                let opt = |v: Option<f64>| v.map(|v| v.to_string()).unwrap_or_default();
+                for k in bars {
+                    let _ = writeln!(
+                        out,
+                        "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+                        req.symbol,
+                        req.interval,
+                        req.adjust.name(),
+                        req.adjust.is_adjusted(),
+                        k.open_time,
+                        k.open,
+                        k.high,
+                        k.low,
+                        k.close,
+                        k.volume,
+                        opt(k.amount),
+                        opt(k.amplitude_pct),
+                        opt(k.change_pct),
+                        opt(k.change),
+                        opt(k.turnover_pct),
+                    );
+                }
+                Ok(out.into())
+            }
+            Inner::Jsonl => {
+                let mut out = Vec::new();
+                for kline in bars {
+                    serde_json::to_writer(
+                        &mut out,
+                        &JsonRow {
+                            symbol: &req.symbol,
+                            interval: &req.interval,
+                            adjust: req.adjust,
+                            adjusted: req.adjust.is_adjusted(),
+                            kline,
+                        },
+                    )?;
+                    out.push(b'\n');
+                }
+                Ok(out.into())
+            }
+            Inner::Parquet {
+                writer,
+                buf,
+                schema,
+            } => {
+                let batch = record_batch(schema.clone(), req, bars)?;
+                writer.write(&batch).context("write parquet batch failed")?;
+                writer.flush().context("flush parquet row group failed")?;
+                Ok(buf.take())
+            }
+        }
+    }
+
+    /// 文件结尾：Parquet 的 footer，其它格式为空。
+    pub fn finish(self) -> anyhow::Result<Bytes> {
+        match self.inner {
+            Inner::Csv | Inner::Jsonl => Ok(Bytes::new()),
+            Inner::Parquet { writer, buf, .. } => {
+                writer.close().context("finish parquet file failed")?;
+                Ok(buf.take())
+            }
+        }
+    }
+}
+
+fn parquet_schema() -> SchemaRef {
+    let float = |name: &str, nullable: bool| Field::new(name, DataType::Float64, nullable);
+    Arc::new(Schema::new(vec![
+        Field::new("symbol", DataType::Utf8, false),
+        Field::new("interval", DataType::Utf8, false),
+        Field::new("adjust", DataType::Utf8, false),
+        Field::new("adjusted", DataType::Boolean, false),
+        Field::new(
+            "open_time",
+            DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into())),
+            false,
+        ),
+        float("open", false),
+        float("high", false),
+        float("low", false),
+        float("close", false),
+        float("volume", false),
+        float("amount", true),
+        float("amplitude_pct", true),
+        float("change_pct", true),
+        float("change", true),
+        float("turnover_pct", true),
+    ]))
+}
+
+fn record_batch(
+    schema: SchemaRef,
+    req: &ExportRequest,
+    bars: &[Kline],
+) -> anyhow::Result<RecordBatch> {
+    let n = bars.len();
+    let repeat = |v: &str| {
+        let mut b = StringBuilder::with_capacity(n, n * v.len());
+        (0..n).for_each(|_| b.append_value(v));
+        Arc::new(b.finish()) as ArrayRef
+    };
+    let floats = |f: &dyn Fn(&Kline) -> Option<f64>| {
+        let mut b = Float64Builder::with_capacity(n);
+        bars.iter().for_each(|k| b.append_option(f(k)));
+        Arc::new(b.finish()) as ArrayRef
+    };
+    let mut adjusted = BooleanBuilder::with_capacity(n);
+    (0..n).for_each(|_| adjusted.append_value(req.adjust.is_adjusted()));
+    let mut open_time = TimestampMillisecondBuilder::with_capacity(n);
+    bars.iter()
+        .for_each(|k| open_time.append_value(k.open_time));
+
+    let columns: Vec<ArrayRef> = vec![
+        repeat(&req.symbol),
+        repeat(&req.interval),
+        repeat(req.adjust.name()),
+        Arc::new(adjusted.finish()),
+        Arc::new(open_time.finish().with_timezone("UTC")),
+        floats(&|k| Some(k.open)),
+        floats(&|k| Some(k.high)),
+        floats(&|k| Some(k.low)),
+        floats(&|k| Some(k.close)),
+        floats(&|k| Some(k.volume)),
+        floats(&|k| k.amount),
+        floats(&|k| k.amplitude_pct),
+        floats(&|k| k.change_pct),
+        floats(&|k| k.change),
+        floats(&|k| k.turnover_pct),
+    ];
+    RecordBatch::try_new(schema, columns).context("build parquet batch failed")
+}
+
+/// `ArrowWriter` 的输出缓冲，每写完一个 row group 就取走已写出的字节。
+#[derive(Clone, Default)]
+struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+impl SharedBuf {
+    fn take(&self) -> Bytes {
+        std::mem::take(&mut *self.0.lock().unwrap()).into()
+    }
+}
+
+impl Write for SharedBuf {
+    fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(data);
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
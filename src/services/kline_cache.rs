@@ -0,0 +1,75 @@
+use crate::models::kline::Kline;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+/// K 线短时缓存，按 (symbol, interval) 存放，过期或超出容量时淘汰。
+///
+/// 多个页面同时打开同一只股票时，避免每次切换周期都打到上游。
+#[derive(Clone)]
+pub struct KlineCache {
+    ttl: Duration,
+    capacity: usize,
+    entries: Arc<RwLock<HashMap<(String, String), CacheEntry>>>,
+}
+
+struct CacheEntry {
+    stored_at: Instant,
+    klines: Arc<Vec<Kline>>,
+}
+
+impl KlineCache {
+    /// `ttl` 为 0 时不缓存。
+    pub fn new(ttl: Duration, capacity: usize) -> Self {
+        Self {
+            ttl,
+            capacity,
+            entries: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    pub async fn get(&self, symbol: &str, interval: &str) -> Option<Arc<Vec<Kline>>> {
+        let entries = self.entries.read().await;
+        let entry = entries.get(&(symbol.to_string(), interval.to_string()))?;
+        (entry.stored_at.elapsed() < self.ttl).then(|| entry.klines.clone())
+    }
+
+    pub async fn insert(
+        &self,
+        symbol: &str,
+        interval: &str,
+        klines: Vec<Kline>,
+    ) -> Arc<Vec<Kline>> {
+        let klines = Arc::new(klines);
+        if self.ttl.is_zero() || self.capacity == 0 {
+            return klines;
+        }
+        let mut entries = self.entries.write().await;
+        entries.retain(|_, e| e.stored_at.elapsed() < self.ttl);
+        if entries.len() >= self.capacity
+            && let Some(oldest) = entries
+                .iter()
+                .min_by_key(|(_, e)| e.stored_at)
+                .map(|(k, _)| k.clone())
+        {
+            entries.remove(&oldest);
+        }
+        entries.insert(
+            (symbol.to_string(), interval.to_string()),
+            CacheEntry {
+                stored_at: Instant::now(),
+                klines: klines.clone(),
+            },
+        );
+        klines
+    }
+
+    /// 清空缓存，返回清掉的条目数。
+    pub async fn clear(&self) -> usize {
+        let mut entries = self.entries.write().await;
+        let n = entries.len();
+        entries.clear();
+        n
+    }
+}
@@ -0,0 +1,253 @@
+use crate::services::upstream::UpstreamUnavailable;
+use prometheus::{
+    Encoder, GaugeVec, Histogram, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge,
+    Opts, Registry, TextEncoder,
+};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Prometheus 指标集合，`GET /metrics` 以文本格式导出。
+///
+/// 报价延迟和 WebSocket 连接数这类“当前值”在抓取时现算，其余在事件发生处累加。
+#[derive(Clone)]
+pub struct Metrics {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    registry: Registry,
+    upstream_duration: HistogramVec,
+    upstream_errors: IntCounterVec,
+    upstream_rejected: IntCounterVec,
+    quote_staleness: GaugeVec,
+    quote_divergence: IntCounterVec,
+    broadcast_lagged: IntCounterVec,
+    ws_connections: IntGauge,
+    ws_messages_sent: IntCounter,
+    ws_disconnects: IntCounterVec,
+    ws_conflations: IntCounter,
+    ws_conflated: IntCounter,
+    kline_cache: IntCounterVec,
+    http_duration: HistogramVec,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let registry = Registry::new_custom(Some("showmarket".to_string()), None)
+            .expect("valid metrics prefix");
+
+        let upstream_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "upstream_request_duration_seconds",
+                "Latency of requests to the market data provider",
+            )
+            .buckets(vec![0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0]),
+            &["endpoint"],
+        )
+        .unwrap();
+        let upstream_errors = IntCounterVec::new(
+            Opts::new(
+                "upstream_errors_total",
+                "Failed requests to the market data provider",
+            ),
+            &["endpoint"],
+        )
+        .unwrap();
+        let upstream_rejected = IntCounterVec::new(
+            Opts::new(
+                "upstream_rejected_total",
+                "Requests rejected locally by the rate limiter or an open circuit",
+            ),
+            &["host", "reason"],
+        )
+        .unwrap();
+        let quote_staleness = GaugeVec::new(
+            Opts::new(
+                "quote_staleness_seconds",
+                "Seconds since the latest quote for a symbol was received",
+            ),
+            &["symbol"],
+        )
+        .unwrap();
+        let quote_divergence = IntCounterVec::new(
+            Opts::new(
+                "quote_divergence_total",
+                "Reconciliation rounds where primary and backup prices disagreed beyond tolerance",
+            ),
+            &["symbol"],
+        )
+        .unwrap();
+        let broadcast_lagged = IntCounterVec::new(
+            Opts::new(
+                "broadcast_lagged_messages_total",
+                "Messages skipped because a broadcast receiver fell behind",
+            ),
+            &["consumer"],
+        )
+        .unwrap();
+        let ws_connections =
+            IntGauge::new("ws_connections", "Currently connected WebSocket clients").unwrap();
+        let ws_messages_sent = IntCounter::new(
+            "ws_messages_sent_total",
+            "Messages sent to WebSocket clients",
+        )
+        .unwrap();
+        let ws_disconnects = IntCounterVec::new(
+            Opts::new(
+                "ws_disconnects_total",
+                "WebSocket connections closed, by reason",
+            ),
+            &["reason"],
+        )
+        .unwrap();
+        let ws_conflations = IntCounter::new(
+            "ws_conflations_total",
+            "Times a WebSocket client fell behind and switched to per-symbol conflation",
+        )
+        .unwrap();
+        let ws_conflated = IntCounter::new(
+            "ws_conflated_messages_total",
+            "Messages replaced by a newer value for the same symbol before reaching a slow client",
+        )
+        .unwrap();
+        let kline_cache = IntCounterVec::new(
+            Opts::new("kline_cache_requests_total", "Kline cache lookups"),
+            &["result"],
+        )
+        .unwrap();
+        let http_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "http_request_duration_seconds",
+                "HTTP request latency by matched route",
+            ),
+            &["method", "route", "status"],
+        )
+        .unwrap();
+
+        for c in [
+            Box::new(upstream_duration.clone()) as Box<dyn prometheus::core::Collector>,
+            Box::new(upstream_errors.clone()),
+            Box::new(upstream_rejected.clone()),
+            Box::new(quote_staleness.clone()),
+            Box::new(quote_divergence.clone()),
+            Box::new(broadcast_lagged.clone()),
+            Box::new(ws_connections.clone()),
+            Box::new(ws_messages_sent.clone()),
+            Box::new(ws_disconnects.clone()),
+            Box::new(ws_conflations.clone()),
+            Box::new(ws_conflated.clone()),
+            Box::new(kline_cache.clone()),
+            Box::new(http_duration.clone()),
+        ] {
+            registry.register(c).expect("metric registered once");
+        }
+
+        Self {
+            inner: Arc::new(Inner {
+                registry,
+                upstream_duration,
+                upstream_errors,
+                upstream_rejected,
+                quote_staleness,
+                quote_divergence,
+                broadcast_lagged,
+                ws_connections,
+                ws_messages_sent,
+                ws_disconnects,
+                ws_conflations,
+                ws_conflated,
+                kline_cache,
+                http_duration,
+            }),
+        }
+    }
+
+    /// 开始计时一次上游请求，`endpoint` 如 "quote"、"klines"、"securities"。
+    pub fn upstream_timer(&self, endpoint: &str) -> Histogram {
+        self.inner.upstream_duration.with_label_values(&[endpoint])
+    }
+
+    pub fn upstream_error(&self, endpoint: &str) {
+        self.inner
+            .upstream_errors
+            .with_label_values(&[endpoint])
+            .inc();
+    }
+
+    pub fn upstream_rejected(&self, host: &str, err: &UpstreamUnavailable) {
+        let reason = match err {
+            UpstreamUnavailable::CircuitOpen { .. } => "circuit_open",
+            UpstreamUnavailable::RateLimited { .. } => "rate_limited",
+        };
+        self.inner
+            .upstream_rejected
+            .with_label_values(&[host, reason])
+            .inc();
+    }
+
+    pub fn quote_divergence(&self, symbol: &str) {
+        self.inner
+            .quote_divergence
+            .with_label_values(&[symbol])
+            .inc();
+    }
+
+    pub fn broadcast_lagged(&self, consumer: &str, skipped: u64) {
+        self.inner
+            .broadcast_lagged
+            .with_label_values(&[consumer])
+            .inc_by(skipped);
+    }
+
+    pub fn ws_message_sent(&self) {
+        self.inner.ws_messages_sent.inc();
+    }
+
+    pub fn ws_disconnected(&self, reason: &str) {
+        self.inner.ws_disconnects.with_label_values(&[reason]).inc();
+    }
+
+    pub fn ws_conflation_started(&self) {
+        self.inner.ws_conflations.inc();
+    }
+
+    pub fn ws_conflated(&self) {
+        self.inner.ws_conflated.inc();
+    }
+
+    pub fn kline_cache(&self, hit: bool) {
+        let result = if hit { "hit" } else { "miss" };
+        self.inner.kline_cache.with_label_values(&[result]).inc();
+    }
+
+    pub fn observe_http(&self, method: &str, route: &str, status: u16, started: Instant) {
+        self.inner
+            .http_duration
+            .with_label_values(&[method, route, &status.to_string()])
+            .observe(started.elapsed().as_secs_f64());
+    }
+
+    /// 抓取前更新按需计算的指标，再导出文本格式。
+    pub fn render(&self, ws_connections: usize, quotes: &[(String, f64)]) -> String {
+        self.inner.ws_connections.set(ws_connections as i64);
+        self.inner.quote_staleness.reset();
+        for (symbol, age_secs) in quotes {
+            self.inner
+                .quote_staleness
+                .with_label_values(&[symbol.as_str()])
+                .set(*age_secs);
+        }
+
+        let mut buf = Vec::new();
+        if let Err(err) = TextEncoder::new().encode(&self.inner.registry.gather(), &mut buf) {
+            tracing::warn!(error = %err, "failed to encode metrics");
+        }
+        String::from_utf8(buf).unwrap_or_default()
+    }
+}
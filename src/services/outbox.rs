@@ -0,0 +1,165 @@
+use crate::models::encoding::{Encoding, WireFormat};
+use crate::models::event::{Published, StreamEvent};
+use crate::services::metrics::Metrics;
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tokio::time::Instant;
+use tokio_tungstenite::tungstenite::protocol::frame::Frame;
+use tokio_tungstenite::tungstenite::protocol::frame::coding::{Data, OpCode};
+use tokio_tungstenite::tungstenite::{Message, Utf8Bytes};
+
+/// 发送队列已关闭，连接的写任务已经退出。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutboxClosed;
+
+/// 积压时可以互相替代的消息：同一 symbol 的价格，或同一 symbol 的新鲜度变化。
+type ConflationKey = (String, &'static str);
+
+#[derive(Debug)]
+enum Queued {
+    Conflated(ConflationKey),
+    /// 告警逐条送达，不参与合并
+    Alert(Published),
+}
+
+/// 一个 WebSocket 连接的待发送消息。
+///
+/// 平时直接进有界队列；队列满了说明客户端落后，此后的价格与新鲜度消息按 symbol 合并，
+/// 只保留序号最大的一条，告警则全部保留。补发按序号顺序进行，合并造成跳号的 symbol，
+/// 之后发出的第一条消息带上 `conflated_from`，客户端据此不必 resync。
+pub struct Outbox {
+    tx: mpsc::Sender<Message>,
+    format: WireFormat,
+    pending: HashMap<ConflationKey, Published>,
+    order: VecDeque<Queued>,
+    /// 每个 symbol 被合并掉的最小序号，下一条发出的该 symbol 消息会带上它
+    skipped: HashMap<String, u64>,
+    behind_since: Option<Instant>,
+    metrics: Metrics,
+}
+
+impl Outbox {
+    pub fn new(tx: mpsc::Sender<Message>, format: WireFormat, metrics: Metrics) -> Self {
+        Self {
+            tx,
+            format,
+            pending: HashMap::new(),
+            order: VecDeque::new(),
+            skipped: HashMap::new(),
+            behind_since: None,
+            metrics,
+        }
+    }
+
+    pub fn sender(&self) -> &mpsc::Sender<Message> {
+        &self.tx
+    }
+
+    pub fn format(&self) -> WireFormat {
+        self.format
+    }
+
+    pub fn is_behind(&self) -> bool {
+        !self.order.is_empty()
+    }
+
+    /// 连续落后了多久，没有积压时为 0。
+    pub fn behind_for(&self) -> Duration {
+        self.behind_since.map_or(Duration::ZERO, |t| t.elapsed())
+    }
+
+    pub fn push_all(&mut self, batch: Vec<Published>) -> Result<(), OutboxClosed> {
+        batch.into_iter().try_for_each(|p| self.push(p))
+    }
+
+    pub fn push(&mut self, published: Published) -> Result<(), OutboxClosed> {
+        if !self.is_behind() {
+            match self.tx.clone().try_reserve_owned() {
+                Ok(permit) => {
+                    if let Some(msg) = self.frame(published) {
+                        permit.send(msg);
+                    }
+                    return Ok(());
+                }
+                Err(TrySendError::Closed(_)) => return Err(OutboxClosed),
+                Err(TrySendError::Full(_)) => {
+                    self.behind_since = Some(Instant::now());
+                    self.metrics.ws_conflation_started();
+                }
+            }
+        }
+
+        let Some(key) = conflation_key(&published.event) else {
+            self.order.push_back(Queued::Alert(published));
+            return Ok(());
+        };
+        let dropped = match self.pending.get(&key) {
+            None => {
+                self.pending.insert(key.clone(), published);
+                self.order.push_back(Queued::Conflated(key));
+                return Ok(());
+            }
+            // 补发或 resync 带来的旧消息不能覆盖更新的
+            Some(current) if current.seq >= published.seq => published.seq,
+            Some(_) => {
+                let old = self.pending.insert(key.clone(), published).map(|p| p.seq);
+                // 挪到队尾，保证同一 symbol 的消息按序号发出
+                self.order
+                    .retain(|q| !matches!(q, Queued::Conflated(k) if *k == key));
+                self.order.push_back(Queued::Conflated(key.clone()));
+                old.unwrap_or_default()
+            }
+        };
+        self.metrics.ws_conflated();
+        let from = self.skipped.entry(key.0).or_insert(dropped);
+        *from = (*from).min(dropped);
+        Ok(())
+    }
+
+    pub fn flush_one(&mut self, permit: mpsc::Permit<'_, Message>) {
+        let published = match self.order.pop_front() {
+            None => return,
+            Some(Queued::Alert(published)) => Some(published),
+            Some(Queued::Conflated(key)) => self.pending.remove(&key),
+        };
+        if let Some(msg) = published.and_then(|p| self.frame(p)) {
+            permit.send(msg);
+        }
+        if self.order.is_empty() {
+            self.behind_since = None;
+        }
+    }
+
+    /// 编码成帧；该 symbol 之前有消息被合并掉时带上 `conflated_from`。
+    fn frame(&mut self, published: Published) -> Option<Message> {
+        let published = match self.skipped.remove(published.event.symbol()) {
+            Some(from) if from < published.seq => published.conflated_from(from),
+            _ => published,
+        };
+        let data = published.encoded(self.format)?;
+        let text = self.format.encoding == Encoding::Json;
+        if self.format.deflate {
+            // 压缩过的消息只能以原始帧发出，首帧置 RSV1
+            let data_type = if text { Data::Text } else { Data::Binary };
+            let mut frame = Frame::message(data, OpCode::Data(data_type), true);
+            frame.header_mut().rsv1 = true;
+            return Some(Message::Frame(frame));
+        }
+        Some(if text {
+            Message::Text(Utf8Bytes::try_from(data).ok()?)
+        } else {
+            Message::Binary(data)
+        })
+    }
+}
+
+fn conflation_key(event: &StreamEvent) -> Option<ConflationKey> {
+    let kind = match event {
+        StreamEvent::Alert(_) => return None,
+        // 后一条新鲜度变化总是代表当前状态
+        StreamEvent::Stale(_) | StreamEvent::Recovered(_) => "freshness",
+        other => other.kind(),
+    };
+    Some((event.symbol().to_string(), kind))
+}
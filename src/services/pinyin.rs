@@ -0,0 +1,140 @@
+//! 由证券中文简称生成拼音首字母。
+//!
+//! GB2312 一级汉字按拼音排序，编码落在哪个区间即可得到首字母；二级汉字和多音字
+//! 查 `OVERRIDES`，读音随词变化的多音字查 `PHRASES`，都查不到的字跳过。
+
+/// GB2312 一级汉字中各首字母的起始编码（没有 I、U、V 开头的音节）。
+const GB2312_INITIALS: [(u16, char); 23] = [
+    (0xB0A1, 'A'),
+    (0xB0C5, 'B'),
+    (0xB2C1, 'C'),
+    (0xB4EE, 'D'),
+    (0xB6EA, 'E'),
+    (0xB7A2, 'F'),
+    (0xB8C1, 'G'),
+    (0xB9FE, 'H'),
+    (0xBBF7, 'J'),
+    (0xBFA6, 'K'),
+    (0xC0AC, 'L'),
+    (0xC2E8, 'M'),
+    (0xC4C3, 'N'),
+    (0xC5B6, 'O'),
+    (0xC5BE, 'P'),
+    (0xC6DA, 'Q'),
+    (0xC8BB, 'R'),
+    (0xC8F6, 'S'),
+    (0xCBFA, 'T'),
+    (0xCDDA, 'W'),
+    (0xCEF4, 'X'),
+    (0xD1B9, 'Y'),
+    (0xD4D1, 'Z'),
+];
+const GB2312_LEVEL1_END: u16 = 0xD7F9;
+
+/// 证券简称中按 GB2312 顺序会取错的多音字，以及常见的二级汉字。
+const OVERRIDES: &[(char, char)] = &[
+    // 多音字：银行、厦门、西藏
+    ('行', 'H'),
+    ('厦', 'X'),
+    ('藏', 'Z'),
+    // 二级汉字
+    ('鑫', 'X'),
+    ('昊', 'H'),
+    ('晟', 'S'),
+    ('锂', 'L'),
+    ('钼', 'M'),
+    ('钴', 'G'),
+    ('钛', 'T'),
+    ('铂', 'B'),
+    ('钨', 'W'),
+    ('镍', 'N'),
+    ('铟', 'Y'),
+    ('锆', 'G'),
+    ('珀', 'P'),
+    ('琨', 'K'),
+    ('璞', 'P'),
+    ('瀚', 'H'),
+    ('烨', 'Y'),
+    ('煜', 'Y'),
+    ('玮', 'W'),
+    ('琪', 'Q'),
+    ('骅', 'H'),
+    ('珂', 'K'),
+    ('钜', 'J'),
+    ('铖', 'C'),
+    ('垚', 'Y'),
+    ('淼', 'M'),
+    ('赟', 'Y'),
+    ('犇', 'B'),
+    ('骐', 'Q'),
+    ('麒', 'Q'),
+    ('彤', 'T'),
+    ('颐', 'Y'),
+    ('睿', 'R'),
+    ('曦', 'X'),
+    ('炜', 'W'),
+    ('缙', 'J'),
+    ('泸', 'L'),
+    ('漳', 'Z'),
+    ('邯', 'H'),
+    ('郸', 'D'),
+    ('汾', 'F'),
+    ('濮', 'P'),
+    ('沣', 'F'),
+    ('浔', 'X'),
+    ('婺', 'W'),
+    ('衢', 'Q'),
+];
+
+/// 只在特定词里换读音的多音字，按整词匹配：`重` 在 "中国重工" 里读 zhong，在 "重庆" 里读 chong。
+const PHRASES: &[(&str, &str)] = &[("重庆", "CQ")];
+
+/// 拼音首字母（大写），如 "浦发银行" -> "PFYH"、"万科A" -> "WKA"、"美团-W" -> "MT"。
+///
+/// 字母与数字原样保留（全角转半角），`-` 之后的股份类别后缀与其他符号忽略。
+pub fn initials(name: &str) -> String {
+    let name = name.split('-').next().unwrap_or_default();
+    let mut out = String::new();
+    let mut rest = name;
+    while let Some(c) = rest.chars().next() {
+        if let Some((phrase, letters)) = PHRASES.iter().find(|(p, _)| rest.starts_with(p)) {
+            out.push_str(letters);
+            rest = &rest[phrase.len()..];
+            continue;
+        }
+        rest = &rest[c.len_utf8()..];
+        // 全角字母数字转半角
+        let c = match c as u32 {
+            0xFF10..=0xFF19 | 0xFF21..=0xFF3A | 0xFF41..=0xFF5A => {
+                char::from_u32(c as u32 - 0xFEE0).unwrap_or(c)
+            }
+            _ => c,
+        };
+        if c.is_ascii_alphanumeric() {
+            out.push(c.to_ascii_uppercase());
+        } else if let Some(initial) = hanzi_initial(c) {
+            out.push(initial);
+        }
+    }
+    out
+}
+
+fn hanzi_initial(c: char) -> Option<char> {
+    if let Some((_, initial)) = OVERRIDES.iter().find(|(h, _)| *h == c) {
+        return Some(*initial);
+    }
+    let mut buf = [0u8; 4];
+    let (bytes, _, unmappable) = encoding_rs::GBK.encode(c.encode_utf8(&mut buf));
+    if unmappable || bytes.len() != 2 {
+        return None;
+    }
+    let code = u16::from_be_bytes([bytes[0], bytes[1]]);
+    if !(GB2312_INITIALS[0].0..=GB2312_LEVEL1_END).contains(&code) {
+        return None;
+    }
+    GB2312_INITIALS
+        .iter()
+        .rev()
+        .find(|(start, _)| code >= *start)
+        .map(|(_, initial)| *initial)
+}
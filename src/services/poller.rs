@@ -0,0 +1,70 @@
+use crate::config::PollerConfig;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::watch;
+
+/// 轮询间隔的下限，与配置校验一致。
+pub const MIN_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 运行中可调整的轮询参数：常驻 symbol 和轮询间隔。
+///
+/// 初始值来自 `[poller]`，管理接口的修改只保存在内存里，重启后恢复为配置值。
+#[derive(Clone)]
+pub struct PollerControl {
+    symbols: Arc<Mutex<Vec<String>>>,
+    interval: Arc<watch::Sender<Duration>>,
+}
+
+impl PollerControl {
+    pub fn new(cfg: &PollerConfig) -> Self {
+        let (interval, _) = watch::channel(cfg.interval());
+        Self {
+            symbols: Arc::new(Mutex::new(cfg.symbols.clone())),
+            interval: Arc::new(interval),
+        }
+    }
+
+    /// 除自选列表外始终轮询的 symbol，保持添加顺序。
+    pub fn symbols(&self) -> Vec<String> {
+        self.symbols.lock().unwrap().clone()
+    }
+
+    /// 已存在时返回 false。
+    pub fn add_symbol(&self, symbol: &str) -> bool {
+        let mut symbols = self.symbols.lock().unwrap();
+        if symbols.iter().any(|s| s == symbol) {
+            return false;
+        }
+        symbols.push(symbol.to_string());
+        true
+    }
+
+    /// 不存在时返回 false。
+    pub fn remove_symbol(&self, symbol: &str) -> bool {
+        let mut symbols = self.symbols.lock().unwrap();
+        let before = symbols.len();
+        symbols.retain(|s| s != symbol);
+        symbols.len() != before
+    }
+
+    pub fn interval(&self) -> Duration {
+        *self.interval.borrow()
+    }
+
+    /// 修改轮询间隔，轮询任务在下一次 tick 前生效。
+    pub fn set_interval(&self, interval: Duration) -> anyhow::Result<()> {
+        if interval < MIN_INTERVAL {
+            anyhow::bail!(
+                "poll interval must be at least {}ms",
+                MIN_INTERVAL.as_millis()
+            );
+        }
+        self.interval.send_replace(interval);
+        Ok(())
+    }
+
+    /// 轮询任务据此在间隔变化时重建定时器。
+    pub fn watch_interval(&self) -> watch::Receiver<Duration> {
+        self.interval.subscribe()
+    }
+}
@@ -0,0 +1,277 @@
+use crate::config::{ProviderKind, ProvidersConfig};
+use crate::models::price::PriceUpdate;
+use crate::models::status::{Divergence, FeedStatus};
+use crate::services::ashare::AshareService;
+use crate::services::tencent::TencentService;
+use crate::services::upstream::{UpstreamClient, UpstreamUnavailable};
+use crate::state::AppState;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// 单个实时报价来源。
+#[derive(Clone)]
+pub enum QuoteProvider {
+    Eastmoney(AshareService),
+    Tencent(TencentService),
+}
+
+impl QuoteProvider {
+    pub fn new(kind: ProviderKind, providers: &ProvidersConfig, http: UpstreamClient) -> Self {
+        match kind {
+            ProviderKind::Eastmoney => QuoteProvider::Eastmoney(
+                AshareService::with_client(http).with_quote_url(&providers.eastmoney_url),
+            ),
+            ProviderKind::Tencent => QuoteProvider::Tencent(
+                TencentService::with_client(http).with_quote_url(&providers.tencent_url),
+            ),
+        }
+    }
+
+    pub fn kind(&self) -> ProviderKind {
+        match self {
+            QuoteProvider::Eastmoney(_) => ProviderKind::Eastmoney,
+            QuoteProvider::Tencent(_) => ProviderKind::Tencent,
+        }
+    }
+
+    pub async fn fetch_quote(&self, symbol: &str) -> anyhow::Result<PriceUpdate> {
+        match self {
+            QuoteProvider::Eastmoney(svc) => svc.fetch_realtime_quote(symbol).await,
+            QuoteProvider::Tencent(svc) => svc.fetch_realtime_quote(symbol).await,
+        }
+    }
+}
+
+#[derive(Default)]
+struct FeedState {
+    /// 切到备用源的时间，为空表示在用主源
+    on_backup_since: Option<Instant>,
+    last_probe: Option<Instant>,
+    divergences: Vec<Divergence>,
+    /// 管理接口手动指定的报价源，设置后不再自动主备切换
+    pinned: Option<QuoteProvider>,
+}
+
+/// 主备报价源：主源连接失败、超时或返回 5xx 时自动切到备用源，之后定期试探主源，恢复即切回。
+///
+/// 单个 symbol 没有数据之类的错误说明主源本身可用，直接返回给调用方，不切换。
+#[derive(Clone)]
+pub struct QuoteFeed {
+    primary: QuoteProvider,
+    backup: Option<QuoteProvider>,
+    failback_interval: Duration,
+    /// 手动切换到主备以外的报价源时用
+    providers: ProvidersConfig,
+    http: UpstreamClient,
+    state: Arc<Mutex<FeedState>>,
+}
+
+impl QuoteFeed {
+    pub fn new(providers: &ProvidersConfig, http: UpstreamClient) -> Self {
+        Self {
+            primary: QuoteProvider::new(providers.primary, providers, http.clone()),
+            backup: providers
+                .backup
+                .map(|kind| QuoteProvider::new(kind, providers, http.clone())),
+            failback_interval: providers.failback_interval(),
+            providers: providers.clone(),
+            http,
+            state: Arc::new(Mutex::new(FeedState::default())),
+        }
+    }
+
+    pub fn active(&self) -> ProviderKind {
+        let st = self.state.lock().unwrap();
+        if let Some(pinned) = &st.pinned {
+            return pinned.kind();
+        }
+        match (&self.backup, st.on_backup_since) {
+            (Some(backup), Some(_)) => backup.kind(),
+            _ => self.primary.kind(),
+        }
+    }
+
+    /// 手动指定报价源，`None` 恢复自动主备切换。只在内存中生效，重启后按配置。
+    pub fn pin(&self, kind: Option<ProviderKind>) {
+        let provider =
+            kind.map(|kind| QuoteProvider::new(kind, &self.providers, self.http.clone()));
+        let mut st = self.state.lock().unwrap();
+        st.pinned = provider;
+        // 恢复自动时从主源开始
+        st.on_backup_since = None;
+        st.last_probe = None;
+    }
+
+    pub fn pinned(&self) -> Option<ProviderKind> {
+        self.state
+            .lock()
+            .unwrap()
+            .pinned
+            .as_ref()
+            .map(QuoteProvider::kind)
+    }
+
+    pub fn status(&self) -> FeedStatus {
+        FeedStatus {
+            primary: self.primary.kind(),
+            backup: self.backup.as_ref().map(QuoteProvider::kind),
+            active: self.active(),
+            pinned: self.pinned(),
+            divergences: self.state.lock().unwrap().divergences.clone(),
+        }
+    }
+
+    pub async fn fetch_quote(&self, symbol: &str) -> anyhow::Result<PriceUpdate> {
+        let pinned = self.state.lock().unwrap().pinned.clone();
+        if let Some(provider) = pinned {
+            return provider.fetch_quote(symbol).await;
+        }
+        let Some(backup) = &self.backup else {
+            return self.primary.fetch_quote(symbol).await;
+        };
+
+        if self.on_backup() && !self.probe_due() {
+            return backup.fetch_quote(symbol).await;
+        }
+
+        match self.primary.fetch_quote(symbol).await {
+            // 主源有响应（包括某个 symbol 没有数据），说明主源可用
+            Err(err) if !is_provider_failure(&err) => {
+                self.fail_back();
+                Err(err)
+            }
+            Ok(update) => {
+                self.fail_back();
+                Ok(update)
+            }
+            Err(err) => {
+                {
+                    let mut st = self.state.lock().unwrap();
+                    if st.on_backup_since.is_none() {
+                        tracing::warn!(
+                            primary = self.primary.kind().name(),
+                            backup = backup.kind().name(),
+                            %symbol,
+                            error = %format!("{err:#}"),
+                            "primary quote provider failed, failing over"
+                        );
+                        st.on_backup_since = Some(Instant::now());
+                    }
+                    st.last_probe = Some(Instant::now());
+                }
+                backup.fetch_quote(symbol).await
+            }
+        }
+    }
+
+    fn fail_back(&self) {
+        if let Some(since) = self.state.lock().unwrap().on_backup_since.take() {
+            tracing::info!(
+                primary = self.primary.kind().name(),
+                after = ?since.elapsed(),
+                "primary quote provider healthy again, failing back"
+            );
+        }
+    }
+
+    fn on_backup(&self) -> bool {
+        self.state.lock().unwrap().on_backup_since.is_some()
+    }
+
+    /// 在备用源上时，每个 `failback_interval` 放一次请求去试探主源。
+    fn probe_due(&self) -> bool {
+        let mut st = self.state.lock().unwrap();
+        let due = st
+            .last_probe
+            .is_none_or(|t| t.elapsed() >= self.failback_interval);
+        if due {
+            st.last_probe = Some(Instant::now());
+        }
+        due
+    }
+
+    /// 分别从主备源拉取报价并比较，返回价差超过 `tolerance_pct` 的 symbol。
+    pub async fn reconcile(&self, symbols: &[String], tolerance_pct: f64) -> Vec<Divergence> {
+        let Some(backup) = &self.backup else {
+            return Vec::new();
+        };
+        let mut out = Vec::new();
+        for symbol in symbols {
+            let (a, b) = tokio::join!(self.primary.fetch_quote(symbol), backup.fetch_quote(symbol));
+            let (Ok(a), Ok(b)) = (a, b) else {
+                // 有一边拿不到就没法对账，故障切换那边会处理
+                continue;
+            };
+            let diff_pct = (a.price - b.price).abs() / a.price.abs().max(f64::EPSILON) * 100.0;
+            if diff_pct > tolerance_pct {
+                out.push(Divergence {
+                    symbol: symbol.clone(),
+                    primary_price: a.price,
+                    backup_price: b.price,
+                    diff_pct,
+                    ts_ms: chrono::Utc::now().timestamp_millis(),
+                });
+            }
+        }
+        self.state.lock().unwrap().divergences = out.clone();
+        out
+    }
+}
+
+/// 是否是报价源本身的故障：连接失败、超时、5xx，或因此打开的熔断。
+fn is_provider_failure(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| {
+        if let Some(err) = cause.downcast_ref::<reqwest::Error>() {
+            return err.is_connect()
+                || err.is_timeout()
+                || err.is_request()
+                || err.is_body()
+                || err.status().is_some_and(|s| s.is_server_error());
+        }
+        matches!(
+            cause.downcast_ref::<UpstreamUnavailable>(),
+            Some(UpstreamUnavailable::CircuitOpen { .. })
+        )
+    })
+}
+
+/// 后台对账：定期比较主备源报价，价差超出容差时打日志并计入指标。
+pub fn spawn_reconciler(state: AppState) {
+    let cfg = state.config().providers.clone();
+    if cfg.backup.is_none() || cfg.reconcile_interval_secs == 0 {
+        return;
+    }
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(cfg.reconcile_interval_secs));
+        // 第一次 tick 立即返回，跳过，等轮询先跑起来
+        interval.tick().await;
+        let shutdown = state.shutdown_token().clone();
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown.cancelled() => break,
+            }
+            let symbols: Vec<String> = state
+                .latest_all()
+                .await
+                .into_iter()
+                .map(|u| u.symbol)
+                .collect();
+            let divergences = state
+                .quotes()
+                .reconcile(&symbols, cfg.reconcile_tolerance_pct)
+                .await;
+            for d in &divergences {
+                tracing::warn!(
+                    symbol = %d.symbol,
+                    primary = d.primary_price,
+                    backup = d.backup_price,
+                    diff_pct = d.diff_pct,
+                    "quote providers disagree beyond tolerance"
+                );
+                state.metrics().quote_divergence(&d.symbol);
+            }
+        }
+    });
+}
@@ -0,0 +1,101 @@
+use crate::models::event::{Published, StreamEvent};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+/// 最近推送过的事件，按 id 顺序保留 `capacity` 条，供断线重连和缺口补发。
+///
+/// 同时为每个 symbol 分配从 1 开始连续递增的序号。
+#[derive(Clone)]
+pub struct ReplayBuffer {
+    capacity: usize,
+    inner: Arc<Mutex<Inner>>,
+}
+
+struct Inner {
+    next_id: u64,
+    seqs: HashMap<String, u64>,
+    events: VecDeque<Published>,
+}
+
+impl ReplayBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            inner: Arc::new(Mutex::new(Inner {
+                next_id: 1,
+                seqs: HashMap::new(),
+                events: VecDeque::new(),
+            })),
+        }
+    }
+
+    /// 分配 id、序号并保存事件。`send` 在锁内调用，保证广播顺序与 id 顺序一致。
+    pub fn push(&self, event: StreamEvent, send: impl FnOnce(Published)) -> u64 {
+        let mut inner = self.inner.lock().unwrap();
+        let id = inner.next_id;
+        inner.next_id += 1;
+        let seq = inner.seqs.entry(event.symbol().to_string()).or_insert(0);
+        *seq += 1;
+        let published = Published::new(id, *seq, event);
+        if inner.events.len() == self.capacity {
+            inner.events.pop_front();
+        }
+        inner.events.push_back(published.clone());
+        send(published);
+        id
+    }
+
+    /// `after` 之后的所有事件；`after` 已经被挤出缓冲区（中间有缺口）时返回 `None`。
+    pub fn since(&self, after: u64) -> Option<Vec<Published>> {
+        let inner = self.inner.lock().unwrap();
+        if after >= inner.next_id {
+            // 比已分配的 id 还大，多半是服务重启过
+            return None;
+        }
+        let oldest = inner.events.front().map_or(inner.next_id, |p| p.id);
+        if after + 1 < oldest {
+            return None;
+        }
+        Some(
+            inner
+                .events
+                .iter()
+                .filter(|p| p.id > after)
+                .cloned()
+                .collect(),
+        )
+    }
+
+    /// `symbol` 序号 `after_seq` 之后的事件；缓冲区里已不完整或序号未知时返回 `None`。
+    pub fn symbol_since(&self, symbol: &str, after_seq: u64) -> Option<Vec<Published>> {
+        let inner = self.inner.lock().unwrap();
+        let current = inner.seqs.get(symbol).copied().unwrap_or(0);
+        if after_seq > current {
+            return None;
+        }
+        let missed: Vec<Published> = inner
+            .events
+            .iter()
+            .filter(|p| p.seq > after_seq && p.event.symbol() == symbol)
+            .cloned()
+            .collect();
+        let complete = missed.len() as u64 == current - after_seq;
+        complete.then_some(missed)
+    }
+
+    /// 最近分配的 id，尚未发布过任何事件时为 0。
+    pub fn last_id(&self) -> u64 {
+        self.inner.lock().unwrap().next_id - 1
+    }
+
+    /// `symbol` 最近分配的序号，尚未发布过时为 0。
+    pub fn seq(&self, symbol: &str) -> u64 {
+        self.inner
+            .lock()
+            .unwrap()
+            .seqs
+            .get(symbol)
+            .copied()
+            .unwrap_or(0)
+    }
+}
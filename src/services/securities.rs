@@ -0,0 +1,203 @@
+use crate::models::market::parse_symbol;
+use crate::models::security::{Security, SecurityKind};
+use crate::services::ashare::AshareService;
+use crate::services::pinyin;
+use anyhow::{Context, anyhow};
+use chrono::NaiveDate;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+
+/// 随程序打包的证券列表，首次启动且尚未从行情源刷新时使用。
+const BUNDLED_CSV: &str = include_str!("../../assets/securities.csv");
+
+/// 证券主数据：代码、交易所、名称、拼音首字母、类型、上市日期。
+///
+/// 启动时优先加载 `path` 处上次刷新的结果，否则使用打包的列表。
+#[derive(Clone)]
+pub struct SecurityMaster {
+    path: PathBuf,
+    by_symbol: Arc<RwLock<HashMap<String, Security>>>,
+}
+
+impl SecurityMaster {
+    pub fn load(path: impl Into<PathBuf>) -> anyhow::Result<Self> {
+        let path = path.into();
+        let list = match std::fs::read_to_string(&path) {
+            Ok(body) => serde_json::from_str(&body)
+                .with_context(|| format!("parse securities file {} failed", path.display()))?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => parse_csv(BUNDLED_CSV)?,
+            Err(err) => {
+                return Err(err).with_context(|| format!("read {} failed", path.display()));
+            }
+        };
+        Ok(Self {
+            path,
+            by_symbol: Arc::new(RwLock::new(index(list))),
+        })
+    }
+
+    pub async fn get(&self, symbol: &str) -> Option<Security> {
+        self.by_symbol.read().await.get(symbol).cloned()
+    }
+
+    pub async fn contains(&self, symbol: &str) -> bool {
+        self.by_symbol.read().await.contains_key(symbol)
+    }
+
+    pub async fn len(&self) -> usize {
+        self.by_symbol.read().await.len()
+    }
+
+    pub async fn is_empty(&self) -> bool {
+        self.by_symbol.read().await.is_empty()
+    }
+
+    /// 按代码、symbol、拼音首字母或中文名搜索。
+    ///
+    /// 排序：完全匹配 > 代码前缀 > 拼音前缀 > 名称前缀 > 名称包含；同档按 symbol 排序。
+    pub async fn search(&self, query: &str, limit: usize) -> Vec<Security> {
+        let q = query.trim();
+        if q.is_empty() {
+            return Vec::new();
+        }
+        let q_upper = q.to_uppercase();
+
+        let map = self.by_symbol.read().await;
+        let mut hits: Vec<(u8, &Security)> = map
+            .values()
+            .filter_map(|s| {
+                let rank = if s.symbol == q_upper || s.code == q_upper || s.name == q {
+                    0
+                } else if s.code.starts_with(&q_upper) {
+                    1
+                } else if !s.pinyin.is_empty() && s.pinyin.starts_with(&q_upper) {
+                    2
+                } else if s.name.starts_with(q) {
+                    3
+                } else if s.name.contains(q) {
+                    4
+                } else {
+                    return None;
+                };
+                Some((rank, s))
+            })
+            .collect();
+        hits.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.symbol.cmp(&b.1.symbol)));
+        hits.into_iter()
+            .take(limit)
+            .map(|(_, s)| s.clone())
+            .collect()
+    }
+
+    /// 用行情源的最新列表替换当前数据并落盘。
+    ///
+    /// 行情源不提供拼音：已有记录沿用原拼音，其余由名称生成。新列表数量少于当前的
+    /// `min_ratio` 倍时视为上游数据不全，保留当前数据并返回错误。
+    pub async fn refresh(&self, svc: &AshareService, min_ratio: f64) -> anyhow::Result<usize> {
+        let mut fresh = svc.fetch_securities().await?;
+        if fresh.is_empty() {
+            return Err(anyhow!("provider returned an empty security list"));
+        }
+
+        let mut map = self.by_symbol.write().await;
+        let current = map.len();
+        if (fresh.len() as f64) < current as f64 * min_ratio {
+            return Err(anyhow!(
+                "provider returned {} securities, fewer than {min_ratio} of the current {current}; keeping the current list",
+                fresh.len()
+            ));
+        }
+        for s in &mut fresh {
+            if !s.pinyin.is_empty() {
+                continue;
+            }
+            s.pinyin = match map.get(&s.symbol) {
+                // 改名后旧拼音不再适用
+                Some(old) if old.name == s.name && !old.pinyin.is_empty() => old.pinyin.clone(),
+                _ => pinyin::initials(&s.name),
+            };
+        }
+        let count = fresh.len();
+        let body = serde_json::to_vec(&fresh)?;
+        *map = index(fresh);
+        drop(map);
+
+        if let Some(dir) = self.path.parent() {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+        let tmp = self.path.with_extension("json.tmp");
+        tokio::fs::write(&tmp, body).await?;
+        tokio::fs::rename(&tmp, &self.path)
+            .await
+            .with_context(|| format!("write {} failed", self.path.display()))?;
+        Ok(count)
+    }
+}
+
+fn index(list: Vec<Security>) -> HashMap<String, Security> {
+    list.into_iter().map(|s| (s.symbol.clone(), s)).collect()
+}
+
+/// 解析打包的 CSV：`symbol,name,pinyin,kind,listed`。
+fn parse_csv(body: &str) -> anyhow::Result<Vec<Security>> {
+    let mut out = Vec::new();
+    for (lineno, line) in body.lines().enumerate().skip(1) {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let cols: Vec<&str> = line.split(',').collect();
+        let [symbol, name, pinyin, kind, listed] = cols[..] else {
+            return Err(anyhow!(
+                "securities.csv line {}: expected 5 columns",
+                lineno + 1
+            ));
+        };
+        let (code, market) = parse_symbol(symbol)
+            .ok_or_else(|| anyhow!("securities.csv line {}: bad symbol {symbol}", lineno + 1))?;
+        let kind = match kind {
+            "stock" => SecurityKind::Stock,
+            "index" => SecurityKind::Index,
+            "etf" => SecurityKind::Etf,
+            "bond" => SecurityKind::Bond,
+            other => {
+                return Err(anyhow!(
+                    "securities.csv line {}: bad kind {other}",
+                    lineno + 1
+                ));
+            }
+        };
+        out.push(Security {
+            symbol: symbol.to_string(),
+            code: code.to_string(),
+            market,
+            name: name.to_string(),
+            pinyin: pinyin.to_string(),
+            kind,
+            listed: NaiveDate::parse_from_str(listed, "%Y-%m-%d").ok(),
+        });
+    }
+    Ok(out)
+}
+
+/// 后台任务：启动时及之后每隔 `period` 从行情源刷新一次证券列表。
+pub fn spawn_security_refresh(
+    master: SecurityMaster,
+    svc: AshareService,
+    period: Duration,
+    min_ratio: f64,
+) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+        loop {
+            interval.tick().await;
+            match master.refresh(&svc, min_ratio).await {
+                Ok(count) => tracing::info!(count, "security master refreshed"),
+                Err(err) => tracing::warn!(error = %err, "failed to refresh security master"),
+            }
+        }
+    });
+}
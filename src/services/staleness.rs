@@ -0,0 +1,98 @@
+use crate::models::event::{Freshness, StreamEvent};
+use crate::models::market::{HolidayCalendar, parse_symbol};
+use crate::models::price::PriceUpdate;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+
+#[derive(Debug)]
+struct Entry {
+    last_price: f64,
+    last_source_ts_ms: Option<i64>,
+    /// 最近一次看到报价前进的时间
+    last_fresh_ms: i64,
+    stale: bool,
+}
+
+/// 报价新鲜度判定，由轮询任务独占持有。
+///
+/// 交易时段内，报价（有源时间时看源时间，否则看价格）超过 `stale_after_ms` 没有前进，
+/// 或者一直拉取失败，即视为变旧；非交易时段（含休市日）价格不动是正常的，不会判为变旧。
+#[derive(Debug)]
+pub struct StalenessTracker {
+    stale_after_ms: i64,
+    calendar: HolidayCalendar,
+    entries: HashMap<String, Entry>,
+}
+
+impl StalenessTracker {
+    pub fn new(stale_after_ms: u64, calendar: HolidayCalendar) -> Self {
+        Self {
+            stale_after_ms: stale_after_ms as i64,
+            calendar,
+            entries: HashMap::new(),
+        }
+    }
+
+    /// 记录一条新报价并填上 `update.stale`；状态变化时返回 `stale` / `recovered` 事件。
+    pub fn observe(&mut self, update: &mut PriceUpdate, now: DateTime<Utc>) -> Option<StreamEvent> {
+        let now_ms = now.timestamp_millis();
+        let trading = self.is_trading(&update.symbol, now);
+        let entry = self
+            .entries
+            .entry(update.symbol.clone())
+            .or_insert_with(|| Entry {
+                last_price: update.price,
+                last_source_ts_ms: update.source_ts_ms,
+                last_fresh_ms: now_ms,
+                stale: false,
+            });
+
+        let advanced = match (update.source_ts_ms, entry.last_source_ts_ms) {
+            (Some(new), Some(old)) => new > old,
+            _ => update.price != entry.last_price,
+        };
+        entry.last_price = update.price;
+        entry.last_source_ts_ms = update.source_ts_ms;
+        if advanced || !trading {
+            entry.last_fresh_ms = now_ms;
+        }
+
+        let stale = trading && now_ms - entry.last_fresh_ms > self.stale_after_ms;
+        update.stale = stale;
+        transition(&update.symbol, entry, stale, now_ms)
+    }
+
+    /// 本轮拉取失败的 symbol 没有新报价，单独检查是否已变旧。
+    pub fn check_failed(&mut self, symbol: &str, now: DateTime<Utc>) -> Option<StreamEvent> {
+        let now_ms = now.timestamp_millis();
+        let trading = self.is_trading(symbol, now);
+        let entry = self.entries.get_mut(symbol)?;
+        let stale = trading && now_ms - entry.last_fresh_ms > self.stale_after_ms;
+        // 非交易时段拉取失败不改变状态，等恢复成功后再更新
+        if !stale {
+            return None;
+        }
+        transition(symbol, entry, stale, now_ms)
+    }
+
+    fn is_trading(&self, symbol: &str, now: DateTime<Utc>) -> bool {
+        parse_symbol(symbol).is_some_and(|(_, market)| self.calendar.is_trading(market, now))
+    }
+}
+
+fn transition(symbol: &str, entry: &mut Entry, stale: bool, now_ms: i64) -> Option<StreamEvent> {
+    if entry.stale == stale {
+        return None;
+    }
+    entry.stale = stale;
+    let freshness = Freshness {
+        symbol: symbol.to_string(),
+        last_fresh_ts_ms: entry.last_fresh_ms,
+        ts_ms: now_ms,
+    };
+    Some(if stale {
+        StreamEvent::Stale(freshness)
+    } else {
+        StreamEvent::Recovered(freshness)
+    })
+}
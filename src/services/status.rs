@@ -0,0 +1,157 @@
+use crate::models::admin::WsClientInfo;
+use crate::models::encoding::WireFormat;
+use crate::models::status::SymbolStatus;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio_util::sync::CancellationToken;
+
+#[derive(Debug, Default, Clone)]
+struct PollRecord {
+    last_ok_ms: Option<i64>,
+    errors: u64,
+    consecutive_errors: u64,
+    last_error: Option<String>,
+}
+
+/// 轮询结果统计，供 `/ready` 和 `/api/status` 使用。
+#[derive(Clone, Default)]
+pub struct PollStatus {
+    records: Arc<Mutex<HashMap<String, PollRecord>>>,
+}
+
+impl PollStatus {
+    pub fn record_ok(&self, symbol: &str, now_ms: i64) {
+        let mut records = self.records.lock().unwrap();
+        let rec = records.entry(symbol.to_string()).or_default();
+        rec.last_ok_ms = Some(now_ms);
+        rec.consecutive_errors = 0;
+    }
+
+    pub fn record_err(&self, symbol: &str, err: &anyhow::Error) {
+        let mut records = self.records.lock().unwrap();
+        let rec = records.entry(symbol.to_string()).or_default();
+        rec.errors += 1;
+        rec.consecutive_errors += 1;
+        rec.last_error = Some(format!("{err:#}"));
+    }
+
+    /// 任一 symbol 最近一次成功拉取的时间。
+    pub fn last_ok_ms(&self) -> Option<i64> {
+        let records = self.records.lock().unwrap();
+        records.values().filter_map(|r| r.last_ok_ms).max()
+    }
+
+    /// 按 symbol 排序的状态快照。
+    pub fn snapshot(&self, now_ms: i64) -> Vec<SymbolStatus> {
+        let records = self.records.lock().unwrap();
+        let mut out: Vec<SymbolStatus> = records
+            .iter()
+            .map(|(symbol, r)| SymbolStatus {
+                symbol: symbol.clone(),
+                last_update_ts_ms: r.last_ok_ms,
+                last_update_age_ms: r.last_ok_ms.map(|t| now_ms - t),
+                errors: r.errors,
+                consecutive_errors: r.consecutive_errors,
+                last_error: r.last_error.clone(),
+            })
+            .collect();
+        out.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+        out
+    }
+}
+
+/// 当前 WebSocket 连接，管理接口据此列出和踢掉连接。
+#[derive(Clone, Default)]
+pub struct ClientRegistry {
+    next_id: Arc<AtomicU64>,
+    clients: Arc<Mutex<HashMap<u64, ClientEntry>>>,
+}
+
+struct ClientEntry {
+    info: WsClientInfo,
+    kick: CancellationToken,
+}
+
+impl ClientRegistry {
+    /// 连接建立时调用，返回的 guard 析构时移除该连接。
+    pub fn connect(
+        &self,
+        key: Option<String>,
+        format: WireFormat,
+        symbols: Vec<String>,
+    ) -> ClientGuard {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let kick = CancellationToken::new();
+        let info = WsClientInfo {
+            id,
+            key,
+            encoding: format.encoding.name(),
+            compression: format.compression(),
+            symbols,
+            connected_at_ms: chrono::Utc::now().timestamp_millis(),
+        };
+        self.clients.lock().unwrap().insert(
+            id,
+            ClientEntry {
+                info,
+                kick: kick.clone(),
+            },
+        );
+        ClientGuard {
+            id,
+            kick,
+            clients: self.clients.clone(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.clients.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 按连接先后排序。
+    pub fn list(&self) -> Vec<WsClientInfo> {
+        let clients = self.clients.lock().unwrap();
+        let mut out: Vec<WsClientInfo> = clients.values().map(|c| c.info.clone()).collect();
+        out.sort_by_key(|c| c.id);
+        out
+    }
+
+    /// 通知连接断开，连接不存在时返回 false。
+    pub fn kick(&self, id: u64) -> bool {
+        match self.clients.lock().unwrap().get(&id) {
+            Some(entry) => {
+                entry.kick.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+pub struct ClientGuard {
+    id: u64,
+    kick: CancellationToken,
+    clients: Arc<Mutex<HashMap<u64, ClientEntry>>>,
+}
+
+impl ClientGuard {
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// 被管理接口踢掉时取消。
+    pub fn kicked(&self) -> &CancellationToken {
+        &self.kick
+    }
+}
+
+impl Drop for ClientGuard {
+    fn drop(&mut self) {
+        self.clients.lock().unwrap().remove(&self.id);
+    }
+}
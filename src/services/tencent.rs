@@ -0,0 +1,94 @@
+use crate::config::ProvidersConfig;
+use crate::models::market::{Market, parse_symbol};
+use crate::models::price::PriceUpdate;
+use crate::services::upstream::UpstreamClient;
+use anyhow::{Context, anyhow};
+use chrono::{NaiveDateTime, TimeZone};
+
+/// 腾讯行情（qt.gtimg.cn）实时报价，用作东方财富的备用源。
+///
+/// 只提供实时价格；K 线和证券列表仍走东方财富。
+#[derive(Clone)]
+pub struct TencentService {
+    http: UpstreamClient,
+    quote_url: String,
+}
+
+impl TencentService {
+    pub fn with_client(http: UpstreamClient) -> Self {
+        Self {
+            http,
+            quote_url: ProvidersConfig::default().tencent_url,
+        }
+    }
+
+    /// 行情接口地址，代码直接拼在后面，如 `https://qt.gtimg.cn/q=` + `sh600000`。
+    pub fn with_quote_url(mut self, url: impl Into<String>) -> Self {
+        self.quote_url = url.into();
+        self
+    }
+
+    pub async fn fetch_realtime_quote(&self, symbol: &str) -> anyhow::Result<PriceUpdate> {
+        let (code, market) = parse_symbol(symbol).context("unsupported symbol")?;
+        let url = format!("{}{}", self.quote_url, to_tencent_code(code, market));
+
+        // 响应是 GBK 编码，只取其中的数字字段，按 lossy UTF-8 解码即可
+        let bytes = self.http.get("tencent_quote", &url).await?;
+        parse_quote(symbol, market, &String::from_utf8_lossy(&bytes))
+    }
+}
+
+fn to_tencent_code(code: &str, market: Market) -> String {
+    match market {
+        Market::SH => format!("sh{code}"),
+        Market::SZ => format!("sz{code}"),
+        Market::BJ => format!("bj{code}"),
+        Market::HK => format!("hk{code}"),
+        Market::O | Market::N | Market::A => format!("us{code}"),
+    }
+}
+
+/// 解析 `v_sh600000="1~浦发银行~600000~10.50~10.45~...";`：
+/// 第 3 段最新价，第 4 段昨收，第 30 段行情时间。
+fn parse_quote(symbol: &str, market: Market, body: &str) -> anyhow::Result<PriceUpdate> {
+    let start = body
+        .find('"')
+        .ok_or_else(|| anyhow!("unexpected quote response: {body}"))?;
+    let fields: Vec<&str> = body[start + 1..]
+        .trim_end_matches([';', '\n', '\r'])
+        .trim_end_matches('"')
+        .split('~')
+        .collect();
+    if fields.len() < 31 {
+        return Err(anyhow!("empty quote data: {body}"));
+    }
+
+    let price: f64 = fields[3]
+        .parse()
+        .with_context(|| format!("parse price failed: {}", fields[3]))?;
+    if price <= 0.0 {
+        return Err(anyhow!("no price for {symbol}"));
+    }
+    let prev_close = fields[4].parse::<f64>().ok().filter(|p| *p > 0.0);
+
+    Ok(PriceUpdate {
+        symbol: symbol.to_string(),
+        price,
+        ts_ms: chrono::Utc::now().timestamp_millis(),
+        prev_close,
+        source_ts_ms: parse_time(fields[30], market),
+        stale: false,
+    })
+}
+
+/// A 股为 `20240305100003`，港股 `2024/03/05 16:08:35`，美股 `2024-03-04 16:00:01`，均为交易所当地时间。
+fn parse_time(s: &str, market: Market) -> Option<i64> {
+    let naive = ["%Y%m%d%H%M%S", "%Y/%m/%d %H:%M:%S", "%Y-%m-%d %H:%M:%S"]
+        .iter()
+        .find_map(|fmt| NaiveDateTime::parse_from_str(s, fmt).ok())?;
+    market
+        .timezone()
+        .from_local_datetime(&naive)
+        .earliest()
+        .map(|dt| dt.timestamp_millis())
+}
@@ -0,0 +1,266 @@
+use crate::config::UpstreamConfig;
+use crate::services::metrics::Metrics;
+use anyhow::Context;
+use bytes::Bytes;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// 上游暂不可用：熔断打开，或限流排队超过 `max_wait_ms`。
+///
+/// 调用方可以从 `anyhow::Error` 中 downcast 出来，据此快速失败而不是等超时。
+#[derive(Debug, Clone)]
+pub enum UpstreamUnavailable {
+    CircuitOpen { host: String, retry_after: Duration },
+    RateLimited { host: String },
+}
+
+impl fmt::Display for UpstreamUnavailable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UpstreamUnavailable::CircuitOpen { host, retry_after } => write!(
+                f,
+                "circuit open for {host}, retry after {}s",
+                retry_after.as_secs().max(1)
+            ),
+            UpstreamUnavailable::RateLimited { host } => write!(f, "rate limited by {host}"),
+        }
+    }
+}
+
+impl std::error::Error for UpstreamUnavailable {}
+
+impl UpstreamUnavailable {
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            UpstreamUnavailable::CircuitOpen { retry_after, .. } => Some(*retry_after),
+            UpstreamUnavailable::RateLimited { .. } => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct HostState {
+    tokens: f64,
+    refilled_at: Instant,
+    consecutive_failures: u32,
+    /// 熔断打开到何时；过了这个时间进入半开，只放一个试探请求
+    open_until: Option<Instant>,
+    /// 半开试探请求的发出时间；调用方中途放弃时，过一个 `open_for` 后允许再试探
+    probe_started: Option<Instant>,
+}
+
+/// 按上游 host 分别限流（令牌桶）和熔断，所有行情客户端共用一个。
+#[derive(Clone)]
+pub struct UpstreamLimiter {
+    rate_per_sec: f64,
+    burst: f64,
+    max_wait: Duration,
+    failure_threshold: u32,
+    open_for: Duration,
+    hosts: Arc<Mutex<HashMap<String, HostState>>>,
+}
+
+impl UpstreamLimiter {
+    pub fn new(cfg: &UpstreamConfig) -> Self {
+        Self {
+            rate_per_sec: cfg.rate_limit_per_sec,
+            burst: cfg.rate_limit_burst.max(1) as f64,
+            max_wait: Duration::from_millis(cfg.rate_limit_max_wait_ms),
+            failure_threshold: cfg.breaker_failure_threshold.max(1),
+            open_for: Duration::from_millis(cfg.breaker_open_ms),
+            hosts: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// 请求前调用：熔断打开时立即返回错误，否则取一个令牌（必要时等待）。
+    pub async fn acquire(&self, host: &str) -> Result<(), UpstreamUnavailable> {
+        let wait = {
+            let mut hosts = self.hosts.lock().unwrap();
+            let now = Instant::now();
+            let st = hosts.entry(host.to_string()).or_insert_with(|| HostState {
+                tokens: self.burst,
+                refilled_at: now,
+                consecutive_failures: 0,
+                open_until: None,
+                probe_started: None,
+            });
+
+            if let Some(until) = st.open_until {
+                let probing = st
+                    .probe_started
+                    .is_some_and(|t| now.duration_since(t) < self.open_for);
+                if now < until || probing {
+                    return Err(UpstreamUnavailable::CircuitOpen {
+                        host: host.to_string(),
+                        retry_after: until.saturating_duration_since(now),
+                    });
+                }
+                // 半开：放这一个请求过去试探
+                st.probe_started = Some(now);
+            }
+
+            if self.rate_per_sec <= 0.0 {
+                Duration::ZERO
+            } else {
+                let elapsed = now.duration_since(st.refilled_at).as_secs_f64();
+                st.tokens = (st.tokens + elapsed * self.rate_per_sec).min(self.burst);
+                st.refilled_at = now;
+                st.tokens -= 1.0;
+                if st.tokens >= 0.0 {
+                    Duration::ZERO
+                } else {
+                    let wait = Duration::from_secs_f64(-st.tokens / self.rate_per_sec);
+                    if wait > self.max_wait {
+                        // 不排队就把令牌还回去
+                        st.tokens += 1.0;
+                        st.probe_started = None;
+                        return Err(UpstreamUnavailable::RateLimited {
+                            host: host.to_string(),
+                        });
+                    }
+                    wait
+                }
+            }
+        };
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+        Ok(())
+    }
+
+    /// 请求结束后调用，连续失败达到阈值即打开熔断。
+    pub fn record(&self, host: &str, ok: bool) {
+        let mut hosts = self.hosts.lock().unwrap();
+        let Some(st) = hosts.get_mut(host) else {
+            return;
+        };
+        st.probe_started = None;
+        if ok {
+            if st.open_until.take().is_some() {
+                tracing::info!(%host, "upstream circuit closed");
+            }
+            st.consecutive_failures = 0;
+            return;
+        }
+        st.consecutive_failures += 1;
+        let reopen = st.open_until.is_some();
+        if reopen || st.consecutive_failures >= self.failure_threshold {
+            if !reopen {
+                tracing::warn!(
+                    %host,
+                    failures = st.consecutive_failures,
+                    open_for = ?self.open_for,
+                    "upstream circuit opened"
+                );
+            }
+            st.open_until = Some(Instant::now() + self.open_for);
+        }
+    }
+
+    /// 熔断当前打开的 host。
+    pub fn open_circuits(&self) -> Vec<String> {
+        let now = Instant::now();
+        let hosts = self.hosts.lock().unwrap();
+        let mut out: Vec<String> = hosts
+            .iter()
+            .filter(|(_, st)| st.open_until.is_some_and(|t| t > now))
+            .map(|(h, _)| h.clone())
+            .collect();
+        out.sort();
+        out
+    }
+}
+
+/// 行情源共用的 HTTP 客户端：统一做限流、熔断和指标统计。
+///
+/// clone 共享同一个连接池，`AppState` 里只建一个，各行情源都用它。
+#[derive(Clone)]
+pub struct UpstreamClient {
+    client: reqwest::Client,
+    limiter: Option<UpstreamLimiter>,
+    metrics: Option<Metrics>,
+}
+
+impl UpstreamClient {
+    pub fn new(cfg: &UpstreamConfig) -> anyhow::Result<Self> {
+        let mut builder = reqwest::Client::builder()
+            .timeout(cfg.timeout())
+            .user_agent(cfg.user_agent.as_str())
+            .pool_max_idle_per_host(cfg.pool_max_idle_per_host)
+            .pool_idle_timeout(
+                (cfg.pool_idle_timeout_ms > 0)
+                    .then(|| Duration::from_millis(cfg.pool_idle_timeout_ms)),
+            )
+            .tcp_keepalive(
+                (cfg.tcp_keepalive_ms > 0).then(|| Duration::from_millis(cfg.tcp_keepalive_ms)),
+            );
+        if !cfg.http2 {
+            builder = builder.http1_only();
+        }
+        if let Some(proxy) = &cfg.proxy {
+            builder = builder.proxy(reqwest::Proxy::all(proxy).context("invalid upstream proxy")?);
+        }
+        Ok(Self {
+            client: builder
+                .build()
+                .context("failed to build upstream HTTP client")?,
+            limiter: None,
+            metrics: None,
+        })
+    }
+
+    pub fn set_metrics(&mut self, metrics: Metrics) {
+        self.metrics = Some(metrics);
+    }
+
+    pub fn set_limiter(&mut self, limiter: UpstreamLimiter) {
+        self.limiter = Some(limiter);
+    }
+
+    /// GET `url`，`endpoint` 用作指标标签，如 "quote"、"klines"。
+    pub async fn get(&self, endpoint: &str, url: &str) -> anyhow::Result<Bytes> {
+        let host = reqwest::Url::parse(url)
+            .with_context(|| format!("invalid upstream url {url}"))?
+            .host_str()
+            .unwrap_or_default()
+            .to_string();
+
+        if let Some(limiter) = &self.limiter
+            && let Err(err) = limiter.acquire(&host).await
+        {
+            if let Some(m) = &self.metrics {
+                m.upstream_rejected(&host, &err);
+            }
+            return Err(err.into());
+        }
+
+        let timer = self
+            .metrics
+            .as_ref()
+            .map(|m| m.upstream_timer(endpoint).start_timer());
+        let result: anyhow::Result<Bytes> = async {
+            let resp = self.client.get(url).send().await?.error_for_status()?;
+            Ok(resp.bytes().await?)
+        }
+        .await;
+        drop(timer);
+
+        if let Some(limiter) = &self.limiter {
+            limiter.record(&host, result.is_ok());
+        }
+        if result.is_err()
+            && let Some(m) = &self.metrics
+        {
+            m.upstream_error(endpoint);
+        }
+        result
+    }
+
+    /// 同 `get`，响应按 UTF-8 解码。
+    pub async fn get_text(&self, endpoint: &str, url: &str) -> anyhow::Result<String> {
+        let bytes = self.get(endpoint, url).await?;
+        String::from_utf8(bytes.to_vec()).context("upstream response is not valid UTF-8")
+    }
+}
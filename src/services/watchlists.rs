@@ -0,0 +1,280 @@
+use crate::models::watchlist::Watchlist;
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+/// 未指定用户时使用的用户 id，新建存储时会为其写入一个默认自选列表。
+pub const DEFAULT_USER: &str = "default";
+pub const DEFAULT_WATCHLIST: &str = "default";
+
+const DEFAULT_SYMBOLS: [&str; 3] = ["000001.SH", "399001.SZ", "399006.SZ"];
+
+#[derive(Debug)]
+pub enum WatchlistError {
+    NotFound,
+    AlreadyExists,
+    /// 要添加的 symbol 已在列表中
+    SymbolExists(String),
+    Invalid(String),
+    Storage(anyhow::Error),
+}
+
+impl std::fmt::Display for WatchlistError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WatchlistError::NotFound => write!(f, "watchlist not found"),
+            WatchlistError::AlreadyExists => write!(f, "watchlist already exists"),
+            WatchlistError::SymbolExists(symbol) => {
+                write!(f, "{symbol} is already in the watchlist")
+            }
+            WatchlistError::Invalid(msg) => write!(f, "{msg}"),
+            WatchlistError::Storage(err) => write!(f, "failed to persist watchlists: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for WatchlistError {}
+
+/// 按用户划分的自选列表，变更后整体写回 JSON 文件。
+#[derive(Clone)]
+pub struct WatchlistStore {
+    path: PathBuf,
+    book: Arc<RwLock<WatchlistBook>>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+struct WatchlistBook {
+    users: BTreeMap<String, Vec<Watchlist>>,
+}
+
+impl WatchlistStore {
+    /// 从 `path` 加载；文件不存在时以默认用户的默认列表初始化。
+    pub fn load(path: impl Into<PathBuf>) -> anyhow::Result<Self> {
+        let path = path.into();
+        let book = match std::fs::read_to_string(&path) {
+            Ok(body) => serde_json::from_str(&body)
+                .with_context(|| format!("parse watchlists file {} failed", path.display()))?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                let mut book = WatchlistBook::default();
+                book.users.insert(
+                    DEFAULT_USER.to_string(),
+                    vec![Watchlist {
+                        name: DEFAULT_WATCHLIST.to_string(),
+                        symbols: DEFAULT_SYMBOLS.iter().map(|s| s.to_string()).collect(),
+                    }],
+                );
+                book
+            }
+            Err(err) => {
+                return Err(err).with_context(|| format!("read {} failed", path.display()));
+            }
+        };
+        Ok(Self {
+            path,
+            book: Arc::new(RwLock::new(book)),
+        })
+    }
+
+    pub async fn list(&self, user: &str) -> Vec<Watchlist> {
+        self.book
+            .read()
+            .await
+            .users
+            .get(user)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub async fn get(&self, user: &str, name: &str) -> Option<Watchlist> {
+        self.list(user).await.into_iter().find(|w| w.name == name)
+    }
+
+    /// 页面使用的列表：优先取用户指定的，其次取该用户第一个，最后回退到默认用户。
+    pub async fn resolve(&self, user: &str, name: Option<&str>) -> Option<Watchlist> {
+        let lists = self.list(user).await;
+        if let Some(name) = name
+            && let Some(w) = lists.iter().find(|w| w.name == name)
+        {
+            return Some(w.clone());
+        }
+        if let Some(first) = lists.into_iter().next() {
+            return Some(first);
+        }
+        self.list(DEFAULT_USER).await.into_iter().next()
+    }
+
+    /// 所有用户所有列表中的 symbol（去重，保持首次出现顺序），供实时轮询使用。
+    pub async fn all_symbols(&self) -> Vec<String> {
+        let book = self.book.read().await;
+        let mut seen = HashSet::new();
+        book.users
+            .values()
+            .flatten()
+            .flat_map(|w| w.symbols.iter())
+            .filter(|s| seen.insert(s.as_str()))
+            .cloned()
+            .collect()
+    }
+
+    pub async fn create(
+        &self,
+        user: &str,
+        name: String,
+        symbols: Vec<String>,
+    ) -> Result<Watchlist, WatchlistError> {
+        validate_name(&name)?;
+        check_unique(&symbols)?;
+        self.update(user, |lists| {
+            if lists.iter().any(|w| w.name == name) {
+                return Err(WatchlistError::AlreadyExists);
+            }
+            let w = Watchlist { name, symbols };
+            lists.push(w.clone());
+            Ok(w)
+        })
+        .await
+    }
+
+    pub async fn delete(&self, user: &str, name: &str) -> Result<(), WatchlistError> {
+        self.update(user, |lists| {
+            let before = lists.len();
+            lists.retain(|w| w.name != name);
+            if lists.len() == before {
+                Err(WatchlistError::NotFound)
+            } else {
+                Ok(())
+            }
+        })
+        .await
+    }
+
+    pub async fn add_symbol(
+        &self,
+        user: &str,
+        name: &str,
+        symbol: String,
+        position: Option<usize>,
+    ) -> Result<Watchlist, WatchlistError> {
+        self.update_list(user, name, |w| {
+            if w.symbols.contains(&symbol) {
+                return Err(WatchlistError::SymbolExists(symbol));
+            }
+            let at = position.unwrap_or(w.symbols.len()).min(w.symbols.len());
+            w.symbols.insert(at, symbol);
+            Ok(())
+        })
+        .await
+    }
+
+    pub async fn remove_symbol(
+        &self,
+        user: &str,
+        name: &str,
+        symbol: &str,
+    ) -> Result<Watchlist, WatchlistError> {
+        self.update_list(user, name, |w| {
+            let before = w.symbols.len();
+            w.symbols.retain(|s| s != symbol);
+            if w.symbols.len() == before {
+                Err(WatchlistError::NotFound)
+            } else {
+                Ok(())
+            }
+        })
+        .await
+    }
+
+    /// 重新排序，`symbols` 必须恰好是现有 symbol 的一个排列。
+    pub async fn reorder(
+        &self,
+        user: &str,
+        name: &str,
+        symbols: Vec<String>,
+    ) -> Result<Watchlist, WatchlistError> {
+        check_unique(&symbols)?;
+        self.update_list(user, name, |w| {
+            let current: HashSet<&String> = w.symbols.iter().collect();
+            let wanted: HashSet<&String> = symbols.iter().collect();
+            if current != wanted {
+                return Err(WatchlistError::Invalid(
+                    "reorder must contain exactly the current symbols".to_string(),
+                ));
+            }
+            w.symbols = symbols;
+            Ok(())
+        })
+        .await
+    }
+
+    pub async fn flush(&self) -> anyhow::Result<()> {
+        let book = self.book.read().await;
+        persist(&self.path, &book).await
+    }
+
+    async fn update_list<F>(
+        &self,
+        user: &str,
+        name: &str,
+        f: F,
+    ) -> Result<Watchlist, WatchlistError>
+    where
+        F: FnOnce(&mut Watchlist) -> Result<(), WatchlistError>,
+    {
+        self.update(user, |lists| {
+            let w = lists
+                .iter_mut()
+                .find(|w| w.name == name)
+                .ok_or(WatchlistError::NotFound)?;
+            f(w)?;
+            Ok(w.clone())
+        })
+        .await
+    }
+
+    async fn update<T, F>(&self, user: &str, f: F) -> Result<T, WatchlistError>
+    where
+        F: FnOnce(&mut Vec<Watchlist>) -> Result<T, WatchlistError>,
+    {
+        // 在副本上修改并落盘，成功后才替换内存，失败时不留下空用户或半截修改
+        let mut book = self.book.write().await;
+        let mut next = book.clone();
+        let out = f(next.users.entry(user.to_string()).or_default())?;
+        persist(&self.path, &next)
+            .await
+            .map_err(WatchlistError::Storage)?;
+        *book = next;
+        Ok(out)
+    }
+}
+
+fn validate_name(name: &str) -> Result<(), WatchlistError> {
+    if name.trim().is_empty() || name.len() > 64 {
+        return Err(WatchlistError::Invalid(
+            "name must be 1-64 characters".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn check_unique(symbols: &[String]) -> Result<(), WatchlistError> {
+    let mut seen = HashSet::new();
+    match symbols.iter().find(|s| !seen.insert(s.as_str())) {
+        Some(dup) => Err(WatchlistError::Invalid(format!("duplicate symbol {dup}"))),
+        None => Ok(()),
+    }
+}
+
+async fn persist(path: &Path, book: &WatchlistBook) -> anyhow::Result<()> {
+    if let Some(dir) = path.parent() {
+        tokio::fs::create_dir_all(dir).await?;
+    }
+    let body = serde_json::to_vec_pretty(book)?;
+    let tmp = path.with_extension("json.tmp");
+    tokio::fs::write(&tmp, body).await?;
+    tokio::fs::rename(&tmp, path)
+        .await
+        .with_context(|| format!("write {} failed", path.display()))
+}
@@ -0,0 +1,303 @@
+use crate::config::WebhookSettings;
+use crate::models::alert::AlertRule;
+use crate::models::event::StreamEvent;
+use crate::models::price::PriceUpdate;
+use crate::state::AppState;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+
+/// 签名头，值形如 `sha256=<hex>`，签名内容为 `"{timestamp}.{body}"`。
+pub const SIGNATURE_HEADER: &str = "x-showmarket-signature";
+/// 签名时使用的 Unix 时间戳（秒），接收方可据此拒绝重放。
+pub const TIMESTAMP_HEADER: &str = "x-showmarket-timestamp";
+
+#[derive(Debug, Clone)]
+pub struct WebhookConfig {
+    pub urls: Vec<String>,
+    /// 为空时不签名
+    pub secret: Option<String>,
+    /// 首次失败后的最大重试次数
+    pub max_retries: u32,
+    /// 首次重试前的等待时间，之后每次翻倍
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    pub timeout: Duration,
+    /// 最终投递失败的记录按行追加到该 JSONL 文件
+    pub dead_letter_path: PathBuf,
+}
+
+impl WebhookConfig {
+    pub fn new(urls: Vec<String>, dead_letter_path: impl Into<PathBuf>) -> Self {
+        Self {
+            urls,
+            secret: None,
+            max_retries: 5,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            timeout: Duration::from_secs(5),
+            dead_letter_path: dead_letter_path.into(),
+        }
+    }
+
+    /// 由配置生成；未配置 URL 时返回 `None`。
+    pub fn from_settings(
+        settings: &WebhookSettings,
+        dead_letter_path: impl Into<PathBuf>,
+    ) -> Option<Self> {
+        if settings.urls.is_empty() {
+            return None;
+        }
+        Some(Self {
+            urls: settings.urls.clone(),
+            secret: settings.secret.clone(),
+            max_retries: settings.max_retries,
+            initial_backoff: Duration::from_millis(settings.initial_backoff_ms),
+            max_backoff: Duration::from_millis(settings.max_backoff_ms),
+            timeout: Duration::from_millis(settings.timeout_ms),
+            dead_letter_path: dead_letter_path.into(),
+        })
+    }
+}
+
+/// POST 给 webhook 的 JSON。
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WebhookPayload {
+    /// 事件类型：`alert`、`stale` 或 `recovered`
+    pub event: String,
+    pub symbol: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alert_id: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rule: Option<AlertRule>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub update: Option<PriceUpdate>,
+    /// `stale` / `recovered`：最近一次看到报价前进的时间
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_fresh_ts_ms: Option<i64>,
+    /// Unix timestamp (ms)
+    pub ts_ms: i64,
+}
+
+impl WebhookPayload {
+    /// 告警和报价新鲜度变化需要通知，价格更新与快照返回 `None`。
+    pub fn from_event(event: &StreamEvent) -> Option<Self> {
+        match event {
+            StreamEvent::Alert(fired) => Some(Self {
+                event: "alert".to_string(),
+                symbol: fired.symbol.clone(),
+                alert_id: Some(fired.alert_id),
+                rule: Some(fired.rule.clone()),
+                update: Some(fired.update.clone()),
+                last_fresh_ts_ms: None,
+                ts_ms: fired.ts_ms,
+            }),
+            StreamEvent::Stale(f) | StreamEvent::Recovered(f) => Some(Self {
+                event: event.kind().to_string(),
+                symbol: f.symbol.clone(),
+                alert_id: None,
+                rule: None,
+                update: None,
+                last_fresh_ts_ms: Some(f.last_fresh_ts_ms),
+                ts_ms: f.ts_ms,
+            }),
+            StreamEvent::Price(_) | StreamEvent::Snapshot(_) => None,
+        }
+    }
+}
+
+/// 计算签名（hex 编码的 HMAC-SHA256）。
+pub fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("hmac accepts any key length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    hex::encode(mac.finalize().into_bytes())
+}
+
+#[derive(Debug, Serialize)]
+struct DeadLetter<'a> {
+    url: &'a str,
+    payload: &'a WebhookPayload,
+    attempts: u32,
+    error: String,
+    /// Unix timestamp (ms)
+    ts_ms: i64,
+}
+
+/// 把事件投递到所有配置的 webhook，失败时指数退避重试，最终失败写入死信文件。
+#[derive(Clone)]
+pub struct WebhookSink {
+    cfg: std::sync::Arc<WebhookConfig>,
+    client: reqwest::Client,
+}
+
+impl WebhookSink {
+    pub fn new(cfg: WebhookConfig) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(cfg.timeout)
+            .user_agent("showmarket-webhook/0.1")
+            .build()
+            .expect("failed to build reqwest client");
+        Self {
+            cfg: std::sync::Arc::new(cfg),
+            client,
+        }
+    }
+
+    /// 并发投递到所有 URL，全部结束（成功或进入死信）后返回。
+    pub async fn deliver(&self, payload: &WebhookPayload) {
+        let body = match serde_json::to_vec(payload) {
+            Ok(body) => body,
+            Err(err) => {
+                tracing::warn!(error = %err, "failed to serialize webhook payload");
+                return;
+            }
+        };
+
+        let mut tasks = tokio::task::JoinSet::new();
+        for url in self.cfg.urls.clone() {
+            let sink = self.clone();
+            let body = body.clone();
+            let payload = payload.clone();
+            tasks.spawn(async move { sink.deliver_one(&url, &payload, body).await });
+        }
+        while tasks.join_next().await.is_some() {}
+    }
+
+    async fn deliver_one(&self, url: &str, payload: &WebhookPayload, body: Vec<u8>) {
+        let mut backoff = self.cfg.initial_backoff;
+        let mut attempts = 0;
+
+        loop {
+            attempts += 1;
+            let err = match self.post(url, &body).await {
+                Ok(()) => return,
+                Err(err) => err,
+            };
+
+            if !err.retryable || attempts > self.cfg.max_retries {
+                tracing::warn!(%url, attempts, error = %err.message, "webhook delivery failed");
+                self.dead_letter(url, payload, attempts, err.message).await;
+                return;
+            }
+
+            tracing::debug!(%url, attempts, error = %err.message, "webhook delivery failed, retrying");
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(self.cfg.max_backoff);
+        }
+    }
+
+    async fn post(&self, url: &str, body: &[u8]) -> Result<(), PostError> {
+        let ts = chrono::Utc::now().timestamp();
+        let mut req = self
+            .client
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(TIMESTAMP_HEADER, ts.to_string());
+        if let Some(secret) = &self.cfg.secret {
+            req = req.header(
+                SIGNATURE_HEADER,
+                format!("sha256={}", sign(secret, ts, body)),
+            );
+        }
+
+        let resp = req
+            .body(body.to_vec())
+            .send()
+            .await
+            .map_err(|e| PostError {
+                retryable: true,
+                message: e.to_string(),
+            })?;
+
+        let status = resp.status();
+        if status.is_success() {
+            return Ok(());
+        }
+        // 4xx 通常是接收方配置问题，重试也没用；429 例外
+        Err(PostError {
+            retryable: status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS,
+            message: format!("receiver responded {status}"),
+        })
+    }
+
+    async fn dead_letter(&self, url: &str, payload: &WebhookPayload, attempts: u32, error: String) {
+        let record = DeadLetter {
+            url,
+            payload,
+            attempts,
+            error,
+            ts_ms: chrono::Utc::now().timestamp_millis(),
+        };
+        let Ok(mut line) = serde_json::to_vec(&record) else {
+            return;
+        };
+        line.push(b'\n');
+
+        let path = &self.cfg.dead_letter_path;
+        if let Some(dir) = path.parent() {
+            let _ = tokio::fs::create_dir_all(dir).await;
+        }
+        let res = async {
+            let mut f = tokio::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .await?;
+            f.write_all(&line).await?;
+            // tokio 的 File 在 drop 前不会等待后台写完成
+            f.flush().await
+        }
+        .await;
+        if let Err(err) = res {
+            tracing::error!(error = %err, path = %path.display(), "failed to write webhook dead letter");
+        }
+    }
+}
+
+struct PostError {
+    retryable: bool,
+    message: String,
+}
+
+/// 后台任务：从通知队列取告警与新鲜度事件交给 webhook 投递。
+///
+/// 队列无界，投递慢时事件只会排队而不会被丢弃；退出时已排队的事件也会投递，
+/// 与其他收尾任务一样受退出期限约束。
+pub fn spawn_webhook_sink(state: AppState, sink: WebhookSink) {
+    let Some(mut rx) = state.take_notifications() else {
+        tracing::warn!("webhook sink already running");
+        return;
+    };
+    let tasks = state.tasks().clone();
+    tasks.spawn(async move {
+        let shutdown = state.shutdown_token().clone();
+        loop {
+            let event = tokio::select! {
+                event = rx.recv() => event,
+                _ = shutdown.cancelled() => break,
+            };
+            let Some(event) = event else { break };
+            dispatch(&state, &sink, &event);
+        }
+        while let Ok(event) = rx.try_recv() {
+            dispatch(&state, &sink, &event);
+        }
+    });
+}
+
+fn dispatch(state: &AppState, sink: &WebhookSink, event: &StreamEvent) {
+    let Some(payload) = WebhookPayload::from_event(event) else {
+        return;
+    };
+    // 单独的任务里重试，不阻塞后续事件；退出时在期限内等待投递完成
+    let sink = sink.clone();
+    state
+        .tasks()
+        .spawn(async move { sink.deliver(&payload).await });
+}
@@ -1,34 +1,258 @@
+use crate::config::Config;
+use crate::models::event::{Published, StreamEvent, SymbolSnapshot};
 use crate::models::price::PriceUpdate;
-use std::sync::Arc;
-use tokio::sync::{RwLock, broadcast};
+use crate::services::alerts::AlertStore;
+use crate::services::ashare::AshareService;
+use crate::services::auth::ApiKeys;
+use crate::services::backfill::BackfillJobs;
+use crate::services::kline_cache::KlineCache;
+use crate::services::metrics::Metrics;
+use crate::services::poller::PollerControl;
+use crate::services::quotes::QuoteFeed;
+use crate::services::replay::ReplayBuffer;
+use crate::services::securities::SecurityMaster;
+use crate::services::status::{ClientRegistry, PollStatus};
+use crate::services::upstream::{UpstreamClient, UpstreamLimiter};
+use crate::services::watchlists::WatchlistStore;
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock};
+use std::time::Instant;
+use tokio::sync::{RwLock, broadcast, mpsc};
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::TaskTracker;
 
 #[derive(Clone)]
 pub struct AppState {
-    latest: Arc<RwLock<Option<PriceUpdate>>>,
-    tx: broadcast::Sender<PriceUpdate>,
+    config: Arc<Config>,
+    /// 每个 symbol 最近一次价格
+    latest: Arc<RwLock<HashMap<String, PriceUpdate>>>,
+    tx: broadcast::Sender<Published>,
+    /// 告警与新鲜度事件另走一条无界队列交给 webhook，不会像广播那样因接收方落后而丢失
+    notify: Arc<OnceLock<mpsc::UnboundedSender<StreamEvent>>>,
+    /// 最近推送的事件，SSE 断线续传从这里补发
+    replay: ReplayBuffer,
+    alerts: AlertStore,
+    watchlists: WatchlistStore,
+    securities: SecurityMaster,
+    poll_status: PollStatus,
+    /// 运行中可调整的轮询 symbol 与间隔
+    poller: PollerControl,
+    kline_cache: KlineCache,
+    /// 历史 K 线下载任务
+    jobs: BackfillJobs,
+    metrics: Metrics,
+    /// 所有行情请求共用的限流与熔断
+    limiter: UpstreamLimiter,
+    /// K 线、证券列表等东方财富接口
+    ashare: AshareService,
+    /// 主备实时报价源
+    quotes: QuoteFeed,
+    ws_clients: ClientRegistry,
+    /// 配置中的 API key，为空时不鉴权
+    api_keys: ApiKeys,
+    started_at: Instant,
+    /// 收到退出信号后取消，后台任务与 WebSocket 连接据此收尾
+    shutdown: CancellationToken,
+    /// 需要在退出前等待完成的任务（WebSocket 连接、webhook 投递等）
+    tasks: TaskTracker,
 }
 
 impl AppState {
-    pub fn new() -> Self {
+    /// 运行时数据（告警、自选列表、证券列表、下载任务等）存放在 `config.server.data_dir` 下。
+    pub fn new(config: Config) -> anyhow::Result<Self> {
+        let data_dir = config.server.data_dir.clone();
+        let kline_cache = KlineCache::new(config.klines.cache_ttl(), config.klines.cache_capacity);
+        let metrics = Metrics::new();
+        let limiter = UpstreamLimiter::new(&config.upstream);
+        // 所有行情请求共用一个连接池
+        let mut http = UpstreamClient::new(&config.upstream)?;
+        http.set_limiter(limiter.clone());
+        http.set_metrics(metrics.clone());
+        let ashare = AshareService::with_client(http.clone())
+            .with_kline_url(&config.klines.history_url)
+            .with_list_url(&config.securities.list_url);
+        let quotes = QuoteFeed::new(&config.providers, http);
         // small buffer; slow clients may miss updates, which is fine for a ticker
         let (tx, _) = broadcast::channel(32);
-        Self {
-            latest: Arc::new(RwLock::new(None)),
+        let replay = ReplayBuffer::new(config.stream.replay_capacity);
+        let api_keys = ApiKeys::new(&config.auth);
+        let poller = PollerControl::new(&config.poller);
+        Ok(Self {
+            config: Arc::new(config),
+            latest: Arc::new(RwLock::new(HashMap::new())),
             tx,
-        }
+            notify: Arc::new(OnceLock::new()),
+            replay,
+            alerts: AlertStore::load(data_dir.join("alerts.json"))?,
+            watchlists: WatchlistStore::load(data_dir.join("watchlists.json"))?,
+            securities: SecurityMaster::load(data_dir.join("securities.json"))?,
+            poll_status: PollStatus::default(),
+            poller,
+            kline_cache,
+            jobs: BackfillJobs::load(&data_dir)?,
+            metrics,
+            limiter,
+            ashare,
+            quotes,
+            ws_clients: ClientRegistry::default(),
+            api_keys,
+            started_at: Instant::now(),
+            shutdown: CancellationToken::new(),
+            tasks: TaskTracker::new(),
+        })
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
     }
 
-    pub fn subscribe(&self) -> broadcast::Receiver<PriceUpdate> {
+    pub fn subscribe(&self) -> broadcast::Receiver<Published> {
         self.tx.subscribe()
     }
 
     pub async fn set_latest(&self, update: PriceUpdate) {
-        *self.latest.write().await = Some(update.clone());
-        // ignore lagging/no receivers
-        let _ = self.tx.send(update);
+        self.latest
+            .write()
+            .await
+            .insert(update.symbol.clone(), update.clone());
+        self.publish(StreamEvent::Price(update));
+    }
+
+    /// 更新缓存报价上的 `stale` 标记，新连接的客户端拿到的快照随之变化。
+    pub async fn set_stale(&self, symbol: &str, stale: bool) {
+        if let Some(update) = self.latest.write().await.get_mut(symbol) {
+            update.stale = stale;
+        }
+    }
+
+    pub fn publish(&self, event: StreamEvent) {
+        if let StreamEvent::Alert(_) | StreamEvent::Stale(_) | StreamEvent::Recovered(_) = &event
+            && let Some(notify) = self.notify.get()
+        {
+            let _ = notify.send(event.clone());
+        }
+        self.replay.push(event, |published| {
+            // ignore lagging/no receivers
+            let _ = self.tx.send(published);
+        });
+    }
+
+    /// 取得通知队列的接收端，之后发布的每条告警和新鲜度变化（`stale` / `recovered`）都会进入队列；只能取一次。
+    pub fn take_notifications(&self) -> Option<mpsc::UnboundedReceiver<StreamEvent>> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.notify.set(tx).ok().map(|_| rx)
+    }
+
+    pub fn replay(&self) -> &ReplayBuffer {
+        &self.replay
+    }
+
+    /// 所有 symbol 的最新价格，带上各自当前的序号，供新连接或缺口过旧时整体补发。
+    pub async fn snapshot(&self) -> Vec<Published> {
+        let id = self.replay.last_id();
+        self.latest_all()
+            .await
+            .into_iter()
+            .map(|update| {
+                let seq = self.replay.seq(&update.symbol);
+                Published::new(id, seq, StreamEvent::Price(update))
+            })
+            .collect()
+    }
+
+    /// 客户端请求从 `after_seq` 重新同步：补发缺失的消息，来不及补发时发一条 `snapshot`。
+    pub async fn resync(&self, symbol: &str, after_seq: u64) -> Vec<Published> {
+        if let Some(missed) = self.replay.symbol_since(symbol, after_seq) {
+            return missed;
+        }
+        let id = self.replay.last_id();
+        let seq = self.replay.seq(symbol);
+        let snapshot = SymbolSnapshot {
+            symbol: symbol.to_string(),
+            latest: self.latest(symbol).await,
+        };
+        vec![Published::new(id, seq, StreamEvent::Snapshot(snapshot))]
+    }
+
+    pub async fn latest(&self, symbol: &str) -> Option<PriceUpdate> {
+        self.latest.read().await.get(symbol).cloned()
+    }
+
+    /// 所有 symbol 的最新价格（按 symbol 排序）。
+    pub async fn latest_all(&self) -> Vec<PriceUpdate> {
+        let mut all: Vec<PriceUpdate> = self.latest.read().await.values().cloned().collect();
+        all.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+        all
+    }
+
+    pub fn alerts(&self) -> &AlertStore {
+        &self.alerts
+    }
+
+    pub fn watchlists(&self) -> &WatchlistStore {
+        &self.watchlists
+    }
+
+    pub fn securities(&self) -> &SecurityMaster {
+        &self.securities
+    }
+
+    pub fn poll_status(&self) -> &PollStatus {
+        &self.poll_status
+    }
+
+    pub fn poller(&self) -> &PollerControl {
+        &self.poller
+    }
+
+    pub fn kline_cache(&self) -> &KlineCache {
+        &self.kline_cache
+    }
+
+    pub fn jobs(&self) -> &BackfillJobs {
+        &self.jobs
+    }
+
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    pub fn limiter(&self) -> &UpstreamLimiter {
+        &self.limiter
+    }
+
+    pub fn ashare(&self) -> &AshareService {
+        &self.ashare
+    }
+
+    pub fn quotes(&self) -> &QuoteFeed {
+        &self.quotes
+    }
+
+    pub fn ws_clients(&self) -> &ClientRegistry {
+        &self.ws_clients
+    }
+
+    pub fn api_keys(&self) -> &ApiKeys {
+        &self.api_keys
+    }
+
+    pub fn started_at(&self) -> Instant {
+        self.started_at
+    }
+
+    pub fn shutdown_token(&self) -> &CancellationToken {
+        &self.shutdown
+    }
+
+    pub fn tasks(&self) -> &TaskTracker {
+        &self.tasks
     }
 
-    pub async fn latest(&self) -> Option<PriceUpdate> {
-        self.latest.read().await.clone()
+    /// 把内存中的存储写回磁盘，退出前调用。
+    pub async fn flush(&self) -> anyhow::Result<()> {
+        self.alerts.flush().await?;
+        self.watchlists.flush().await?;
+        Ok(())
     }
 }
@@ -0,0 +1,285 @@
+use axum::body::{Body, to_bytes};
+use axum::http::{Request, StatusCode, header};
+use futures_util::StreamExt;
+use showmarket::config::{Config, ProviderKind, Scope};
+use showmarket::state::AppState;
+use std::time::Duration;
+use tokio_tungstenite::tungstenite::Message;
+use tower::ServiceExt;
+
+mod common;
+use common::{TestState, key};
+
+const ADMIN_KEY: &str = "admin-key-0123456789";
+const QUOTES_KEY: &str = "quotes-key-0123456789";
+
+fn state() -> TestState {
+    let mut config = Config::default();
+    config.poller.symbols = vec!["000001.SH".to_string()];
+    config.auth.keys = vec![
+        key("ops", ADMIN_KEY, &[Scope::Admin]),
+        key("partner", QUOTES_KEY, &[Scope::Quotes]),
+    ];
+    common::state_with(config)
+}
+
+async fn send(
+    state: &AppState,
+    method: &str,
+    uri: &str,
+    body: Option<serde_json::Value>,
+) -> (StatusCode, serde_json::Value) {
+    let mut req = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::AUTHORIZATION, format!("Bearer {ADMIN_KEY}"));
+    let body = match body {
+        Some(json) => {
+            req = req.header(header::CONTENT_TYPE, "application/json");
+            Body::from(json.to_string())
+        }
+        None => Body::empty(),
+    };
+    let resp = showmarket::app(state.clone())
+        .oneshot(req.body(body).unwrap())
+        .await
+        .unwrap();
+    let status = resp.status();
+    let body = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (
+        status,
+        serde_json::from_slice(&body).unwrap_or(serde_json::Value::Null),
+    )
+}
+
+#[tokio::test]
+async fn admin_routes_require_admin_scope() {
+    let state = state();
+    let resp = showmarket::app(state.clone())
+        .oneshot(
+            Request::get("/api/admin/poller")
+                .header(header::AUTHORIZATION, format!("Bearer {QUOTES_KEY}"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn poller_symbols_and_interval_change_at_runtime() {
+    let state = state();
+
+    let (status, body) = send(&state, "GET", "/api/admin/poller", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["interval_ms"], 800);
+    assert_eq!(body["symbols"], serde_json::json!(["000001.SH"]));
+
+    let (status, body) = send(
+        &state,
+        "POST",
+        "/api/admin/poller/symbols",
+        Some(serde_json::json!({ "symbol": "600519.SH" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(
+        body["symbols"],
+        serde_json::json!(["000001.SH", "600519.SH"])
+    );
+    assert_eq!(
+        state.poller().symbols(),
+        vec!["000001.SH".to_string(), "600519.SH".to_string()]
+    );
+
+    let (status, body) = send(
+        &state,
+        "POST",
+        "/api/admin/poller/symbols",
+        Some(serde_json::json!({ "symbol": "600519.SH" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT, "{body}");
+    let (status, body) = send(
+        &state,
+        "POST",
+        "/api/admin/poller/symbols",
+        Some(serde_json::json!({ "symbol": "bogus" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "invalid_symbol");
+
+    let (status, _) = send(
+        &state,
+        "DELETE",
+        "/api/admin/poller/symbols/000001.SH",
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = send(
+        &state,
+        "DELETE",
+        "/api/admin/poller/symbols/000001.SH",
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(state.poller().symbols(), vec!["600519.SH".to_string()]);
+
+    let mut changes = state.poller().watch_interval();
+    let (status, body) = send(
+        &state,
+        "PUT",
+        "/api/admin/poller/interval",
+        Some(serde_json::json!({ "interval_ms": 2000 })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["interval_ms"], 2000);
+    assert!(changes.has_changed().unwrap());
+    assert_eq!(*changes.borrow_and_update(), Duration::from_millis(2000));
+
+    let (status, _) = send(
+        &state,
+        "PUT",
+        "/api/admin/poller/interval",
+        Some(serde_json::json!({ "interval_ms": 10 })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (_, body) = send(&state, "GET", "/api/status", None).await;
+    assert_eq!(body["poll_interval_ms"], 2000);
+}
+
+#[tokio::test]
+async fn provider_can_be_pinned_and_released() {
+    let state = state();
+
+    let (status, body) = send(
+        &state,
+        "PUT",
+        "/api/admin/provider",
+        Some(serde_json::json!({ "provider": "tencent" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["active"], "tencent");
+    assert_eq!(body["pinned"], "tencent");
+    assert_eq!(state.quotes().active(), ProviderKind::Tencent);
+
+    let (status, _) = send(
+        &state,
+        "PUT",
+        "/api/admin/provider",
+        Some(serde_json::json!({ "provider": "bloomberg" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, body) = send(
+        &state,
+        "PUT",
+        "/api/admin/provider",
+        Some(serde_json::json!({ "provider": "auto" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["active"], "eastmoney");
+    assert!(body["pinned"].is_null());
+}
+
+#[tokio::test]
+async fn kline_cache_can_be_flushed() {
+    let state = state();
+    state
+        .kline_cache()
+        .insert("600000.SH", "1d", Vec::new())
+        .await;
+    let (status, body) = send(&state, "DELETE", "/api/admin/klines/cache", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["flushed"], 1);
+    assert!(state.kline_cache().get("600000.SH", "1d").await.is_none());
+}
+
+#[tokio::test]
+async fn clients_are_listed_and_can_be_kicked() {
+    let state = state();
+    state.set_latest(common::price("600000.SH", 10.0)).await;
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let app = showmarket::app(state.clone());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let url = format!(
+        "ws://{addr}/ws/prices?token={QUOTES_KEY}&encoding=msgpack&symbols=600000.SH,000001.SH"
+    );
+    let (mut ws, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+    // 订阅了 600000.SH，先收到它的快照
+    let first = tokio::time::timeout(Duration::from_secs(5), ws.next())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+    assert!(matches!(first, Message::Binary(_)));
+
+    let (status, body) = send(&state, "GET", "/api/admin/clients", None).await;
+    assert_eq!(status, StatusCode::OK);
+    let clients = body.as_array().unwrap();
+    assert_eq!(clients.len(), 1);
+    assert_eq!(clients[0]["key"], "partner");
+    assert_eq!(clients[0]["encoding"], "msgpack");
+    assert_eq!(
+        clients[0]["symbols"],
+        serde_json::json!(["000001.SH", "600000.SH"])
+    );
+    let id = clients[0]["id"].as_u64().unwrap();
+
+    let (status, _) = send(&state, "DELETE", "/api/admin/clients/999", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = send(&state, "DELETE", &format!("/api/admin/clients/{id}"), None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    let mut close = None;
+    while let Some(Ok(msg)) = ws.next().await {
+        if let Message::Close(frame) = msg {
+            close = frame;
+        }
+    }
+    assert_eq!(
+        close.expect("expected a close frame").reason,
+        "kicked by admin"
+    );
+    assert_eq!(state.ws_clients().len(), 0);
+    let metrics = state.metrics().render(0, &[]);
+    assert!(metrics.contains(r#"showmarket_ws_disconnects_total{reason="kicked"} 1"#));
+}
+
+#[tokio::test]
+async fn ws_only_sends_subscribed_symbols() {
+    let state = state();
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let app = showmarket::app(state.clone());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let url = format!("ws://{addr}/ws/prices?token={QUOTES_KEY}&symbols=600000.SH");
+    let (mut ws, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    for (symbol, price) in [("000001.SH", 3000.0), ("600000.SH", 10.5)] {
+        state.set_latest(common::price(symbol, price)).await;
+    }
+    let msg = tokio::time::timeout(Duration::from_secs(5), ws.next())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+    let Message::Text(txt) = msg else {
+        panic!("expected text frame, got {msg:?}");
+    };
+    let json: serde_json::Value = serde_json::from_str(&txt).unwrap();
+    assert_eq!(json["symbol"], "600000.SH");
+}
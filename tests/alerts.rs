@@ -0,0 +1,198 @@
+use showmarket::models::alert::{Alert, AlertRule, NewAlert};
+use showmarket::models::event::StreamEvent;
+use showmarket::models::price::PriceUpdate;
+use showmarket::services::alerts::{AlertEngine, AlertStore, spawn_alert_engine};
+use std::time::Duration;
+
+mod common;
+
+fn alert(id: u64, rule: AlertRule) -> Alert {
+    Alert {
+        id,
+        symbol: "600000.SH".to_string(),
+        rule,
+        debounce_ms: 0,
+        cooldown_ms: 0,
+        created_ts_ms: 0,
+    }
+}
+
+fn tick(price: f64, ts_ms: i64) -> PriceUpdate {
+    PriceUpdate {
+        ts_ms,
+        prev_close: Some(10.0),
+        ..common::price("600000.SH", price)
+    }
+}
+
+/// 依次喂入报价，返回每个报价触发的告警数。
+fn run(engine: &mut AlertEngine, alerts: &[Alert], prices: &[f64]) -> Vec<usize> {
+    prices
+        .iter()
+        .enumerate()
+        .map(|(i, p)| engine.on_update(alerts, &tick(*p, i as i64 * 1000)).len())
+        .collect()
+}
+
+#[test]
+fn cross_fires_only_when_price_changes_side() {
+    let alerts = [
+        alert(1, AlertRule::CrossAbove { level: 10.5 }),
+        alert(2, AlertRule::CrossBelow { level: 9.5 }),
+    ];
+
+    // 创建时已在 level 上方：不是穿越，不触发
+    let mut engine = AlertEngine::default();
+    assert_eq!(run(&mut engine, &alerts[..1], &[11.0, 11.2]), [0, 0]);
+    assert_eq!(
+        run(&mut engine, &alerts[..1], &[10.0, 10.8, 11.0]),
+        [0, 1, 0]
+    );
+    // 回落后再次上穿
+    assert_eq!(run(&mut engine, &alerts[..1], &[10.4, 10.6]), [0, 1]);
+
+    let mut engine = AlertEngine::default();
+    assert_eq!(
+        run(&mut engine, &alerts[1..], &[9.0, 9.8, 9.4, 9.3]),
+        [0, 0, 1, 0]
+    );
+}
+
+#[test]
+fn debounce_and_cooldown_are_applied() {
+    let mut rule = alert(1, AlertRule::CrossAbove { level: 10.5 });
+    rule.debounce_ms = 1500;
+    rule.cooldown_ms = 10_000;
+    let alerts = [rule];
+    let mut engine = AlertEngine::default();
+
+    // 上穿后需持续 1.5s：t=1s 开始满足，t=2s 还不够，t=3s 触发
+    assert_eq!(
+        run(&mut engine, &alerts, &[10.0, 11.0, 11.0, 11.0]),
+        [0, 0, 0, 1]
+    );
+    // 冷却期内回落再上穿也不触发
+    let fired: Vec<usize> = [(10.0, 4000), (11.0, 5000), (11.0, 7000)]
+        .into_iter()
+        .map(|(p, ts)| engine.on_update(&alerts, &tick(p, ts)).len())
+        .collect();
+    assert_eq!(fired, [0, 0, 0]);
+}
+
+#[test]
+fn level_rules_fire_on_first_matching_tick() {
+    let alerts = [
+        alert(1, AlertRule::ChangePctAbove { pct: 5.0 }),
+        alert(2, AlertRule::ChangePctBelow { pct: -5.0 }),
+    ];
+    let mut engine = AlertEngine::default();
+    assert_eq!(
+        run(&mut engine, &alerts, &[10.6, 10.7, 10.0, 10.6]),
+        [1, 0, 0, 1]
+    );
+    assert_eq!(run(&mut engine, &alerts, &[9.4]), [1]);
+}
+
+#[test]
+fn bollinger_break_needs_full_window() {
+    let alerts = [alert(1, AlertRule::BollingerBreak { period: 3, k: 2.0 })];
+    let mut engine = AlertEngine::default();
+    assert_eq!(
+        run(&mut engine, &alerts, &[20.0, 10.0, 10.1, 9.9, 10.0, 12.0]),
+        [0, 0, 0, 0, 0, 1]
+    );
+}
+
+#[test]
+fn deleted_alerts_lose_their_state() {
+    let alerts = [alert(1, AlertRule::CrossAbove { level: 10.5 })];
+    let mut engine = AlertEngine::default();
+    assert_eq!(run(&mut engine, &alerts, &[10.0]), [0]);
+    // 删除后重建同 id：重新从“未见另一侧”开始
+    assert!(engine.on_update(&[], &tick(10.0, 0)).is_empty());
+    assert_eq!(run(&mut engine, &alerts, &[11.0]), [0]);
+}
+
+#[tokio::test]
+async fn store_persists_alerts() {
+    let dir = common::temp_dir();
+    let path = dir.path().join("alerts.json");
+    let store = AlertStore::load(&path).unwrap();
+    let new = |level| NewAlert {
+        symbol: "600000.SH".to_string(),
+        rule: AlertRule::CrossAbove { level },
+        debounce_ms: 0,
+        cooldown_ms: 0,
+    };
+    let a = store.create(new(10.0), 1).await.unwrap();
+    let b = store.create(new(11.0), 2).await.unwrap();
+    assert_eq!((a.id, b.id), (1, 2));
+    assert!(store.delete(a.id).await.unwrap());
+    assert!(!store.delete(a.id).await.unwrap());
+
+    let store = AlertStore::load(&path).unwrap();
+    assert_eq!(store.list().await, vec![b]);
+    // id 不复用
+    assert_eq!(store.create(new(12.0), 3).await.unwrap().id, 3);
+}
+
+#[tokio::test]
+async fn failed_writes_leave_alerts_untouched() {
+    let dir = common::temp_dir();
+    let path = dir.path().join("alerts.json");
+    let store = AlertStore::load(&path).unwrap();
+    let new = NewAlert {
+        symbol: "600000.SH".to_string(),
+        rule: AlertRule::CrossAbove { level: 10.0 },
+        debounce_ms: 0,
+        cooldown_ms: 0,
+    };
+    let a = store.create(new.clone(), 1).await.unwrap();
+
+    // 落盘失败时内存不变
+    std::fs::remove_file(&path).unwrap();
+    std::fs::create_dir(&path).unwrap();
+    assert!(store.create(new.clone(), 2).await.is_err());
+    assert!(store.delete(a.id).await.is_err());
+    assert_eq!(store.list().await, vec![a]);
+
+    std::fs::remove_dir(&path).unwrap();
+    assert_eq!(store.create(new, 3).await.unwrap().id, 2);
+}
+
+#[tokio::test]
+async fn engine_replays_prices_missed_while_lagging() {
+    let state = common::state();
+    let new = NewAlert {
+        symbol: "600000.SH".to_string(),
+        rule: AlertRule::CrossAbove { level: 10.5 },
+        debounce_ms: 0,
+        cooldown_ms: 0,
+    };
+    let alert = state.alerts().create(new, 0).await.unwrap();
+    spawn_alert_engine(state.clone());
+    // 让引擎先订阅上
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    // 一口气发布超过广播容量的报价，穿越发生在被挤掉的那一段
+    state.publish(StreamEvent::Price(tick(10.0, 0)));
+    state.publish(StreamEvent::Price(tick(11.0, 1000)));
+    for i in 0..64 {
+        state.publish(StreamEvent::Price(common::price(
+            "000001.SH",
+            3300.0 + i as f64,
+        )));
+    }
+    let mut rx = state.subscribe();
+    let fired = tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            if let StreamEvent::Alert(fired) = rx.recv().await.unwrap().event {
+                return fired;
+            }
+        }
+    })
+    .await
+    .unwrap();
+    assert_eq!(fired.alert_id, alert.id);
+    assert_eq!(fired.update.price, 11.0);
+}
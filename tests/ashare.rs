@@ -0,0 +1,145 @@
+use axum::extract::{Query, State};
+use axum::{Json, Router, routing::get};
+use chrono::TimeZone;
+use showmarket::models::market::Market;
+use showmarket::services::ashare::{AshareService, parse_em_time, to_secid};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::net::TcpListener;
+
+type Secids = Arc<Mutex<Vec<String>>>;
+
+/// 模拟东方财富实时行情与 K 线接口，记录请求的 secid。
+async fn upstream() -> (String, Secids) {
+    let secids = Secids::default();
+    let app = Router::new()
+        .route(
+            "/quote",
+            get(
+                |State(secids): State<Secids>, Query(q): Query<HashMap<String, String>>| async move {
+                    let secid = q["secid"].clone();
+                    secids.lock().unwrap().push(secid.clone());
+                    let data = match secid.as_str() {
+                        // 未返回 f59 时按市场默认精度
+                        "1.600000" => serde_json::json!({ "f43": 1052, "f60": 1040, "f86": 1_700_000_000 }),
+                        "116.00700" => serde_json::json!({ "f43": 301_400, "f60": 300_000 }),
+                        "105.AAPL" => serde_json::json!({ "f43": 189_950, "f60": "-" }),
+                        // f59 优先于默认精度
+                        "106.BRK_B" => serde_json::json!({ "f43": 4_123_456, "f59": 4, "f60": 4_100_000 }),
+                        _ => serde_json::Value::Null,
+                    };
+                    Json(serde_json::json!({ "data": data }))
+                },
+            ),
+        )
+        .route(
+            "/kline",
+            get(
+                |State(secids): State<Secids>, Query(q): Query<HashMap<String, String>>| async move {
+                    secids.lock().unwrap().push(q["secid"].clone());
+                    let klines = if q["klt"] == "101" {
+                        vec!["2024-03-11 09:31,10,10.1,10.2,9.9,100", "bad-time,1,1,1,1,1"]
+                    } else {
+                        vec!["2024-03-08,10,10.1,10.2,9.9,100", "2024-03-11,10,10.1,10.2,9.9,100"]
+                    };
+                    Json(serde_json::json!({ "data": { "klines": klines } }))
+                },
+            ),
+        )
+        .with_state(secids.clone());
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    (format!("http://{addr}"), secids)
+}
+
+#[test]
+fn secids_use_market_prefixes() {
+    for (symbol, secid) in [
+        ("600000.SH", "1.600000"),
+        ("000001.SZ", "0.000001"),
+        ("899050.BJ", "0.899050"),
+        ("00700.HK", "116.00700"),
+        ("AAPL.O", "105.AAPL"),
+        ("BABA.N", "106.BABA"),
+        ("BRK.B.N", "106.BRK_B"),
+        ("SPY.A", "107.SPY"),
+    ] {
+        assert_eq!(to_secid(symbol).as_deref(), Some(secid), "{symbol}");
+    }
+    assert_eq!(to_secid("bogus"), None);
+    assert_eq!(to_secid("0700.HK"), None);
+}
+
+#[test]
+fn kline_times_use_exchange_timezone_and_close() {
+    let at = |tz: chrono_tz::Tz, y, m, d, h, min| {
+        tz.with_ymd_and_hms(y, m, d, h, min, 0)
+            .unwrap()
+            .timestamp_millis()
+    };
+    use chrono_tz::{America::New_York, Asia::Hong_Kong, Asia::Shanghai};
+
+    assert_eq!(
+        parse_em_time("2024-03-08", Market::SH),
+        Some(at(Shanghai, 2024, 3, 8, 15, 0))
+    );
+    assert_eq!(
+        parse_em_time("2024-03-08", Market::HK),
+        Some(at(Hong_Kong, 2024, 3, 8, 16, 0))
+    );
+    // 美股收盘按纽约时间，跨夏令时切换（2024-03-10）后 UTC 时刻相应提前一小时
+    let before = parse_em_time("2024-03-08", Market::O).unwrap();
+    let after = parse_em_time("2024-03-11", Market::N).unwrap();
+    assert_eq!(before, at(New_York, 2024, 3, 8, 16, 0));
+    assert_eq!(after - before, (3 * 24 - 1) * 3_600_000);
+    assert_eq!(
+        parse_em_time("2024-03-11 09:31", Market::A),
+        Some(at(New_York, 2024, 3, 11, 9, 31))
+    );
+    assert_eq!(parse_em_time("not a time", Market::SH), None);
+}
+
+#[tokio::test]
+async fn quotes_are_scaled_per_market() {
+    let (url, secids) = upstream().await;
+    let svc = AshareService::new().with_quote_url(format!("{url}/quote"));
+
+    let q = svc.fetch_realtime_quote("600000.SH").await.unwrap();
+    assert_eq!((q.price, q.prev_close), (10.52, Some(10.4)));
+    assert_eq!(q.source_ts_ms, Some(1_700_000_000_000));
+    let q = svc.fetch_realtime_quote("00700.HK").await.unwrap();
+    assert_eq!((q.price, q.prev_close), (301.4, Some(300.0)));
+    let q = svc.fetch_realtime_quote("AAPL.O").await.unwrap();
+    assert_eq!((q.price, q.prev_close), (189.95, None));
+    let q = svc.fetch_realtime_quote("BRK.B.N").await.unwrap();
+    assert_eq!(q.price, 412.3456);
+    assert!(svc.fetch_realtime_quote("000001.SZ").await.is_err());
+
+    assert_eq!(
+        *secids.lock().unwrap(),
+        ["1.600000", "116.00700", "105.AAPL", "106.BRK_B", "0.000001"]
+    );
+}
+
+#[tokio::test]
+async fn us_klines_use_new_york_close() {
+    let (url, secids) = upstream().await;
+    let svc = AshareService::new().with_kline_url(format!("{url}/kline"));
+
+    let bars = svc.fetch_klines("AAPL.O", "1d", 10).await.unwrap();
+    let ny = |d| {
+        chrono_tz::America::New_York
+            .with_ymd_and_hms(2024, 3, d, 16, 0, 0)
+            .unwrap()
+            .timestamp_millis()
+    };
+    assert_eq!(
+        bars.iter().map(|b| b.open_time).collect::<Vec<_>>(),
+        [ny(8), ny(11)]
+    );
+    // 时间无法解析的行被跳过
+    let bars = svc.fetch_klines("00700.HK", "1m", 10).await.unwrap();
+    assert_eq!(bars.len(), 1);
+    assert_eq!(*secids.lock().unwrap(), ["105.AAPL", "116.00700"]);
+}
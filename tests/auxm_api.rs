@@ -1 +1,137 @@
-// 目前主要逻辑通过浏览器端验证，这里暂时不保留 REST/WS 端到端测试。
+use axum::body::{Body, to_bytes};
+use axum::http::{Request, StatusCode};
+use showmarket::models::price::PriceUpdate;
+use showmarket::state::AppState;
+use tower::ServiceExt;
+
+mod common;
+use common::state;
+
+async fn get(state: &AppState, uri: &str) -> (StatusCode, serde_json::Value) {
+    let resp = showmarket::app(state.clone())
+        .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = resp.status();
+    let body = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+#[tokio::test]
+async fn health_is_ok() {
+    let (status, body) = get(&state(), "/health").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["status"], "ok");
+}
+
+#[tokio::test]
+async fn ready_requires_fresh_quote() {
+    let state = state();
+    let (status, body) = get(&state, "/ready").await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["ready"], false);
+
+    let now = chrono::Utc::now().timestamp_millis();
+    state.poll_status().record_ok("600000.SH", now);
+    state
+        .set_latest(PriceUpdate {
+            ts_ms: now,
+            ..common::price("600000.SH", 10.5)
+        })
+        .await;
+    let (status, body) = get(&state, "/ready").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["fresh_quote"], true);
+    assert_eq!(body["provider_reachable"], true);
+}
+
+#[tokio::test]
+async fn status_reports_poll_errors() {
+    let state = state();
+    let now = chrono::Utc::now().timestamp_millis();
+    state.poll_status().record_ok("600000.SH", now);
+    state
+        .poll_status()
+        .record_err("600000.SH", &anyhow::anyhow!("timeout"));
+
+    let (status, body) = get(&state, "/api/status").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["version"], env!("CARGO_PKG_VERSION"));
+    assert_eq!(body["ws_clients"], 0);
+    let sym = &body["symbols"][0];
+    assert_eq!(sym["symbol"], "600000.SH");
+    assert_eq!(sym["errors"], 1);
+    assert_eq!(sym["consecutive_errors"], 1);
+    assert_eq!(sym["last_error"], "timeout");
+}
+
+#[tokio::test]
+async fn metrics_exports_prometheus_text() {
+    let state = state();
+    state
+        .set_latest(PriceUpdate {
+            ts_ms: chrono::Utc::now().timestamp_millis(),
+            ..common::price("600000.SH", 10.5)
+        })
+        .await;
+    // 先请求一次，让 HTTP 耗时直方图有数据
+    get(&state, "/health").await;
+
+    let resp = showmarket::app(state.clone())
+        .oneshot(Request::get("/metrics").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    let text = String::from_utf8(body.to_vec()).unwrap();
+    assert!(text.contains(r#"showmarket_quote_staleness_seconds{symbol="600000.SH"}"#));
+    assert!(text.contains(r#"route="/health""#));
+    assert!(text.contains("showmarket_ws_connections 0"));
+}
+
+#[tokio::test]
+async fn klines_errors_are_structured() {
+    let state = state();
+
+    let (status, body) = get(&state, "/api/klines/600000.SH?interval=2m").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "invalid_interval");
+    assert!(body["request_id"].is_string());
+
+    let (status, body) = get(&state, "/api/klines/not-a-symbol").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "invalid_symbol");
+
+    let (status, body) = get(&state, "/api/klines/999999.SH").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["code"], "unknown_security");
+}
+
+#[tokio::test]
+async fn request_id_is_echoed() {
+    let state = state();
+    let resp = showmarket::app(state.clone())
+        .oneshot(
+            Request::get("/api/alerts/42")
+                .method("DELETE")
+                .header("x-request-id", "abc-123")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    assert_eq!(resp.headers()["x-request-id"], "abc-123");
+    let body = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["request_id"], "abc-123");
+    assert_eq!(body["code"], "not_found");
+}
+
+#[tokio::test]
+async fn status_reports_quote_providers() {
+    let (_, body) = get(&state(), "/api/status").await;
+    assert_eq!(body["provider"]["primary"], "eastmoney");
+    assert_eq!(body["provider"]["backup"], "tencent");
+    assert_eq!(body["provider"]["active"], "eastmoney");
+}
@@ -0,0 +1,313 @@
+use axum::body::{Body, to_bytes};
+use axum::extract::{Query, State};
+use axum::http::{Request, StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use axum::{Json, Router, routing::get};
+use showmarket::config::Config;
+use showmarket::models::job::{BackfillSpec, JobState};
+use showmarket::models::kline::{Adjust, Kline};
+use showmarket::services::backfill;
+use showmarket::state::AppState;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tower::ServiceExt;
+
+mod common;
+
+#[derive(Clone, Default)]
+struct Upstream {
+    /// 每次请求的 `beg` 参数
+    calls: Arc<Mutex<Vec<String>>>,
+    /// 为 true 时 2021 年以后的窗口返回 500
+    fail_late: Arc<AtomicBool>,
+}
+
+/// 模拟东方财富历史 K 线接口：每个请求窗口返回一根以 `beg` 为日期的日线。
+async fn upstream() -> (String, Upstream) {
+    let upstream = Upstream::default();
+    let app = Router::new()
+        .route(
+            "/kline",
+            get(
+                |State(up): State<Upstream>, Query(q): Query<HashMap<String, String>>| async move {
+                    let beg = q["beg"].clone();
+                    up.calls.lock().unwrap().push(beg.clone());
+                    if up.fail_late.load(Ordering::SeqCst) && beg.as_str() >= "20210101" {
+                        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+                    }
+                    let day = format!("{}-{}-{}", &beg[..4], &beg[4..6], &beg[6..]);
+                    Json(serde_json::json!({
+                        "data": { "klines": [format!("{day},10,10.5,10.8,9.9,1000,1000000,1.2,0.5,0.05,0.3")] }
+                    }))
+                    .into_response()
+                },
+            ),
+        )
+        .with_state(upstream.clone());
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    (format!("http://{addr}/kline"), upstream)
+}
+
+fn config(url: &str, data_dir: &Path) -> Config {
+    let mut config = Config::default();
+    config.server.data_dir = data_dir.to_path_buf();
+    config.klines.history_url = url.to_string();
+    config.backfill.retry_delay_ms = 20;
+    config.backfill.max_retries = 1000;
+    config.upstream.breaker_failure_threshold = 1000;
+    config
+}
+
+async fn send(
+    state: &AppState,
+    method: &str,
+    uri: &str,
+    body: Option<serde_json::Value>,
+) -> (StatusCode, serde_json::Value) {
+    let mut req = Request::builder().method(method).uri(uri);
+    let body = match body {
+        Some(json) => {
+            req = req.header(header::CONTENT_TYPE, "application/json");
+            Body::from(json.to_string())
+        }
+        None => Body::empty(),
+    };
+    let resp: Response = showmarket::app(state.clone())
+        .oneshot(req.body(body).unwrap())
+        .await
+        .unwrap();
+    let status = resp.status();
+    let body = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (
+        status,
+        serde_json::from_slice(&body).unwrap_or(serde_json::Value::Null),
+    )
+}
+
+fn read_history(path: &Path) -> Vec<Kline> {
+    std::fs::read_to_string(path)
+        .unwrap()
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn backfill_job_downloads_history_and_reports_progress() {
+    let (url, _) = upstream().await;
+    let dir = common::temp_dir();
+    let state = AppState::new(config(&url, dir.path())).unwrap();
+
+    let (status, job) = send(
+        &state,
+        "POST",
+        "/api/admin/jobs/backfill",
+        Some(serde_json::json!({
+            "symbols": ["600000.SH"],
+            "intervals": ["1d", "1w"],
+            "start": "2020-01-01",
+            "end": "2022-12-31",
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::ACCEPTED, "{job}");
+    // 日线一年一个窗口共 3 个，周线 5 年一个窗口
+    assert_eq!(job["windows_total"], 4);
+    let id = job["id"].as_u64().unwrap();
+
+    let job = tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            let (_, job) = send(&state, "GET", &format!("/api/jobs/{id}"), None).await;
+            if job["state"] == "completed" {
+                break job;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .expect("job did not complete");
+    assert_eq!(job["windows_done"], 4);
+    assert_eq!(job["bars"], 4);
+    assert_eq!(job["tasks"][0]["cursor"], "2022-12-31");
+
+    let daily = read_history(
+        &state
+            .jobs()
+            .history_path("600000.SH", "1d", Adjust::Forward),
+    );
+    assert_eq!(daily.len(), 3);
+    assert_eq!(daily[0].turnover_pct, Some(0.3));
+    let weekly = read_history(
+        &state
+            .jobs()
+            .history_path("600000.SH", "1w", Adjust::Forward),
+    );
+    assert_eq!(weekly.len(), 1);
+
+    let (_, jobs) = send(&state, "GET", "/api/jobs", None).await;
+    assert_eq!(jobs.as_array().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn interrupted_job_resumes_from_last_committed_window() {
+    let (url, upstream) = upstream().await;
+    let dir = common::temp_dir();
+    upstream.fail_late.store(true, Ordering::SeqCst);
+
+    let state = AppState::new(config(&url, dir.path())).unwrap();
+    let spec = BackfillSpec {
+        symbols: vec!["600000.SH".to_string()],
+        intervals: vec!["1d".to_string()],
+        adjust: Adjust::None,
+        start: "2020-01-01".parse().ok(),
+        end: "2022-12-31".parse().ok(),
+    };
+    let tasks = backfill::plan(&spec, state.securities()).await.unwrap();
+    let id = state.jobs().submit(spec, tasks).await.unwrap().id;
+    let run = tokio::spawn({
+        let state = state.clone();
+        async move { backfill::run_job(&state, id).await }
+    });
+    // 第一个窗口写完后第二个窗口一直失败，此时模拟进程退出
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while state.jobs().get(id).await.unwrap().windows_done < 1 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+    state.shutdown_token().cancel();
+    let job = run.await.unwrap().unwrap().unwrap();
+    assert_eq!(job.state, JobState::Running);
+    assert_eq!(job.windows_done, 1);
+
+    // 退出时分片还没并入历史文件，其中有崩溃时写了一半的窗口
+    let path = state.jobs().history_path("600000.SH", "1d", Adjust::None);
+    let part = state.jobs().part_path(id, "600000.SH", "1d", Adjust::None);
+    assert!(!path.exists());
+    let mut body = std::fs::read(&part).unwrap();
+    body.extend_from_slice(b"{\"open_time\":1,\"op");
+    std::fs::write(&part, body).unwrap();
+
+    upstream.fail_late.store(false, Ordering::SeqCst);
+    upstream.calls.lock().unwrap().clear();
+    let state = AppState::new(config(&url, dir.path())).unwrap();
+    assert_eq!(state.jobs().unfinished().await, vec![id]);
+    let job = backfill::run_job(&state, id).await.unwrap().unwrap();
+    assert_eq!(job.state, JobState::Completed);
+    assert_eq!(job.bars, 3);
+    assert_eq!(
+        *upstream.calls.lock().unwrap(),
+        vec!["20210101".to_string(), "20220102".to_string()]
+    );
+    let bars = read_history(&path);
+    assert_eq!(bars.len(), 3);
+    assert!(bars.windows(2).all(|w| w[0].open_time < w[1].open_time));
+    // 结束的任务不再引用分片偏移，删分片前崩溃也不会在续传时截断新文件
+    assert_eq!(job.tasks[0].committed_bytes, 0);
+    assert!(!part.exists());
+}
+
+#[tokio::test]
+async fn overlapping_jobs_on_one_series_are_merged() {
+    let (url, _) = upstream().await;
+    let dir = common::temp_dir();
+    let state = AppState::new(config(&url, dir.path())).unwrap();
+
+    let mut ids = Vec::new();
+    for (start, end) in [("2020-01-01", "2022-12-31"), ("2021-01-01", "2023-12-31")] {
+        let spec = BackfillSpec {
+            symbols: vec!["600000.SH".to_string()],
+            intervals: vec!["1d".to_string()],
+            adjust: Adjust::None,
+            start: start.parse().ok(),
+            end: end.parse().ok(),
+        };
+        let tasks = backfill::plan(&spec, state.securities()).await.unwrap();
+        ids.push(state.jobs().submit(spec, tasks).await.unwrap().id);
+    }
+    // 两个任务同时下载同一序列，窗口交错写入
+    let runs: Vec<_> = ids
+        .iter()
+        .map(|&id| {
+            let state = state.clone();
+            tokio::spawn(async move { backfill::run_job(&state, id).await })
+        })
+        .collect();
+    for run in runs {
+        let job = run.await.unwrap().unwrap().unwrap();
+        assert_eq!(job.state, JobState::Completed);
+        assert_eq!(job.bars, 3);
+    }
+
+    // 2021、2022 两个窗口两个任务都下载了，合并后只保留一份
+    let bars = read_history(&state.jobs().history_path("600000.SH", "1d", Adjust::None));
+    assert_eq!(bars.len(), 4, "{bars:?}");
+    assert!(bars.windows(2).all(|w| w[0].open_time < w[1].open_time));
+    for id in ids {
+        assert!(
+            !state
+                .jobs()
+                .part_path(id, "600000.SH", "1d", Adjust::None)
+                .exists()
+        );
+    }
+}
+
+#[tokio::test]
+async fn backfill_jobs_can_be_cancelled_and_validate_input() {
+    let (url, upstream) = upstream().await;
+    upstream.fail_late.store(true, Ordering::SeqCst);
+    let dir = common::temp_dir();
+    let state = AppState::new(config(&url, dir.path())).unwrap();
+
+    for (body, code) in [
+        (serde_json::json!({ "symbols": [] }), "bad_request"),
+        (
+            serde_json::json!({ "symbols": ["600000.SH"], "intervals": ["2d"] }),
+            "invalid_interval",
+        ),
+        (
+            serde_json::json!({ "symbols": ["bogus"] }),
+            "invalid_symbol",
+        ),
+        (
+            serde_json::json!({ "symbols": ["600000.SH"], "start": "2024-02-01", "end": "2024-01-01" }),
+            "bad_request",
+        ),
+    ] {
+        let (status, json) = send(&state, "POST", "/api/admin/jobs/backfill", Some(body)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{json}");
+        assert_eq!(json["code"], code);
+    }
+    let (status, _) = send(&state, "GET", "/api/jobs/999", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (_, job) = send(
+        &state,
+        "POST",
+        "/api/admin/jobs/backfill",
+        Some(serde_json::json!({
+            "symbols": ["600000.SH"],
+            "start": "2020-01-01",
+            "end": "2022-12-31",
+        })),
+    )
+    .await;
+    let id = job["id"].as_u64().unwrap();
+    let (status, job) = send(&state, "DELETE", &format!("/api/admin/jobs/{id}"), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(job["state"], "cancelled");
+    assert!(state.jobs().unfinished().await.is_empty());
+    // 后台任务收到取消后停止重试
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let calls = upstream.calls.lock().unwrap().len();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(upstream.calls.lock().unwrap().len(), calls);
+}
@@ -0,0 +1,124 @@
+use axum::{Json, Router, routing::get};
+use showmarket::services::export::COLUMNS;
+use std::path::PathBuf;
+use std::process::Output;
+use tempfile::TempDir;
+use tokio::net::TcpListener;
+use tokio::process::Command;
+
+mod common;
+
+/// 模拟东方财富历史 K 线接口，固定返回两根日线。
+async fn upstream() -> String {
+    let app = Router::new().route(
+        "/kline",
+        get(|| async {
+            Json(serde_json::json!({
+                "data": { "klines": [
+                    "2024-01-02,10,10.5,10.8,9.9,12000,1000000,1.2,0.5,0.05,0.3",
+                    "2024-01-03,10.5,10.2,10.6,10.1,8000,900000,1.1,-2.86,-0.3,0.2",
+                ] }
+            }))
+        }),
+    );
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    format!("http://{addr}/kline")
+}
+
+/// 返回的临时目录在测试结束时删除，需一直持有。
+fn config_file(history_url: &str) -> (TempDir, PathBuf) {
+    let tmp = common::temp_dir();
+    let dir = tmp.path();
+    let path = dir.join("showmarket.toml");
+    std::fs::write(
+        &path,
+        format!(
+            "[server]\ndata_dir = {:?}\n\n[klines]\nhistory_url = {history_url:?}\n",
+            dir.join("data")
+        ),
+    )
+    .unwrap();
+    (tmp, path)
+}
+
+async fn cli(config: &PathBuf, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_showmarket-cli"))
+        .arg("--config")
+        .arg(config)
+        .args(args)
+        .env_remove("RUST_LOG")
+        .output()
+        .await
+        .unwrap()
+}
+
+fn stdout(out: &Output) -> String {
+    String::from_utf8(out.stdout.clone()).unwrap()
+}
+
+#[tokio::test]
+async fn search_prints_aligned_table() {
+    let (_dir, config) = config_file("http://127.0.0.1:9/kline");
+    let out = cli(&config, &["search", "茅台"]).await;
+    assert!(out.status.success());
+    let text = stdout(&out);
+    let lines: Vec<&str> = text.lines().collect();
+    assert!(lines[0].starts_with("symbol"));
+    assert!(lines[2].starts_with("600519.SH  贵州茅台  GZMT"), "{text}");
+    // 没有输出到终端时不带颜色
+    assert!(!text.contains('\x1b'));
+}
+
+#[tokio::test]
+async fn klines_print_as_table_csv_and_jsonl() {
+    let (_dir, config) = config_file(&upstream().await);
+
+    let out = cli(
+        &config,
+        &["klines", "000001.sh", "--interval", "1d", "--limit", "500"],
+    )
+    .await;
+    assert!(out.status.success());
+    let text = stdout(&out);
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.len(), 4, "{text}");
+    assert!(lines[2].starts_with("2024-01-02"), "{text}");
+    assert!(
+        lines[2].contains("+0.50%") && lines[2].contains("1.20万"),
+        "{text}"
+    );
+    assert!(lines[3].contains("-2.86%"), "{text}");
+
+    let out = cli(&config, &["klines", "000001.SH", "--format", "csv"]).await;
+    assert!(out.status.success());
+    let text = stdout(&out);
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines[0], COLUMNS.join(","));
+    assert_eq!(lines.len(), 3);
+    assert!(lines[1].starts_with("000001.SH,1d,forward,true,"));
+
+    let out = cli(&config, &["klines", "000001.SH", "--format", "jsonl"]).await;
+    let rows: Vec<serde_json::Value> = stdout(&out)
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[1]["change_pct"], -2.86);
+}
+
+#[tokio::test]
+async fn invalid_arguments_fail_with_message() {
+    let (_dir, config) = config_file("http://127.0.0.1:9/kline");
+    for args in [
+        &["quote", "bogus"][..],
+        &["klines", "600000.SH", "--interval", "2d"],
+        &["watch", "600000.SH,nope"],
+    ] {
+        let out = cli(&config, args).await;
+        assert!(!out.status.success(), "{args:?}");
+        let err = String::from_utf8_lossy(&out.stderr);
+        assert!(err.contains("unsupported"), "{args:?}: {err}");
+    }
+}
@@ -0,0 +1,71 @@
+//! 集成测试共用的夹具：临时数据目录上的 `AppState`、报价与 API key 构造。
+#![allow(dead_code)]
+
+use showmarket::config::{ApiKeyConfig, Config, Scope};
+use showmarket::models::price::PriceUpdate;
+use showmarket::state::AppState;
+use std::ops::Deref;
+use tempfile::TempDir;
+
+/// 数据目录为临时目录的 `AppState`，drop 时删除目录。
+pub struct TestState {
+    state: AppState,
+    dir: TempDir,
+}
+
+impl TestState {
+    pub fn data_dir(&self) -> &std::path::Path {
+        self.dir.path()
+    }
+}
+
+impl Deref for TestState {
+    type Target = AppState;
+
+    fn deref(&self) -> &AppState {
+        &self.state
+    }
+}
+
+pub fn temp_dir() -> TempDir {
+    tempfile::Builder::new()
+        .prefix("showmarket-test-")
+        .tempdir()
+        .unwrap()
+}
+
+pub fn state() -> TestState {
+    state_with(Config::default())
+}
+
+/// 用给定配置构造，`server.data_dir` 会被替换为新的临时目录。
+pub fn state_with(mut config: Config) -> TestState {
+    let dir = temp_dir();
+    config.server.data_dir = dir.path().to_path_buf();
+    TestState {
+        state: AppState::new(config).unwrap(),
+        dir,
+    }
+}
+
+pub fn price(symbol: &str, price: f64) -> PriceUpdate {
+    PriceUpdate {
+        symbol: symbol.to_string(),
+        price,
+        ts_ms: 1_700_000_000_000,
+        prev_close: None,
+        source_ts_ms: None,
+        stale: false,
+    }
+}
+
+pub fn key(name: &str, key: &str, scopes: &[Scope]) -> ApiKeyConfig {
+    ApiKeyConfig {
+        name: name.to_string(),
+        key: key.to_string(),
+        scopes: scopes.to_vec(),
+        rate_limit_per_sec: None,
+        rate_limit_burst: None,
+        daily_quota: None,
+    }
+}
@@ -0,0 +1,216 @@
+use arrow_array::{Array, Float64Array, StringArray, TimestampMillisecondArray};
+use axum::body::{Body, to_bytes};
+use axum::extract::{Query, State};
+use axum::http::{Request, StatusCode, header};
+use axum::{Json, Router, routing::get};
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use showmarket::config::Config;
+use showmarket::state::AppState;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::net::TcpListener;
+use tower::ServiceExt;
+
+mod common;
+use common::TestState;
+
+type Calls = Arc<Mutex<Vec<HashMap<String, String>>>>;
+
+/// 模拟东方财富历史 K 线接口：每个请求窗口返回一根以 `beg` 为日期的日线。
+async fn upstream() -> (String, Calls) {
+    let calls = Calls::default();
+    let app = Router::new()
+        .route(
+            "/kline",
+            get(
+                |State(calls): State<Calls>, Query(q): Query<HashMap<String, String>>| async move {
+                    calls.lock().unwrap().push(q.clone());
+                    let beg = &q["beg"];
+                    let day = format!("{}-{}-{}", &beg[..4], &beg[4..6], &beg[6..]);
+                    Json(serde_json::json!({
+                        "data": {
+                            "klines": [format!("{day},10,10.5,10.8,9.9,1000,1000000,1.2,0.5,0.05,")]
+                        }
+                    }))
+                },
+            ),
+        )
+        .with_state(calls.clone());
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    (format!("http://{addr}/kline"), calls)
+}
+
+async fn state() -> (TestState, Calls) {
+    let (url, calls) = upstream().await;
+    let mut config = Config::default();
+    config.klines.history_url = url;
+    (common::state_with(config), calls)
+}
+
+/// A 股日线的 open_time 取当天 15:00（北京时间），与解析上游数据时一致。
+fn daily_ms(y: i32, m: u32, d: u32) -> i64 {
+    use chrono::TimeZone;
+    chrono_tz::Asia::Shanghai
+        .with_ymd_and_hms(y, m, d, 15, 0, 0)
+        .unwrap()
+        .timestamp_millis()
+}
+
+async fn fetch(state: &AppState, uri: &str) -> (StatusCode, HashMap<String, String>, Vec<u8>) {
+    let resp = showmarket::app(state.clone())
+        .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = resp.status();
+    let headers = resp
+        .headers()
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_str().unwrap_or_default().to_string()))
+        .collect();
+    let body = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (status, headers, body.to_vec())
+}
+
+#[tokio::test]
+async fn csv_export_has_header_and_one_request_per_window() {
+    let (state, calls) = state().await;
+    let (status, headers, body) = fetch(
+        &state,
+        "/api/klines/600000.SH/export?start=2020-01-01&end=2022-12-31&adjust=none",
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        headers[header::CONTENT_TYPE.as_str()],
+        "text/csv; charset=utf-8"
+    );
+    assert_eq!(
+        headers[header::CONTENT_DISPOSITION.as_str()],
+        "attachment; filename=\"600000.SH_1d_none_20200101-20221231.csv\""
+    );
+
+    let text = String::from_utf8(body).unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(
+        lines[0],
+        "symbol,interval,adjust,adjusted,open_time,open,high,low,close,volume,\
+         amount,amplitude_pct,change_pct,change,turnover_pct"
+    );
+    // 三年按 366 天一个窗口切成 3 段，每段一根
+    assert_eq!(lines.len(), 4);
+    assert_eq!(
+        lines[1],
+        format!(
+            "600000.SH,1d,none,false,{},10,10.8,9.9,10.5,1000,1000000,1.2,0.5,0.05,",
+            daily_ms(2020, 1, 1)
+        )
+    );
+
+    let calls = calls.lock().unwrap();
+    let ranges: Vec<(&str, &str)> = calls
+        .iter()
+        .map(|q| (q["beg"].as_str(), q["end"].as_str()))
+        .collect();
+    assert_eq!(
+        ranges,
+        vec![
+            ("20200101", "20201231"),
+            ("20210101", "20220101"),
+            ("20220102", "20221231"),
+        ]
+    );
+    assert!(calls.iter().all(|q| q["fqt"] == "0"));
+}
+
+#[tokio::test]
+async fn jsonl_export_flattens_flags_and_extra_fields() {
+    let (state, calls) = state().await;
+    let (status, headers, body) = fetch(
+        &state,
+        "/api/klines/600000.SH/export?format=jsonl&start=20240102&end=20240105",
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        headers[header::CONTENT_TYPE.as_str()],
+        "application/x-ndjson"
+    );
+    let text = String::from_utf8(body).unwrap();
+    let rows: Vec<serde_json::Value> = text
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0]["adjust"], "forward");
+    assert_eq!(rows[0]["adjusted"], true);
+    assert_eq!(rows[0]["amount"], 1_000_000.0);
+    assert_eq!(rows[0]["change_pct"], 0.5);
+    assert!(rows[0].get("turnover_pct").is_none());
+    assert_eq!(calls.lock().unwrap()[0]["fqt"], "1");
+}
+
+#[tokio::test]
+async fn parquet_export_reads_back() {
+    let (state, _) = state().await;
+    let (status, headers, body) = fetch(
+        &state,
+        "/api/klines/600000.SH/export?format=parquet&adjust=backward&start=2019-06-01&end=2020-12-31",
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        headers[header::CONTENT_TYPE.as_str()],
+        "application/vnd.apache.parquet"
+    );
+
+    let reader = ParquetRecordBatchReaderBuilder::try_new(bytes::Bytes::from(body))
+        .unwrap()
+        .build()
+        .unwrap();
+    let batches: Vec<_> = reader.map(Result::unwrap).collect();
+    let rows: usize = batches.iter().map(|b| b.num_rows()).sum();
+    assert_eq!(rows, 2);
+    let batch = &batches[0];
+    let adjust = batch
+        .column_by_name("adjust")
+        .unwrap()
+        .as_any()
+        .downcast_ref::<StringArray>()
+        .unwrap();
+    assert_eq!(adjust.value(0), "backward");
+    let open_time = batch
+        .column_by_name("open_time")
+        .unwrap()
+        .as_any()
+        .downcast_ref::<TimestampMillisecondArray>()
+        .unwrap();
+    assert_eq!(open_time.value(0), daily_ms(2019, 6, 1));
+    let turnover = batch
+        .column_by_name("turnover_pct")
+        .unwrap()
+        .as_any()
+        .downcast_ref::<Float64Array>()
+        .unwrap();
+    assert!(turnover.is_null(0));
+}
+
+#[tokio::test]
+async fn invalid_export_requests_are_rejected() {
+    let (state, calls) = state().await;
+    for uri in [
+        "/api/klines/600000.SH/export?format=xlsx",
+        "/api/klines/600000.SH/export?adjust=sideways",
+        "/api/klines/600000.SH/export?start=2024-02-01&end=2024-01-01",
+        "/api/klines/600000.SH/export?start=yesterday",
+    ] {
+        let (status, _, body) = fetch(&state, uri).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{uri}");
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["code"], "bad_request", "{uri}");
+    }
+    let (status, _, _) = fetch(&state, "/api/klines/600000.SH/export?interval=2d").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(calls.lock().unwrap().is_empty());
+}
@@ -0,0 +1,387 @@
+use axum::body::{Body, to_bytes};
+use axum::http::{Method, Request, StatusCode, header};
+use serde_json::{Value, json};
+use showmarket::config::{Config, Scope};
+use showmarket::models::event::{Published, StreamEvent};
+use showmarket::models::kline::Kline;
+use showmarket::models::price::PriceUpdate;
+use showmarket::state::AppState;
+use std::collections::BTreeSet;
+use tower::ServiceExt;
+use utoipa::OpenApi;
+
+mod common;
+use common::{TestState, key};
+
+const ADMIN_KEY: &str = "admin-key-0123456789";
+
+fn state() -> TestState {
+    let mut config = Config::default();
+    config.auth.keys = vec![key("ops", ADMIN_KEY, &[Scope::Admin])];
+    common::state_with(config)
+}
+
+fn spec() -> Value {
+    serde_json::to_value(showmarket::openapi::ApiDoc::openapi()).unwrap()
+}
+
+async fn send(
+    state: &AppState,
+    method: Method,
+    uri: &str,
+    body: Option<Value>,
+) -> (StatusCode, Value) {
+    let mut req = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::AUTHORIZATION, format!("Bearer {ADMIN_KEY}"));
+    let body = match body {
+        Some(json) => {
+            req = req.header(header::CONTENT_TYPE, "application/json");
+            Body::from(json.to_string())
+        }
+        None => Body::empty(),
+    };
+    let resp = showmarket::app(state.clone())
+        .oneshot(req.body(body).unwrap())
+        .await
+        .unwrap();
+    let status = resp.status();
+    let body = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+/// `src/lib.rs` 中注册的 (方法, 路由模板)。
+fn registered_routes() -> BTreeSet<(String, String)> {
+    let source = include_str!("../src/lib.rs");
+    let mut out = BTreeSet::new();
+    for chunk in source.split(".route(").skip(1) {
+        let chunk = chunk.split(".route_layer(").next().unwrap();
+        let path = chunk.split('"').nth(1).unwrap().to_string();
+        for method in ["get", "post", "put", "delete", "patch"] {
+            if chunk.contains(&format!("{method}(handlers::")) {
+                out.insert((method.to_string(), path.clone()));
+            }
+        }
+    }
+    out
+}
+
+fn documented_routes(spec: &Value) -> BTreeSet<(String, String)> {
+    let mut out = BTreeSet::new();
+    for (path, item) in spec["paths"].as_object().unwrap() {
+        for method in item.as_object().unwrap().keys() {
+            out.insert((method.clone(), path.clone()));
+        }
+    }
+    out
+}
+
+fn resolve<'a>(spec: &'a Value, schema: &'a Value) -> &'a Value {
+    match schema["$ref"].as_str() {
+        Some(r) => {
+            let name = r.trim_start_matches("#/components/schemas/");
+            resolve(spec, &spec["components"]["schemas"][name])
+        }
+        None => schema,
+    }
+}
+
+fn type_matches(ty: &str, value: &Value) -> bool {
+    match ty {
+        "null" => value.is_null(),
+        "boolean" => value.is_boolean(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "number" => value.is_number(),
+        "string" => value.is_string(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        _ => false,
+    }
+}
+
+/// 按 schema 校验 `value`，返回 schema 声明过的字段名；`strict` 时多出的字段也算不符。
+fn check(
+    spec: &Value,
+    schema: &Value,
+    value: &Value,
+    strict: bool,
+) -> Result<BTreeSet<String>, String> {
+    let schema = resolve(spec, schema);
+    if let Some(branches) = schema["oneOf"].as_array().or(schema["anyOf"].as_array()) {
+        let mut errors = Vec::new();
+        for branch in branches {
+            match check(spec, branch, value, strict) {
+                Ok(declared) => return Ok(declared),
+                Err(err) => errors.push(err),
+            }
+        }
+        return Err(format!("{value} matches no branch: {errors:?}"));
+    }
+    if let Some(parts) = schema["allOf"].as_array() {
+        let mut declared = BTreeSet::new();
+        for part in parts {
+            declared.extend(check(spec, part, value, false)?);
+        }
+        if strict
+            && let Some(extra) = value
+                .as_object()
+                .and_then(|obj| obj.keys().find(|k| !declared.contains(*k)))
+        {
+            return Err(format!("undocumented field {extra} in {value}"));
+        }
+        return Ok(declared);
+    }
+    match &schema["type"] {
+        Value::String(ty) if !type_matches(ty, value) => {
+            return Err(format!("{value} is not {ty}"));
+        }
+        Value::Array(types)
+            if !types
+                .iter()
+                .any(|t| type_matches(t.as_str().unwrap(), value)) =>
+        {
+            return Err(format!("{value} is not any of {types:?}"));
+        }
+        _ => {}
+    }
+    if let Some(allowed) = schema["enum"].as_array()
+        && !allowed.contains(value)
+    {
+        return Err(format!("{value} not in {allowed:?}"));
+    }
+    if let (Some(items), Some(values)) = (schema.get("items"), value.as_array()) {
+        for v in values {
+            check(spec, items, v, strict)?;
+        }
+    }
+    let mut declared = BTreeSet::new();
+    if let Some(obj) = value.as_object() {
+        let props = schema["properties"]
+            .as_object()
+            .cloned()
+            .unwrap_or_default();
+        for required in schema["required"].as_array().into_iter().flatten() {
+            let name = required.as_str().unwrap();
+            if !obj.contains_key(name) {
+                return Err(format!("missing required field {name} in {value}"));
+            }
+        }
+        for (name, prop) in &props {
+            if let Some(v) = obj.get(name) {
+                check(spec, prop, v, strict)?;
+            }
+            declared.insert(name.clone());
+        }
+        if strict
+            && !props.is_empty()
+            && let Some(extra) = obj.keys().find(|k| !declared.contains(*k))
+        {
+            return Err(format!("undocumented field {extra} in {value}"));
+        }
+    }
+    Ok(declared)
+}
+
+fn assert_matches(spec: &Value, schema: Value, value: &Value) {
+    if let Err(err) = check(spec, &schema, value, true) {
+        panic!("response does not match {schema}: {err}");
+    }
+}
+
+fn response_schema(spec: &Value, method: &str, path: &str, status: StatusCode) -> Value {
+    spec["paths"][path][method]["responses"][status.as_str()]["content"]["application/json"]
+        ["schema"]
+        .clone()
+}
+
+#[tokio::test]
+async fn spec_and_docs_are_public() {
+    let state = state();
+    let resp = showmarket::app(state.clone())
+        .oneshot(
+            Request::get("/api/openapi.json")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    let served: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(served, spec());
+    assert!(served["openapi"].as_str().unwrap().starts_with("3."));
+
+    let resp = showmarket::app(state.clone())
+        .oneshot(Request::get("/api/docs/").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    assert!(String::from_utf8_lossy(&body).contains("swagger"));
+}
+
+#[test]
+fn every_route_is_documented() {
+    let registered: BTreeSet<_> = registered_routes()
+        .into_iter()
+        // 页面本身不属于 API
+        .filter(|(_, path)| path != "/")
+        .collect();
+    let documented = documented_routes(&spec());
+    let undocumented: Vec<_> = registered.difference(&documented).collect();
+    let stale: Vec<_> = documented.difference(&registered).collect();
+    assert!(
+        undocumented.is_empty(),
+        "routes missing from the spec: {undocumented:?}"
+    );
+    assert!(
+        stale.is_empty(),
+        "spec documents routes that do not exist: {stale:?}"
+    );
+}
+
+#[tokio::test]
+async fn documented_operations_are_routed() {
+    let state = state();
+    for (method, path) in documented_routes(&spec()) {
+        // 取不会打到上游的参数值
+        let uri = path
+            .replace("{symbol}", "bogus")
+            .replace("{id}", "999")
+            .replace("{name}", "missing");
+        let method: Method = method.to_uppercase().parse().unwrap();
+        let resp = showmarket::app(state.clone())
+            .oneshot(
+                Request::builder()
+                    .method(method.clone())
+                    .uri(&uri)
+                    .header(header::AUTHORIZATION, format!("Bearer {ADMIN_KEY}"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let status = resp.status();
+        assert_ne!(status, StatusCode::METHOD_NOT_ALLOWED, "{method} {path}");
+        if status == StatusCode::NOT_FOUND {
+            // 路由不存在时 axum 返回空响应体；handler 的 404 总带错误码
+            let body = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+            assert!(!body.is_empty(), "{method} {path} is not routed");
+        }
+    }
+}
+
+#[tokio::test]
+async fn responses_match_documented_schemas() {
+    let spec = spec();
+    let state = state();
+
+    let (status, body) = send(&state, Method::GET, "/api/klines/bogus", None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let schema = response_schema(&spec, "get", "/api/klines/{symbol}", status);
+    assert_matches(&spec, schema, &body);
+
+    let (status, body) = send(&state, Method::GET, "/api/markets", None).await;
+    assert_matches(
+        &spec,
+        response_schema(&spec, "get", "/api/markets", status),
+        &body,
+    );
+
+    state
+        .set_latest(PriceUpdate {
+            symbol: "600000.SH".to_string(),
+            price: 10.0,
+            ts_ms: 1_700_000_000_000,
+            prev_close: Some(9.9),
+            source_ts_ms: Some(1_700_000_000_000),
+            stale: false,
+        })
+        .await;
+    let (status, body) = send(&state, Method::GET, "/api/status", None).await;
+    assert_matches(
+        &spec,
+        response_schema(&spec, "get", "/api/status", status),
+        &body,
+    );
+
+    let (status, body) = send(
+        &state,
+        Method::POST,
+        "/api/alerts",
+        Some(json!({"symbol": "600000.SH", "rule": {"kind": "bollinger_break", "period": 20, "k": 2.0}})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_matches(
+        &spec,
+        response_schema(&spec, "post", "/api/alerts", status),
+        &body,
+    );
+
+    let (status, body) = send(
+        &state,
+        Method::POST,
+        "/api/watchlists",
+        Some(json!({"name": "core", "symbols": ["600000.SH"]})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_matches(
+        &spec,
+        response_schema(&spec, "post", "/api/watchlists", status),
+        &body,
+    );
+
+    let (status, body) = send(&state, Method::GET, "/api/admin/poller", None).await;
+    assert_matches(
+        &spec,
+        response_schema(&spec, "get", "/api/admin/poller", status),
+        &body,
+    );
+
+    // 上游数据和推送消息不经过这里的 HTTP 调用，直接按类型校验
+    let kline = Kline {
+        open_time: 1_700_000_000_000,
+        open: 10.0,
+        high: 10.5,
+        low: 9.8,
+        close: 10.2,
+        volume: 12_345.0,
+        amount: Some(125_000.0),
+        amplitude_pct: Some(7.0),
+        change_pct: Some(1.5),
+        change: Some(0.15),
+        turnover_pct: None,
+    };
+    let schema = response_schema(&spec, "get", "/api/klines/{symbol}", StatusCode::OK);
+    assert_matches(&spec, schema, &json!([kline]));
+
+    let update = state.latest("600000.SH").await.unwrap();
+    let published = Published::new(1, 1, StreamEvent::Price(update));
+    let message: Value =
+        serde_json::from_slice(&published.encoded(Default::default()).unwrap()).unwrap();
+    assert_matches(
+        &spec,
+        json!({"$ref": "#/components/schemas/Published"}),
+        &message,
+    );
+    assert_matches(
+        &spec,
+        json!({"$ref": "#/components/schemas/ClientMessage"}),
+        &json!({"type": "resync", "symbol": "600000.SH", "after_seq": 3}),
+    );
+}
+
+#[test]
+fn checker_rejects_undocumented_fields() {
+    let spec = spec();
+    let schema = json!({"$ref": "#/components/schemas/ErrorBody"});
+    let ok = json!({"code": "not_found", "message": "x", "request_id": null});
+    assert!(check(&spec, &schema, &ok, true).is_ok());
+    let extra = json!({"code": "not_found", "message": "x", "hint": "y"});
+    assert!(check(&spec, &schema, &extra, true).is_err());
+    let missing = json!({"code": "not_found"});
+    assert!(check(&spec, &schema, &missing, true).is_err());
+}
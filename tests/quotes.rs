@@ -0,0 +1,168 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::{Json, Router, routing::get};
+use showmarket::config::{ProviderKind, ProvidersConfig, UpstreamConfig};
+use showmarket::services::quotes::QuoteFeed;
+use showmarket::services::upstream::UpstreamClient;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::net::TcpListener;
+
+const HEALTHY: u8 = 0;
+const SERVER_ERROR: u8 = 1;
+const NO_DATA: u8 = 2;
+const HANGING: u8 = 3;
+
+/// 可切换状态的模拟报价源，记录收到的请求数。
+#[derive(Clone, Default)]
+struct Stub {
+    mode: Arc<AtomicU8>,
+    hits: Arc<AtomicUsize>,
+}
+
+impl Stub {
+    fn set(&self, mode: u8) {
+        self.mode.store(mode, Ordering::SeqCst);
+    }
+
+    fn hits(&self) -> usize {
+        self.hits.load(Ordering::SeqCst)
+    }
+
+    /// 按当前状态应答；健康时返回 `ok`。
+    async fn respond(&self, ok: Response) -> Response {
+        self.hits.fetch_add(1, Ordering::SeqCst);
+        match self.mode.load(Ordering::SeqCst) {
+            SERVER_ERROR => StatusCode::SERVICE_UNAVAILABLE.into_response(),
+            NO_DATA => Json(serde_json::json!({ "data": null })).into_response(),
+            HANGING => {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                ok
+            }
+            _ => ok,
+        }
+    }
+}
+
+async fn listen(app: Router) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    format!("http://{addr}")
+}
+
+/// 主源（东方财富格式）报 10.50，备用源（腾讯格式）报 10.60。
+async fn feed(failback_interval_ms: u64) -> (QuoteFeed, Stub, Stub) {
+    let primary = Stub::default();
+    let backup = Stub::default();
+    let eastmoney = listen(
+        Router::new()
+            .route(
+                "/quote",
+                get(|State(stub): State<Stub>| async move {
+                    let ok = Json(serde_json::json!({
+                        "data": { "f43": 1050, "f59": 2, "f60": 1045, "f86": 1_709_604_003 }
+                    }));
+                    stub.respond(ok.into_response()).await
+                }),
+            )
+            .with_state(primary.clone()),
+    )
+    .await;
+    let tencent = listen(
+        Router::new()
+            .route(
+                "/q/{code}",
+                get(
+                    |State(stub): State<Stub>, Path(code): Path<String>| async move {
+                        let mut fields = vec!["0"; 31];
+                        fields[3] = "10.60";
+                        fields[4] = "10.45";
+                        fields[30] = "20240305100003";
+                        let ok = format!("v_{code}=\"{}\";\n", fields.join("~"));
+                        stub.respond(ok.into_response()).await
+                    },
+                ),
+            )
+            .with_state(backup.clone()),
+    )
+    .await;
+
+    let providers = ProvidersConfig {
+        primary: ProviderKind::Eastmoney,
+        backup: Some(ProviderKind::Tencent),
+        failback_interval_ms,
+        eastmoney_url: format!("{eastmoney}/quote"),
+        tencent_url: format!("{tencent}/q/"),
+        ..ProvidersConfig::default()
+    };
+    let http = UpstreamClient::new(&UpstreamConfig {
+        timeout_ms: 300,
+        ..UpstreamConfig::default()
+    })
+    .unwrap();
+    (QuoteFeed::new(&providers, http), primary, backup)
+}
+
+#[tokio::test]
+async fn missing_symbol_data_does_not_fail_over() {
+    let (feed, primary, backup) = feed(60_000).await;
+    primary.set(NO_DATA);
+
+    let err = feed.fetch_quote("600000.SH").await.unwrap_err();
+    assert!(format!("{err:#}").contains("empty quote data"), "{err:#}");
+    assert_eq!(feed.active(), ProviderKind::Eastmoney);
+    assert_eq!((primary.hits(), backup.hits()), (1, 0));
+}
+
+#[tokio::test]
+async fn fails_over_on_server_errors_and_timeouts_then_fails_back() {
+    for failure in [SERVER_ERROR, HANGING] {
+        let (feed, primary, backup) = feed(500).await;
+        assert_eq!(feed.fetch_quote("600000.SH").await.unwrap().price, 10.5);
+
+        primary.set(failure);
+        assert_eq!(feed.fetch_quote("600000.SH").await.unwrap().price, 10.6);
+        assert_eq!(feed.active(), ProviderKind::Tencent);
+        assert_eq!((primary.hits(), backup.hits()), (2, 1));
+
+        // 试探间隔内直接走备用源，主源恢复了也不马上切回
+        primary.set(HEALTHY);
+        assert_eq!(feed.fetch_quote("600000.SH").await.unwrap().price, 10.6);
+        assert_eq!((primary.hits(), backup.hits()), (2, 2));
+
+        tokio::time::sleep(Duration::from_millis(600)).await;
+        assert_eq!(feed.fetch_quote("600000.SH").await.unwrap().price, 10.5);
+        assert_eq!(feed.active(), ProviderKind::Eastmoney);
+        assert_eq!((primary.hits(), backup.hits()), (3, 2));
+    }
+}
+
+#[tokio::test]
+async fn failed_probe_stays_on_backup() {
+    let (feed, primary, backup) = feed(200).await;
+    primary.set(SERVER_ERROR);
+    feed.fetch_quote("600000.SH").await.unwrap();
+
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert_eq!(feed.fetch_quote("600000.SH").await.unwrap().price, 10.6);
+    assert_eq!(feed.active(), ProviderKind::Tencent);
+    assert_eq!((primary.hits(), backup.hits()), (2, 2));
+}
+
+#[tokio::test]
+async fn divergence_beyond_tolerance_is_flagged() {
+    let (feed, _primary, _backup) = feed(60_000).await;
+    let symbols = ["600000.SH".to_string()];
+
+    // 10.50 对 10.60，相差约 0.95%
+    assert!(feed.reconcile(&symbols, 2.0).await.is_empty());
+    let divergences = feed.reconcile(&symbols, 0.5).await;
+    assert_eq!(divergences.len(), 1);
+    let d = &divergences[0];
+    assert_eq!((d.primary_price, d.backup_price), (10.5, 10.6));
+    assert!((d.diff_pct - 0.952).abs() < 0.01, "{}", d.diff_pct);
+    assert_eq!(feed.status().divergences.len(), 1);
+}
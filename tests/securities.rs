@@ -0,0 +1,123 @@
+use axum::extract::{Query, State};
+use axum::{Json, Router, routing::get};
+use showmarket::services::ashare::AshareService;
+use showmarket::services::pinyin;
+use showmarket::services::securities::SecurityMaster;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::net::TcpListener;
+
+mod common;
+
+#[test]
+fn pinyin_is_derived_from_names() {
+    // 打包列表中的拼音是人工整理的，生成结果应与之一致
+    for line in include_str!("../assets/securities.csv").lines().skip(1) {
+        let cols: Vec<&str> = line.split(',').collect();
+        assert_eq!(pinyin::initials(cols[1]), cols[2], "{}", cols[1]);
+    }
+    for (name, expected) in [
+        ("邯郸钢铁", "HDGT"),
+        ("厦门银行", "XMYH"),
+        ("重庆啤酒", "CQPJ"),
+        ("中国重工", "ZGZG"),
+        ("重庆银行", "CQYH"),
+        ("*ST康美", "STKM"),
+        ("Ｂ股指数", "BGZS"),
+        ("天齐锂业", "TQLY"),
+    ] {
+        assert_eq!(pinyin::initials(name), expected, "{name}");
+    }
+}
+
+/// 模拟东方财富证券列表接口：只有沪深 A 股板块有数据，共 `total` 行，
+/// 且不管请求的 pz 多大，每页最多返回 100 行。
+async fn upstream(total: Arc<AtomicUsize>) -> String {
+    let app = Router::new()
+        .route(
+            "/clist",
+            get(
+                |State(total): State<Arc<AtomicUsize>>,
+                 Query(q): Query<HashMap<String, String>>| async move {
+                    // 查询串中的 "+" 解码为空格
+                    if q["fs"] != "m:0 t:6,m:0 t:80,m:1 t:2,m:1 t:23" {
+                        return Json(serde_json::json!({ "data": null }));
+                    }
+                    let total = total.load(Ordering::SeqCst);
+                    let page: usize = q["pn"].parse().unwrap();
+                    let size = q["pz"].parse::<usize>().unwrap().min(100);
+                    let diff: Vec<serde_json::Value> = ((page - 1) * size
+                        ..(page * size).min(total))
+                        .map(|i| {
+                            let name = match i {
+                                0 => "浦发银行".to_string(),
+                                1 => "邯郸钢铁".to_string(),
+                                i => format!("测试{i}"),
+                            };
+                            serde_json::json!({
+                                "f12": format!("600{i:03}"),
+                                "f13": 1,
+                                "f14": name,
+                                "f26": 19991110,
+                            })
+                        })
+                        .collect();
+                    Json(serde_json::json!({ "data": { "total": total, "diff": diff } }))
+                },
+            ),
+        )
+        .with_state(total);
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    format!("http://{addr}/clist")
+}
+
+#[tokio::test]
+async fn refresh_pages_through_capped_responses_and_fills_pinyin() {
+    let total = Arc::new(AtomicUsize::new(250));
+    let svc = AshareService::new().with_list_url(upstream(total).await);
+    let dir = common::temp_dir();
+    let path = dir.path().join("securities.json");
+    let master = SecurityMaster::load(&path).unwrap();
+
+    assert_eq!(master.refresh(&svc, 0.9).await.unwrap(), 250);
+    assert_eq!(master.len().await, 250);
+    let hits = master.search("hdgt", 5).await;
+    assert_eq!(hits[0].symbol, "600001.SH");
+    assert_eq!(master.get("600000.SH").await.unwrap().pinyin, "PFYH");
+    assert_eq!(master.get("600249.SH").await.unwrap().pinyin, "CS249");
+    // 不在新列表中的打包证券被移除
+    assert!(!master.contains("000001.SZ").await);
+
+    // 落盘后重新加载结果相同
+    let reloaded = SecurityMaster::load(&path).unwrap();
+    assert_eq!(reloaded.len().await, 250);
+    assert_eq!(reloaded.get("600001.SH").await.unwrap().pinyin, "HDGT");
+}
+
+#[tokio::test]
+async fn truncated_list_does_not_replace_saved_master() {
+    let total = Arc::new(AtomicUsize::new(250));
+    let svc = AshareService::new().with_list_url(upstream(total.clone()).await);
+    let dir = common::temp_dir();
+    let path = dir.path().join("securities.json");
+    let master = SecurityMaster::load(&path).unwrap();
+    master.refresh(&svc, 0.9).await.unwrap();
+    let saved = std::fs::read(&path).unwrap();
+
+    total.store(100, Ordering::SeqCst);
+    let err = master.refresh(&svc, 0.9).await.unwrap_err();
+    assert!(
+        err.to_string().contains("keeping the current list"),
+        "{err}"
+    );
+    assert_eq!(master.len().await, 250);
+    assert_eq!(std::fs::read(&path).unwrap(), saved);
+
+    // 小幅减少（如退市）照常替换
+    total.store(240, Ordering::SeqCst);
+    assert_eq!(master.refresh(&svc, 0.9).await.unwrap(), 240);
+    assert!(!master.contains("600245.SH").await);
+}
@@ -0,0 +1,127 @@
+use axum::{Json, Router, routing::get};
+use futures_util::StreamExt;
+use showmarket::config::Config;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::net::TcpListener;
+use tokio::sync::Notify;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+
+mod common;
+
+/// 每次请求都等待 `delay` 才返回一根日线的 K 线接口；收到请求时通知 `Notify`。
+async fn slow_upstream(delay: Duration) -> (String, Arc<Notify>) {
+    let received = Arc::new(Notify::new());
+    let notify = received.clone();
+    let app = Router::new().route(
+        "/kline",
+        get(move || async move {
+            notify.notify_one();
+            tokio::time::sleep(delay).await;
+            Json(serde_json::json!({
+                "data": { "klines": ["2024-03-08,10,10.1,10.2,9.9,100"] }
+            }))
+        }),
+    );
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    (format!("http://{addr}/kline"), received)
+}
+
+fn state(history_url: String, shutdown_timeout_ms: u64) -> common::TestState {
+    let mut config = Config::default();
+    config.klines.history_url = history_url;
+    config.server.shutdown_timeout_ms = shutdown_timeout_ms;
+    common::state_with(config)
+}
+
+#[tokio::test]
+async fn in_flight_requests_finish_before_exit() {
+    let (upstream, received) = slow_upstream(Duration::from_millis(500)).await;
+    let state = state(upstream, 5_000);
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (signal, fire) = tokio::sync::oneshot::channel::<()>();
+    let server = tokio::spawn(showmarket::serve(state.clone(), listener, async {
+        let _ = fire.await;
+    }));
+
+    let request = tokio::spawn(reqwest::get(format!(
+        "http://{addr}/api/klines/600000.SH?interval=1d"
+    )));
+    received.notified().await;
+    signal.send(()).unwrap();
+
+    let resp = request.await.unwrap().unwrap();
+    assert_eq!(resp.status(), 200);
+    let bars: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(bars.as_array().map(Vec::len), Some(1), "{bars}");
+    assert!(server.await.unwrap().unwrap(), "drained within deadline");
+    assert!(state.shutdown_token().is_cancelled());
+    // 退出后不再接受新连接
+    assert!(reqwest::get(format!("http://{addr}/health")).await.is_err());
+}
+
+#[tokio::test]
+async fn websocket_sessions_are_closed_and_awaited() {
+    let state = state(slow_upstream(Duration::ZERO).await.0, 5_000);
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = tokio::spawn(showmarket::serve(
+        state.clone(),
+        listener,
+        std::future::pending(),
+    ));
+
+    let (mut ws, _) = tokio_tungstenite::connect_async(format!("ws://{addr}/ws/prices"))
+        .await
+        .unwrap();
+    state.shutdown_token().cancel();
+
+    let close = loop {
+        let msg = tokio::time::timeout(Duration::from_secs(5), ws.next())
+            .await
+            .expect("timed out waiting for close frame")
+            .unwrap()
+            .unwrap();
+        if let Message::Close(frame) = msg {
+            break frame.unwrap();
+        }
+    };
+    assert_eq!(close.code, CloseCode::Away);
+    assert_eq!(close.reason, "server shutting down");
+    assert!(server.await.unwrap().unwrap(), "drained within deadline");
+    assert_eq!(state.tasks().len(), 0);
+}
+
+#[tokio::test]
+async fn shutdown_gives_up_after_timeout() {
+    let (upstream, received) = slow_upstream(Duration::from_secs(30)).await;
+    let state = state(upstream, 300);
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = tokio::spawn(showmarket::serve(
+        state.clone(),
+        listener,
+        std::future::pending(),
+    ));
+
+    let _request = tokio::spawn(reqwest::get(format!(
+        "http://{addr}/api/klines/600000.SH?interval=1d"
+    )));
+    received.notified().await;
+    let started = Instant::now();
+    state.shutdown_token().cancel();
+
+    let drained = server.await.unwrap().unwrap();
+    let elapsed = started.elapsed();
+    assert!(!drained);
+    assert!(
+        elapsed >= Duration::from_millis(300) && elapsed < Duration::from_secs(2),
+        "{elapsed:?}"
+    );
+    // 超时后仍会落盘
+    assert!(state.data_dir().join("watchlists.json").exists());
+}
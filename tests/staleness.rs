@@ -0,0 +1,134 @@
+use chrono::{DateTime, Duration, NaiveDate, TimeZone, Utc};
+use showmarket::config::Config;
+use showmarket::models::event::StreamEvent;
+use showmarket::models::market::{HolidayCalendar, Market};
+use showmarket::models::price::PriceUpdate;
+use showmarket::services::staleness::StalenessTracker;
+
+/// 2024-03-05（周二）10:00 北京时间，A 股交易时段内。
+fn trading_time() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2024, 3, 5, 2, 0, 0).unwrap()
+}
+
+fn quote(price: f64, source_ts: DateTime<Utc>, at: DateTime<Utc>) -> PriceUpdate {
+    PriceUpdate {
+        symbol: "600000.SH".to_string(),
+        price,
+        ts_ms: at.timestamp_millis(),
+        prev_close: None,
+        source_ts_ms: Some(source_ts.timestamp_millis()),
+        stale: false,
+    }
+}
+
+#[test]
+fn frozen_source_time_goes_stale_then_recovers() {
+    let mut tracker = StalenessTracker::new(60_000, HolidayCalendar::default());
+    let t0 = trading_time();
+
+    let mut q = quote(10.0, t0, t0);
+    assert!(tracker.observe(&mut q, t0).is_none());
+    assert!(!q.stale);
+
+    // 源时间不动，30 秒内还不算旧
+    let t1 = t0 + Duration::seconds(30);
+    let mut q = quote(10.0, t0, t1);
+    assert!(tracker.observe(&mut q, t1).is_none());
+    assert!(!q.stale);
+
+    let t2 = t0 + Duration::seconds(90);
+    let mut q = quote(10.0, t0, t2);
+    let event = tracker.observe(&mut q, t2);
+    assert!(q.stale);
+    match event {
+        Some(StreamEvent::Stale(f)) => {
+            assert_eq!(f.symbol, "600000.SH");
+            assert_eq!(f.last_fresh_ts_ms, t0.timestamp_millis());
+        }
+        other => panic!("expected stale event, got {other:?}"),
+    }
+
+    let t3 = t0 + Duration::seconds(95);
+    let mut q = quote(10.01, t3, t3);
+    assert!(matches!(
+        tracker.observe(&mut q, t3),
+        Some(StreamEvent::Recovered(_))
+    ));
+    assert!(!q.stale);
+}
+
+#[test]
+fn failing_polls_go_stale_only_while_trading() {
+    let mut tracker = StalenessTracker::new(60_000, HolidayCalendar::default());
+    let t0 = trading_time();
+    let mut q = quote(10.0, t0, t0);
+    tracker.observe(&mut q, t0);
+
+    assert!(
+        tracker
+            .check_failed("600000.SH", t0 + Duration::seconds(30))
+            .is_none()
+    );
+    assert!(matches!(
+        tracker.check_failed("600000.SH", t0 + Duration::seconds(61)),
+        Some(StreamEvent::Stale(_))
+    ));
+    // 已经 stale，不重复推送
+    assert!(
+        tracker
+            .check_failed("600000.SH", t0 + Duration::seconds(120))
+            .is_none()
+    );
+
+    // 周六：价格不动也不算旧
+    let mut tracker = StalenessTracker::new(60_000, HolidayCalendar::default());
+    let sat = Utc.with_ymd_and_hms(2024, 3, 9, 2, 0, 0).unwrap();
+    let mut q = quote(10.0, t0, sat);
+    tracker.observe(&mut q, sat);
+    let later = sat + Duration::hours(1);
+    let mut q = quote(10.0, t0, later);
+    assert!(tracker.observe(&mut q, later).is_none());
+    assert!(!q.stale);
+    assert!(tracker.check_failed("600000.SH", later).is_none());
+}
+
+#[test]
+fn holidays_are_not_trading_days() {
+    let config: Config = toml::from_str(
+        r#"
+[calendar]
+holidays = { SH = ["2024-03-05"], HK = ["2024-03-29"] }
+"#,
+    )
+    .unwrap();
+    let calendar = HolidayCalendar::new(&config.calendar.holidays);
+    let t0 = trading_time();
+    assert!(calendar.is_holiday(Market::SH, NaiveDate::from_ymd_opt(2024, 3, 5).unwrap()));
+    assert!(!calendar.is_trading(Market::SH, t0));
+    // 休市日按市场区分
+    assert!(calendar.is_trading(Market::SZ, t0));
+    // 按交易所当地日期判断：北京时间 3 月 29 日 10:00 是港股耶稣受难节
+    let good_friday = Utc.with_ymd_and_hms(2024, 3, 29, 2, 0, 0).unwrap();
+    assert!(!calendar.is_trading(Market::HK, good_friday));
+    assert!(calendar.is_trading(Market::SH, good_friday));
+
+    // 休市日价格不动、拉取失败都不算旧
+    let mut tracker = StalenessTracker::new(60_000, calendar);
+    let mut q = quote(10.0, t0, t0);
+    tracker.observe(&mut q, t0);
+    let later = t0 + Duration::hours(1);
+    let mut q = quote(10.0, t0, later);
+    assert!(tracker.observe(&mut q, later).is_none());
+    assert!(!q.stale);
+    assert!(tracker.check_failed("600000.SH", later).is_none());
+
+    // 环境变量覆盖其他项时保留配置文件中的休市日
+    let mut config = config;
+    config
+        .apply_env([(
+            "SHOWMARKET_POLLER_STALE_AFTER_MS".to_string(),
+            "30000".to_string(),
+        )])
+        .unwrap();
+    assert_eq!(config.calendar.holidays[&Market::HK].len(), 1);
+}
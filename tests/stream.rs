@@ -0,0 +1,131 @@
+use axum::body::{Body, to_bytes};
+use axum::http::{Request, StatusCode};
+use showmarket::config::Config;
+use showmarket::models::event::{Freshness, StreamEvent};
+use showmarket::services::replay::ReplayBuffer;
+use showmarket::state::AppState;
+use tower::ServiceExt;
+
+mod common;
+use common::{price, state};
+
+fn stale(symbol: &str) -> StreamEvent {
+    StreamEvent::Stale(Freshness {
+        symbol: symbol.to_string(),
+        last_fresh_ts_ms: 1,
+        ts_ms: 2,
+    })
+}
+
+/// 取消关闭令牌后请求 SSE：只会收到补发/快照部分，响应随即结束。
+async fn sse(state: &AppState, uri: &str, last_event_id: Option<&str>) -> (StatusCode, String) {
+    state.shutdown_token().cancel();
+    let mut req = Request::get(uri);
+    if let Some(id) = last_event_id {
+        req = req.header("last-event-id", id);
+    }
+    let resp = showmarket::app(state.clone())
+        .oneshot(req.body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = resp.status();
+    let body = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (status, String::from_utf8(body.to_vec()).unwrap())
+}
+
+#[test]
+fn replay_buffer_reports_gaps() {
+    let buf = ReplayBuffer::new(3);
+    for i in 0..5 {
+        buf.push(stale(&format!("60000{i}.SH")), |_| {});
+    }
+    assert_eq!(buf.last_id(), 5);
+    let ids: Vec<u64> = buf.since(2).unwrap().iter().map(|p| p.id).collect();
+    assert_eq!(ids, vec![3, 4, 5]);
+    assert!(buf.since(5).unwrap().is_empty());
+    // id 1 之后的 2 已被挤出
+    assert!(buf.since(1).is_none());
+    // 重启前的 id
+    assert!(buf.since(9).is_none());
+}
+
+#[tokio::test]
+async fn sse_resumes_from_last_event_id() {
+    let state = state();
+    state.set_latest(price("600000.SH", 10.0)).await;
+    state.set_latest(price("000001.SZ", 11.0)).await;
+    state.publish(stale("600000.SH"));
+
+    let (status, body) = sse(&state, "/api/stream/prices?symbols=600000.SH", Some("1")).await;
+    assert_eq!(status, StatusCode::OK);
+    // id 2 是 000001.SZ，被过滤掉
+    assert!(!body.contains("000001.SZ"));
+    assert!(body.contains("id: 3\n"));
+    assert!(body.contains(r#""type":"stale""#));
+    assert!(!body.contains(r#""type":"price""#));
+}
+
+#[tokio::test]
+async fn sse_sends_snapshot_without_resumable_id() {
+    let state = state();
+    state.set_latest(price("600000.SH", 10.0)).await;
+    state.set_latest(price("600000.SH", 10.5)).await;
+
+    let (status, body) = sse(&state, "/api/stream/prices", Some("42")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body.matches("data: ").count(), 1);
+    assert!(body.contains(r#""price":10.5"#));
+    assert!(body.contains("id: 2\n"));
+}
+
+#[tokio::test]
+async fn sse_rejects_bad_symbols() {
+    let (status, body) = sse(&state(), "/api/stream/prices?symbols=nope", None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body.contains("invalid_symbol"));
+}
+
+#[tokio::test]
+async fn sequence_numbers_are_per_symbol() {
+    let state = state();
+    let mut rx = state.subscribe();
+    state.set_latest(price("600000.SH", 10.0)).await;
+    state.set_latest(price("000001.SZ", 11.0)).await;
+    state.publish(stale("600000.SH"));
+
+    let seqs: Vec<(u64, u64)> = (0..3)
+        .map(|_| rx.try_recv().unwrap())
+        .map(|p| (p.id, p.seq))
+        .collect();
+    assert_eq!(seqs, vec![(1, 1), (2, 1), (3, 2)]);
+
+    let json = serde_json::to_value(&state.snapshot().await[1]).unwrap();
+    assert_eq!(json["type"], "price");
+    assert_eq!(json["symbol"], "600000.SH");
+    assert_eq!(json["seq"], 2);
+}
+
+#[tokio::test]
+async fn resync_replays_or_falls_back_to_snapshot() {
+    let mut config = Config::default();
+    config.stream.replay_capacity = 4;
+    let state = common::state_with(config);
+    for i in 0..4 {
+        state.set_latest(price("600000.SH", 10.0 + i as f64)).await;
+    }
+    state.set_latest(price("000001.SZ", 11.0)).await;
+
+    // 600000.SH 的 seq 3、4 仍在缓冲区
+    let missed = state.resync("600000.SH", 2).await;
+    let seqs: Vec<u64> = missed.iter().map(|p| p.seq).collect();
+    assert_eq!(seqs, vec![3, 4]);
+    assert!(state.resync("600000.SH", 4).await.is_empty());
+
+    // seq 2 已被挤出，改发快照
+    let snapshot = state.resync("600000.SH", 0).await;
+    assert_eq!(snapshot.len(), 1);
+    let json = serde_json::to_value(&snapshot[0]).unwrap();
+    assert_eq!(json["type"], "snapshot");
+    assert_eq!(json["seq"], 4);
+    assert_eq!(json["latest"]["price"], 13.0);
+}
@@ -0,0 +1,72 @@
+use axum::{Router, http::StatusCode, routing::get};
+use showmarket::config::UpstreamConfig;
+use showmarket::services::upstream::{UpstreamClient, UpstreamLimiter, UpstreamUnavailable};
+use std::time::Duration;
+use tokio::net::TcpListener;
+
+fn config() -> UpstreamConfig {
+    UpstreamConfig {
+        rate_limit_per_sec: 0.0,
+        breaker_failure_threshold: 3,
+        breaker_open_ms: 100,
+        ..UpstreamConfig::default()
+    }
+}
+
+#[tokio::test]
+async fn breaker_opens_after_consecutive_failures_and_half_opens() {
+    let limiter = UpstreamLimiter::new(&config());
+    for _ in 0..3 {
+        limiter.acquire("example.com").await.unwrap();
+        limiter.record("example.com", false);
+    }
+
+    let err = limiter.acquire("example.com").await.unwrap_err();
+    assert!(matches!(err, UpstreamUnavailable::CircuitOpen { .. }));
+    assert_eq!(limiter.open_circuits(), vec!["example.com".to_string()]);
+    // 其它 host 不受影响
+    limiter.acquire("other.com").await.unwrap();
+
+    tokio::time::sleep(Duration::from_millis(120)).await;
+    // 半开：只放一个试探请求
+    limiter.acquire("example.com").await.unwrap();
+    assert!(limiter.acquire("example.com").await.is_err());
+    limiter.record("example.com", true);
+    limiter.acquire("example.com").await.unwrap();
+    assert!(limiter.open_circuits().is_empty());
+}
+
+#[tokio::test]
+async fn rate_limiter_rejects_beyond_max_wait() {
+    let limiter = UpstreamLimiter::new(&UpstreamConfig {
+        rate_limit_per_sec: 1.0,
+        rate_limit_burst: 2,
+        rate_limit_max_wait_ms: 10,
+        ..config()
+    });
+    limiter.acquire("example.com").await.unwrap();
+    limiter.acquire("example.com").await.unwrap();
+    let err = limiter.acquire("example.com").await.unwrap_err();
+    assert!(matches!(err, UpstreamUnavailable::RateLimited { .. }));
+}
+
+#[tokio::test]
+async fn client_fails_fast_once_circuit_is_open() {
+    let app = Router::new().route("/", get(|| async { StatusCode::SERVICE_UNAVAILABLE }));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    let url = format!("http://{addr}/");
+
+    let mut client = UpstreamClient::new(&config()).unwrap();
+    client.set_limiter(UpstreamLimiter::new(&config()));
+    for _ in 0..3 {
+        let err = client.get("test", &url).await.unwrap_err();
+        assert!(err.downcast_ref::<UpstreamUnavailable>().is_none());
+    }
+    let err = client.get("test", &url).await.unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<UpstreamUnavailable>(),
+        Some(UpstreamUnavailable::CircuitOpen { .. })
+    ));
+}
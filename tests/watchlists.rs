@@ -0,0 +1,253 @@
+use axum::body::{Body, to_bytes};
+use axum::http::{Request, StatusCode, header};
+use showmarket::services::watchlists::{
+    DEFAULT_USER, DEFAULT_WATCHLIST, WatchlistError, WatchlistStore,
+};
+use showmarket::state::AppState;
+use tower::ServiceExt;
+
+mod common;
+
+fn symbols(list: &[&str]) -> Vec<String> {
+    list.iter().map(|s| s.to_string()).collect()
+}
+
+async fn send(
+    state: &AppState,
+    method: &str,
+    uri: &str,
+    body: serde_json::Value,
+) -> (StatusCode, serde_json::Value) {
+    let req = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    let resp = showmarket::app(state.clone()).oneshot(req).await.unwrap();
+    let status = resp.status();
+    let body = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (
+        status,
+        serde_json::from_slice(&body).unwrap_or(serde_json::Value::Null),
+    )
+}
+
+#[tokio::test]
+async fn create_add_reorder_and_remove() {
+    let dir = common::temp_dir();
+    let store = WatchlistStore::load(dir.path().join("watchlists.json")).unwrap();
+
+    let w = store
+        .create("alice", "banks".to_string(), symbols(&["600000.SH"]))
+        .await
+        .unwrap();
+    assert_eq!(w.symbols, ["600000.SH"]);
+    let w = store
+        .add_symbol("alice", "banks", "600036.SH".to_string(), None)
+        .await
+        .unwrap();
+    assert_eq!(w.symbols, ["600000.SH", "600036.SH"]);
+    let w = store
+        .add_symbol("alice", "banks", "601398.SH".to_string(), Some(0))
+        .await
+        .unwrap();
+    assert_eq!(w.symbols, ["601398.SH", "600000.SH", "600036.SH"]);
+
+    let w = store
+        .reorder(
+            "alice",
+            "banks",
+            symbols(&["600036.SH", "601398.SH", "600000.SH"]),
+        )
+        .await
+        .unwrap();
+    assert_eq!(w.symbols, ["600036.SH", "601398.SH", "600000.SH"]);
+    // 重排必须恰好是现有 symbol 的一个排列
+    for bad in [
+        symbols(&["600036.SH", "601398.SH"]),
+        symbols(&["600036.SH", "601398.SH", "600519.SH"]),
+    ] {
+        assert!(matches!(
+            store.reorder("alice", "banks", bad).await,
+            Err(WatchlistError::Invalid(_))
+        ));
+    }
+
+    let w = store
+        .remove_symbol("alice", "banks", "601398.SH")
+        .await
+        .unwrap();
+    assert_eq!(w.symbols, ["600036.SH", "600000.SH"]);
+    assert!(matches!(
+        store.remove_symbol("alice", "banks", "601398.SH").await,
+        Err(WatchlistError::NotFound)
+    ));
+
+    // 用户之间互不影响
+    assert!(store.list("bob").await.is_empty());
+    assert_eq!(
+        store.list(DEFAULT_USER).await[0].name,
+        DEFAULT_WATCHLIST.to_string()
+    );
+
+    store.delete("alice", "banks").await.unwrap();
+    assert!(store.get("alice", "banks").await.is_none());
+    assert!(matches!(
+        store.delete("alice", "banks").await,
+        Err(WatchlistError::NotFound)
+    ));
+}
+
+#[tokio::test]
+async fn lists_survive_reload() {
+    let dir = common::temp_dir();
+    let path = dir.path().join("watchlists.json");
+    let store = WatchlistStore::load(&path).unwrap();
+    store
+        .create(
+            "alice",
+            "tech".to_string(),
+            symbols(&["300750.SZ", "688981.SH"]),
+        )
+        .await
+        .unwrap();
+    store
+        .reorder("alice", "tech", symbols(&["688981.SH", "300750.SZ"]))
+        .await
+        .unwrap();
+    store.delete(DEFAULT_USER, DEFAULT_WATCHLIST).await.unwrap();
+
+    let store = WatchlistStore::load(&path).unwrap();
+    let lists = store.list("alice").await;
+    assert_eq!(lists.len(), 1);
+    assert_eq!(lists[0].symbols, ["688981.SH", "300750.SZ"]);
+    // 已删除的默认列表不会因重新加载而恢复
+    assert!(store.list(DEFAULT_USER).await.is_empty());
+    assert_eq!(store.all_symbols().await, ["688981.SH", "300750.SZ"]);
+}
+
+#[tokio::test]
+async fn failed_writes_leave_lists_untouched() {
+    let dir = common::temp_dir();
+    let path = dir.path().join("watchlists.json");
+    let store = WatchlistStore::load(&path).unwrap();
+    // 出错的修改不会留下空用户
+    assert!(matches!(
+        store.delete("bob", "missing").await,
+        Err(WatchlistError::NotFound)
+    ));
+    store.flush().await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+    assert!(body["users"].get("bob").is_none());
+
+    // 落盘失败时内存也不变
+    std::fs::remove_file(&path).unwrap();
+    std::fs::create_dir(&path).unwrap();
+    assert!(matches!(
+        store
+            .create("alice", "banks".to_string(), symbols(&["600000.SH"]))
+            .await,
+        Err(WatchlistError::Storage(_))
+    ));
+    assert!(store.list("alice").await.is_empty());
+    assert!(matches!(
+        store
+            .add_symbol(
+                DEFAULT_USER,
+                DEFAULT_WATCHLIST,
+                "600000.SH".to_string(),
+                None
+            )
+            .await,
+        Err(WatchlistError::Storage(_))
+    ));
+    let default = store.get(DEFAULT_USER, DEFAULT_WATCHLIST).await.unwrap();
+    assert!(!default.symbols.contains(&"600000.SH".to_string()));
+}
+
+#[tokio::test]
+async fn duplicates_are_rejected() {
+    let dir = common::temp_dir();
+    let store = WatchlistStore::load(dir.path().join("watchlists.json")).unwrap();
+    assert!(matches!(
+        store
+            .create(
+                "alice",
+                "dup".to_string(),
+                symbols(&["600000.SH", "600000.SH"])
+            )
+            .await,
+        Err(WatchlistError::Invalid(_))
+    ));
+    store
+        .create("alice", "banks".to_string(), symbols(&["600000.SH"]))
+        .await
+        .unwrap();
+    assert!(matches!(
+        store.create("alice", "banks".to_string(), Vec::new()).await,
+        Err(WatchlistError::AlreadyExists)
+    ));
+    assert!(matches!(
+        store
+            .add_symbol("alice", "banks", "600000.SH".to_string(), None)
+            .await,
+        Err(WatchlistError::SymbolExists(_))
+    ));
+    assert!(matches!(
+        store
+            .reorder("alice", "banks", symbols(&["600000.SH", "600000.SH"]))
+            .await,
+        Err(WatchlistError::Invalid(_))
+    ));
+}
+
+#[tokio::test]
+async fn api_rejects_unknown_and_duplicate_symbols() {
+    let state = common::state();
+
+    let (status, body) = send(
+        &state,
+        "POST",
+        "/api/watchlists",
+        serde_json::json!({ "name": "mixed", "symbols": ["600000.SH", "999999.SH"] }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND, "{body}");
+    assert_eq!(body["code"], "unknown_security");
+
+    let (status, body) = send(
+        &state,
+        "POST",
+        "/api/watchlists/default/symbols",
+        serde_json::json!({ "symbol": "bogus" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+    assert_eq!(body["code"], "invalid_symbol");
+
+    let (status, body) = send(
+        &state,
+        "POST",
+        "/api/watchlists/default/symbols",
+        serde_json::json!({ "symbol": "000001.SH" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT, "{body}");
+    assert_eq!(body["code"], "conflict");
+
+    let (status, body) = send(
+        &state,
+        "PUT",
+        "/api/watchlists/default/symbols",
+        serde_json::json!({ "symbols": ["000001.SH", "399001.SZ", "999999.SH"] }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+    assert_eq!(body["code"], "bad_request");
+
+    // 失败的请求都没有改动数据
+    let lists = state.watchlists().list(DEFAULT_USER).await;
+    assert_eq!(lists.len(), 1);
+    assert_eq!(lists[0].symbols, ["000001.SH", "399001.SZ", "399006.SZ"]);
+}
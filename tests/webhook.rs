@@ -0,0 +1,196 @@
+use axum::{Router, body::Bytes, extract::State, http::HeaderMap, http::StatusCode, routing::post};
+use showmarket::models::alert::{AlertFired, AlertRule};
+use showmarket::models::event::{Freshness, StreamEvent};
+use showmarket::models::price::PriceUpdate;
+use showmarket::services::webhook::{
+    SIGNATURE_HEADER, TIMESTAMP_HEADER, WebhookConfig, WebhookPayload, WebhookSink, sign,
+    spawn_webhook_sink,
+};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::TcpListener;
+
+mod common;
+
+#[derive(Clone, Default)]
+struct Received {
+    hits: Arc<Mutex<Vec<(HeaderMap, Bytes)>>>,
+    /// 前 N 次返回 500
+    fail_first: Arc<Mutex<usize>>,
+}
+
+async fn receive(State(r): State<Received>, headers: HeaderMap, body: Bytes) -> StatusCode {
+    r.hits.lock().unwrap().push((headers, body));
+    let mut fail = r.fail_first.lock().unwrap();
+    if *fail > 0 {
+        *fail -= 1;
+        StatusCode::INTERNAL_SERVER_ERROR
+    } else {
+        StatusCode::OK
+    }
+}
+
+async fn spawn_receiver(received: Received) -> String {
+    let app = Router::new()
+        .route("/hook", post(receive))
+        .with_state(received);
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    format!("http://{addr}/hook")
+}
+
+fn payload() -> WebhookPayload {
+    WebhookPayload {
+        event: "alert".to_string(),
+        symbol: "000001.SH".to_string(),
+        alert_id: Some(1),
+        rule: Some(AlertRule::CrossAbove { level: 3300.0 }),
+        update: Some(PriceUpdate {
+            prev_close: Some(3290.0),
+            ..common::price("000001.SH", 3301.5)
+        }),
+        last_fresh_ts_ms: None,
+        ts_ms: 1_700_000_000_000,
+    }
+}
+
+fn config(url: String, dead_letter: std::path::PathBuf) -> WebhookConfig {
+    let mut cfg = WebhookConfig::new(vec![url], dead_letter);
+    cfg.secret = Some("s3cret".to_string());
+    cfg.max_retries = 2;
+    cfg.initial_backoff = Duration::from_millis(10);
+    cfg
+}
+
+#[tokio::test]
+async fn delivers_signed_payload_after_retry() {
+    let received = Received::default();
+    *received.fail_first.lock().unwrap() = 1;
+    let url = spawn_receiver(received.clone()).await;
+    let dir = common::temp_dir();
+    let dead_letter = dir.path().join("dead_letter.jsonl");
+
+    WebhookSink::new(config(url, dead_letter.clone()))
+        .deliver(&payload())
+        .await;
+
+    let hits = received.hits.lock().unwrap();
+    assert_eq!(hits.len(), 2);
+    let (headers, body) = &hits[1];
+    let ts: i64 = headers[TIMESTAMP_HEADER].to_str().unwrap().parse().unwrap();
+    let expected = format!("sha256={}", sign("s3cret", ts, body));
+    assert_eq!(headers[SIGNATURE_HEADER].to_str().unwrap(), expected);
+    let got: WebhookPayload = serde_json::from_slice(body).unwrap();
+    assert_eq!(got, payload());
+    assert!(!dead_letter.exists());
+}
+
+#[tokio::test]
+async fn exhausted_retries_go_to_dead_letter() {
+    let received = Received::default();
+    *received.fail_first.lock().unwrap() = usize::MAX;
+    let url = spawn_receiver(received.clone()).await;
+    let dir = common::temp_dir();
+    let dead_letter = dir.path().join("dead_letter.jsonl");
+
+    WebhookSink::new(config(url, dead_letter.clone()))
+        .deliver(&payload())
+        .await;
+
+    // 首次 + 2 次重试
+    assert_eq!(received.hits.lock().unwrap().len(), 3);
+    let log = std::fs::read_to_string(&dead_letter).unwrap();
+    let record: serde_json::Value = serde_json::from_str(log.lines().next().unwrap()).unwrap();
+    assert_eq!(record["attempts"], 3);
+    assert_eq!(record["payload"]["symbol"], "000001.SH");
+}
+
+#[tokio::test]
+async fn sink_delivers_every_alert_in_a_burst() {
+    let received = Received::default();
+    let url = spawn_receiver(received.clone()).await;
+    let state = common::state();
+    let dead_letter = state.data_dir().join("dead_letter.jsonl");
+    spawn_webhook_sink(
+        state.clone(),
+        WebhookSink::new(config(url, dead_letter.clone())),
+    );
+
+    // 远超事件广播的容量，且中间不让出执行权
+    for id in 1..=200 {
+        state.publish(StreamEvent::Alert(AlertFired {
+            alert_id: id,
+            symbol: "000001.SH".to_string(),
+            rule: AlertRule::CrossAbove { level: 3300.0 },
+            update: common::price("000001.SH", 3301.5),
+            ts_ms: 1_700_000_000_000,
+        }));
+        state.publish(StreamEvent::Price(common::price("000001.SH", 3301.5)));
+    }
+
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while received.hits.lock().unwrap().len() < 200 {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .expect("alerts were dropped");
+    let mut ids: Vec<u64> = received
+        .hits
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(_, body)| {
+            serde_json::from_slice::<WebhookPayload>(body)
+                .unwrap()
+                .alert_id
+                .unwrap()
+        })
+        .collect();
+    ids.sort();
+    assert_eq!(ids, (1..=200).collect::<Vec<_>>());
+    assert!(!dead_letter.exists());
+}
+
+#[tokio::test]
+async fn sink_delivers_freshness_changes() {
+    let received = Received::default();
+    let url = spawn_receiver(received.clone()).await;
+    let state = common::state();
+    spawn_webhook_sink(
+        state.clone(),
+        WebhookSink::new(config(url, state.data_dir().join("dead_letter.jsonl"))),
+    );
+
+    let freshness = |ts_ms| Freshness {
+        symbol: "000001.SH".to_string(),
+        last_fresh_ts_ms: 1_700_000_000_000,
+        ts_ms,
+    };
+    state.publish(StreamEvent::Stale(freshness(1_700_000_060_000)));
+    state.publish(StreamEvent::Recovered(freshness(1_700_000_120_000)));
+
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while received.hits.lock().unwrap().len() < 2 {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .expect("freshness changes were not delivered");
+    let mut got: Vec<WebhookPayload> = received
+        .hits
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(_, body)| serde_json::from_slice(body).unwrap())
+        .collect();
+    got.sort_by_key(|p| p.ts_ms);
+    assert_eq!(got[0].event, "stale");
+    assert_eq!(got[1].event, "recovered");
+    for p in &got {
+        assert_eq!(p.symbol, "000001.SH");
+        assert_eq!(p.last_fresh_ts_ms, Some(1_700_000_000_000));
+        assert!(p.alert_id.is_none());
+    }
+}
@@ -0,0 +1,506 @@
+use futures_util::{SinkExt, StreamExt};
+use showmarket::config::Config;
+use showmarket::models::alert::{AlertFired, AlertRule};
+use showmarket::models::encoding::{Encoding, WireFormat};
+use showmarket::models::event::{Freshness, Published, StreamEvent};
+use showmarket::services::metrics::Metrics;
+use showmarket::services::outbox::Outbox;
+use showmarket::state::AppState;
+use std::io::Write;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::Message;
+
+mod common;
+use common::{price, state, state_with};
+
+async fn serve(state: &AppState) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let app = showmarket::app(state.clone());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    format!("ws://{addr}/ws/prices")
+}
+
+async fn next_message(
+    ws: &mut tokio_tungstenite::WebSocketStream<
+        tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>,
+    >,
+) -> Message {
+    loop {
+        let msg = tokio::time::timeout(Duration::from_secs(5), ws.next())
+            .await
+            .expect("timed out waiting for message")
+            .unwrap()
+            .unwrap();
+        if !matches!(msg, Message::Ping(_) | Message::Pong(_)) {
+            return msg;
+        }
+    }
+}
+
+#[test]
+fn encodings_round_trip() {
+    let published = Published::new(7, 3, StreamEvent::Price(price("600000.SH", 10.5)));
+    let json: serde_json::Value =
+        serde_json::from_slice(&published.encoded(WireFormat::default()).unwrap()).unwrap();
+    assert_eq!(json["type"], "price");
+    assert_eq!(json["seq"], 3);
+
+    for encoding in [Encoding::Msgpack, Encoding::Cbor] {
+        let format = WireFormat {
+            encoding,
+            deflate: false,
+        };
+        let bytes = published.encoded(format).unwrap();
+        let decoded: serde_json::Value = encoding.decode(&bytes).unwrap();
+        assert_eq!(decoded, json, "{}", encoding.name());
+
+        let deflated = published
+            .encoded(WireFormat {
+                encoding,
+                deflate: true,
+            })
+            .unwrap();
+        assert!(!deflated.ends_with(&[0, 0, 0xff, 0xff]));
+        assert_eq!(inflate(&deflated), bytes);
+    }
+}
+
+#[test]
+fn each_format_is_encoded_once() {
+    let published = Published::new(1, 1, StreamEvent::Price(price("600000.SH", 10.5)));
+    let shared = published.clone();
+    let format = WireFormat {
+        encoding: Encoding::Cbor,
+        deflate: false,
+    };
+    let a = published.encoded(format).unwrap();
+    let b = shared.encoded(format).unwrap();
+    assert_eq!(a.as_ptr(), b.as_ptr());
+}
+
+#[tokio::test]
+async fn ws_negotiates_msgpack_and_resyncs() {
+    let state = state();
+    state.set_latest(price("600000.SH", 10.0)).await;
+    let url = serve(&state).await;
+
+    let (mut ws, _) = tokio_tungstenite::connect_async(format!("{url}?encoding=msgpack"))
+        .await
+        .unwrap();
+    // 连接后先收到快照
+    let Message::Binary(data) = next_message(&mut ws).await else {
+        panic!("expected a binary frame");
+    };
+    let snapshot: serde_json::Value = Encoding::Msgpack.decode(&data).unwrap();
+    assert_eq!(snapshot["symbol"], "600000.SH");
+    assert_eq!(snapshot["seq"], 1);
+
+    state.set_latest(price("600000.SH", 10.2)).await;
+    let Message::Binary(data) = next_message(&mut ws).await else {
+        panic!("expected a binary frame");
+    };
+    let update: serde_json::Value = Encoding::Msgpack.decode(&data).unwrap();
+    assert_eq!(update["price"], 10.2);
+    assert_eq!(update["seq"], 2);
+
+    ws.send(Message::Text(
+        r#"{"type":"resync","symbol":"600000.SH","after_seq":1}"#.into(),
+    ))
+    .await
+    .unwrap();
+    let Message::Binary(data) = next_message(&mut ws).await else {
+        panic!("expected a binary frame");
+    };
+    let replayed: serde_json::Value = Encoding::Msgpack.decode(&data).unwrap();
+    assert_eq!(replayed, update);
+}
+
+/// permessage-deflate 负载：raw deflate 同步刷新后去掉 `00 00 ff ff`。
+#[tokio::test]
+async fn ws_delivers_alerts_created_over_rest() {
+    let state = state();
+    showmarket::services::alerts::spawn_alert_engine(state.clone());
+    let url = serve(&state).await;
+    let api = url
+        .replace("ws://", "http://")
+        .replace("/ws/prices", "/api/alerts");
+
+    let resp = reqwest::Client::new()
+        .post(&api)
+        .json(&serde_json::json!({
+            "symbol": "600000.SH",
+            "rule": {"kind": "cross_above", "level": 10.5},
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert!(resp.status().is_success(), "{}", resp.status());
+    let created: serde_json::Value = resp.json().await.unwrap();
+
+    state.set_latest(price("600000.SH", 10.0)).await;
+    let (mut ws, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+    let Message::Text(snapshot) = next_message(&mut ws).await else {
+        panic!("expected a text frame");
+    };
+    let snapshot: serde_json::Value = serde_json::from_str(&snapshot).unwrap();
+    assert_eq!(snapshot["price"], 10.0);
+
+    // 价格穿越后客户端先收到报价，再收到告警
+    state.set_latest(price("600000.SH", 11.0)).await;
+    let alert = loop {
+        let Message::Text(txt) = next_message(&mut ws).await else {
+            panic!("expected a text frame");
+        };
+        let msg: serde_json::Value = serde_json::from_str(&txt).unwrap();
+        if msg["type"] == "alert" {
+            break msg;
+        }
+        assert_eq!(msg["type"], "price");
+    };
+    assert_eq!(alert["alert_id"], created["id"]);
+    assert_eq!(alert["symbol"], "600000.SH");
+    assert_eq!(alert["update"]["price"], 11.0);
+}
+
+fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut enc = flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::fast());
+    enc.write_all(data).unwrap();
+    enc.flush().unwrap();
+    let mut out = std::mem::take(enc.get_mut());
+    out.truncate(out.len() - 4);
+    out
+}
+
+fn inflate(data: &[u8]) -> Vec<u8> {
+    let mut input = data.to_vec();
+    input.extend_from_slice(&[0, 0, 0xff, 0xff]);
+    let mut out = Vec::with_capacity(64 * 1024);
+    flate2::Decompress::new(false)
+        .decompress_vec(&input, &mut out, flate2::FlushDecompress::Sync)
+        .unwrap();
+    out
+}
+
+/// 读一个服务端帧（不带掩码），跳过 ping；返回首字节与负载。
+async fn read_frame(tcp: &mut TcpStream) -> (u8, Vec<u8>) {
+    loop {
+        let mut head = [0u8; 2];
+        tcp.read_exact(&mut head).await.unwrap();
+        let len = match head[1] & 0x7f {
+            126 => tcp.read_u16().await.unwrap() as usize,
+            127 => tcp.read_u64().await.unwrap() as usize,
+            n => n as usize,
+        };
+        let mut payload = vec![0; len];
+        tcp.read_exact(&mut payload).await.unwrap();
+        if head[0] & 0x0f != 0x9 {
+            return (head[0], payload);
+        }
+    }
+}
+
+#[tokio::test]
+async fn ws_negotiates_permessage_deflate() {
+    use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+
+    let state = state();
+    state.set_latest(price("600000.SH", 10.0)).await;
+    let url = serve(&state).await;
+    let addr = url
+        .trim_start_matches("ws://")
+        .trim_end_matches("/ws/prices")
+        .to_string();
+
+    // tungstenite 客户端不支持该扩展，这里手工握手、收发帧
+    let mut tcp = TcpStream::connect(&addr).await.unwrap();
+    let handshake = format!(
+        "GET /ws/prices HTTP/1.1\r\nHost: {addr}\r\nConnection: Upgrade\r\nUpgrade: websocket\r\n\
+         Sec-WebSocket-Version: 13\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+         Sec-WebSocket-Extensions: permessage-deflate; client_max_window_bits\r\n\r\n"
+    );
+    tcp.write_all(handshake.as_bytes()).await.unwrap();
+    let mut head = Vec::new();
+    while !head.ends_with(b"\r\n\r\n") {
+        head.push(tcp.read_u8().await.unwrap());
+    }
+    let head = String::from_utf8(head).unwrap().to_ascii_lowercase();
+    assert!(head.starts_with("http/1.1 101"), "{head}");
+    assert!(head.contains(
+        "sec-websocket-extensions: permessage-deflate; server_no_context_takeover; client_no_context_takeover"
+    ));
+
+    // 快照是压缩过的文本帧：FIN + RSV1 + text
+    let (b0, payload) = read_frame(&mut tcp).await;
+    assert_eq!(b0, 0x80 | 0x40 | 0x1);
+    let snapshot: serde_json::Value = serde_json::from_slice(&inflate(&payload)).unwrap();
+    assert_eq!(snapshot["price"], 10.0);
+
+    // 客户端发来的压缩帧（带掩码）同样能解
+    let msg = deflate(br#"{"type":"resync","symbol":"600000.SH","after_seq":0}"#);
+    let key = [0x12, 0x34, 0x56, 0x78];
+    let mut frame = vec![0x80 | 0x40 | 0x1, 0x80 | msg.len() as u8];
+    frame.extend_from_slice(&key);
+    frame.extend(msg.iter().enumerate().map(|(i, b)| b ^ key[i % 4]));
+    tcp.write_all(&frame).await.unwrap();
+    let (_, payload) = read_frame(&mut tcp).await;
+    let replayed: serde_json::Value = serde_json::from_slice(&inflate(&payload)).unwrap();
+    assert_eq!(replayed["symbol"], "600000.SH");
+    assert_eq!(replayed["seq"], 1);
+
+    let clients = state.ws_clients().list();
+    assert_eq!(clients[0].compression, "permessage-deflate");
+
+    // 不提供扩展或参数无法满足时不压缩
+    for offer in [None, Some("permessage-deflate; server_max_window_bits=10")] {
+        let mut req = url.as_str().into_client_request().unwrap();
+        if let Some(offer) = offer {
+            req.headers_mut()
+                .insert("sec-websocket-extensions", offer.parse().unwrap());
+        }
+        let (mut ws, resp) = tokio_tungstenite::connect_async(req).await.unwrap();
+        assert!(resp.headers().get("sec-websocket-extensions").is_none());
+        assert!(matches!(next_message(&mut ws).await, Message::Text(_)));
+    }
+}
+
+#[tokio::test]
+async fn ws_negotiates_encoding_by_subprotocol() {
+    use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+
+    let state = state();
+    state.set_latest(price("600000.SH", 10.0)).await;
+    let url = serve(&state).await;
+
+    let mut req = url.into_client_request().unwrap();
+    req.headers_mut()
+        .insert("sec-websocket-protocol", "showmarket.cbor".parse().unwrap());
+    let (mut ws, resp) = tokio_tungstenite::connect_async(req).await.unwrap();
+    assert_eq!(
+        resp.headers().get("sec-websocket-protocol").unwrap(),
+        "showmarket.cbor"
+    );
+    let Message::Binary(data) = next_message(&mut ws).await else {
+        panic!("expected a binary frame");
+    };
+    let snapshot: serde_json::Value = Encoding::Cbor.decode(&data).unwrap();
+    assert_eq!(snapshot["price"], 10.0);
+}
+
+fn heartbeat_config() -> Config {
+    let mut config = Config::default();
+    config.stream.ws_ping_interval_ms = 100;
+    config.stream.ws_idle_timeout_ms = 300;
+    config
+}
+
+#[tokio::test]
+async fn ws_reaps_idle_clients() {
+    let state = state_with(heartbeat_config());
+    let url = serve(&state).await;
+
+    // 不读就不会回 pong
+    let (mut ws, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(state.ws_clients().len(), 1);
+    tokio::time::sleep(Duration::from_millis(600)).await;
+    assert_eq!(state.ws_clients().len(), 0);
+
+    // 先排队的 ping 会触发回 pong，服务端已关闭连接时读到的是写错误，只看有没有收到关闭帧
+    let mut close = None;
+    while let Some(Ok(msg)) = ws.next().await {
+        if let Message::Close(frame) = msg {
+            close = frame;
+        }
+    }
+    assert_eq!(
+        close.expect("expected a close frame").reason,
+        "idle timeout"
+    );
+    let metrics = state.metrics().render(0, &[]);
+    assert!(metrics.contains(r#"showmarket_ws_disconnects_total{reason="idle_timeout"} 1"#));
+}
+
+#[tokio::test]
+async fn ws_keeps_clients_that_answer_pings() {
+    let state = state_with(heartbeat_config());
+    let url = serve(&state).await;
+
+    let (mut ws, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+    // 读取时 tungstenite 自动回 pong
+    let deadline = tokio::time::Instant::now() + Duration::from_millis(800);
+    let mut pings = 0;
+    while let Ok(Some(msg)) = tokio::time::timeout_at(deadline, ws.next()).await {
+        if let Message::Ping(_) = msg.unwrap() {
+            pings += 1;
+        }
+    }
+    assert!(pings >= 3, "got {pings} pings");
+    assert_eq!(state.ws_clients().len(), 1);
+}
+
+#[tokio::test]
+async fn ws_conflates_then_drops_slow_consumers() {
+    let mut config = Config::default();
+    config.stream.ws_send_queue = 1;
+    config.stream.ws_max_behind_ms = 300;
+    config.stream.ws_ping_interval_ms = 100;
+    config.stream.ws_idle_timeout_ms = 60_000;
+    let state = state_with(config);
+    let url = serve(&state).await;
+
+    // 连上之后不再读，直到内核缓冲区写满
+    let (_ws, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    // 用很长的 symbol 撑大消息，尽快写满内核缓冲区
+    let symbols: Vec<String> = ["600000.SH", "000001.SZ", "00700.HK"]
+        .iter()
+        .map(|s| format!("{s}{}", "x".repeat(8192)))
+        .collect();
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(10);
+    let mut i = 0usize;
+    while !state.ws_clients().is_empty() && tokio::time::Instant::now() < deadline {
+        state.publish(StreamEvent::Price(price(&symbols[i % 3], i as f64)));
+        i += 1;
+        if i.is_multiple_of(16) {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+    }
+    assert_eq!(state.ws_clients().len(), 0, "slow client was not dropped");
+
+    let metrics = state.metrics().render(0, &[]);
+    assert!(
+        metrics.contains("showmarket_ws_conflations_total 1"),
+        "{metrics}"
+    );
+    assert!(!metrics.contains("showmarket_ws_conflated_messages_total 0"));
+    assert!(metrics.contains(r#"showmarket_ws_disconnects_total{reason="slow_consumer"} 1"#));
+}
+
+/// 容量为 1 的发送队列：第一条消息直接入队，之后的进入积压。
+fn backlogged() -> (Outbox, tokio::sync::mpsc::Receiver<Message>) {
+    let (tx, rx) = tokio::sync::mpsc::channel(1);
+    let mut outbox = Outbox::new(tx, WireFormat::default(), Metrics::new());
+    outbox
+        .push(Published::new(
+            1,
+            1,
+            StreamEvent::Price(price("600000.SH", 10.0)),
+        ))
+        .unwrap();
+    (outbox, rx)
+}
+
+/// 逐条补发积压的消息并解析成 JSON。
+async fn drain(
+    outbox: &mut Outbox,
+    rx: &mut tokio::sync::mpsc::Receiver<Message>,
+) -> Vec<serde_json::Value> {
+    let mut out = Vec::new();
+    let tx = outbox.sender().clone();
+    // 第一条是积压前直接入队的消息
+    rx.recv().await.unwrap();
+    while outbox.is_behind() {
+        let permit = tx.reserve().await.unwrap();
+        outbox.flush_one(permit);
+        let Message::Text(text) = rx.recv().await.unwrap() else {
+            panic!("expected a text frame");
+        };
+        out.push(serde_json::from_str(text.as_str()).unwrap());
+    }
+    out
+}
+
+fn published(seq: u64, event: StreamEvent) -> Published {
+    Published::new(seq, seq, event)
+}
+
+fn freshness(symbol: &str) -> Freshness {
+    Freshness {
+        symbol: symbol.to_string(),
+        last_fresh_ts_ms: 0,
+        ts_ms: 0,
+    }
+}
+
+#[tokio::test]
+async fn conflation_keeps_the_highest_seq_and_marks_the_gap() {
+    let (mut outbox, mut rx) = backlogged();
+    let tick = |seq, p| published(seq, StreamEvent::Price(price("600000.SH", p)));
+    outbox.push(tick(3, 10.3)).unwrap();
+    outbox.push(tick(4, 10.4)).unwrap();
+    // resync 补发的旧消息不能覆盖更新的
+    outbox.push(tick(2, 10.2)).unwrap();
+
+    let frames = drain(&mut outbox, &mut rx).await;
+    assert_eq!(frames.len(), 1);
+    assert_eq!(frames[0]["seq"], 4);
+    assert_eq!(frames[0]["price"], 10.4);
+    assert_eq!(frames[0]["conflated_from"], 2);
+}
+
+#[tokio::test]
+async fn alerts_are_never_conflated() {
+    let (mut outbox, mut rx) = backlogged();
+    for seq in 2..=4 {
+        let fired = AlertFired {
+            alert_id: seq,
+            symbol: "600000.SH".to_string(),
+            rule: AlertRule::CrossAbove { level: 10.0 },
+            update: price("600000.SH", 10.0 + seq as f64),
+            ts_ms: 0,
+        };
+        outbox
+            .push(published(seq, StreamEvent::Alert(fired)))
+            .unwrap();
+    }
+
+    let frames = drain(&mut outbox, &mut rx).await;
+    let ids: Vec<_> = frames.iter().map(|f| f["alert_id"].clone()).collect();
+    assert_eq!(ids, [2, 3, 4]);
+    assert!(frames.iter().all(|f| f.get("conflated_from").is_none()));
+}
+
+#[tokio::test]
+async fn stale_and_recovered_share_a_conflation_key() {
+    let (mut outbox, mut rx) = backlogged();
+    outbox
+        .push(published(2, StreamEvent::Stale(freshness("600000.SH"))))
+        .unwrap();
+    outbox
+        .push(published(3, StreamEvent::Recovered(freshness("600000.SH"))))
+        .unwrap();
+    outbox
+        .push(published(4, StreamEvent::Stale(freshness("000001.SZ"))))
+        .unwrap();
+
+    let frames = drain(&mut outbox, &mut rx).await;
+    assert_eq!(frames.len(), 2);
+    assert_eq!(frames[0]["type"], "recovered");
+    assert_eq!(frames[0]["conflated_from"], 2);
+    assert_eq!(frames[1]["symbol"], "000001.SZ");
+    assert!(frames[1].get("conflated_from").is_none());
+}
+
+#[tokio::test]
+async fn backlog_is_flushed_in_seq_order_per_symbol() {
+    let (mut outbox, mut rx) = backlogged();
+    outbox
+        .push(published(2, StreamEvent::Price(price("600000.SH", 10.2))))
+        .unwrap();
+    outbox
+        .push(published(3, StreamEvent::Stale(freshness("600000.SH"))))
+        .unwrap();
+    outbox
+        .push(published(4, StreamEvent::Price(price("600000.SH", 10.4))))
+        .unwrap();
+
+    // seq 2 被 seq 4 替代：先发 stale（带上跳过的起点），再发最新价格
+    let frames = drain(&mut outbox, &mut rx).await;
+    let seqs: Vec<_> = frames.iter().map(|f| f["seq"].clone()).collect();
+    assert_eq!(seqs, [3, 4]);
+    assert_eq!(frames[0]["conflated_from"], 2);
+    assert!(frames[1].get("conflated_from").is_none());
+}